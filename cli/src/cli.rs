@@ -1,10 +1,15 @@
 use clap::ArgMatches;
 
-use crate::commands::{lookup::cli_lookup, ls::cli_ls};
+use crate::commands::{lookup::cli_lookup, ls::cli_ls, resolve::cli_resolve_domain};
+use pknames_core::simulation::{generator::HonestGraphConfig, harness::SimulationConfig};
 use std::path::{Path, PathBuf};
 
 use super::commands::{
     add::cli_add, getinfo::cli_getinfo, pkarr::publish::cli_publish, remove::cli_remove, pkarr::resolve::cli_resolve,
+    pkarr::transport::cli_transport, block::{cli_block, cli_unblock},
+    pin::{cli_pin, cli_unpin}, petname::cli_petname, identity::cli_identity, key::cli_key,
+    bundle::{cli_export, cli_import}, history::{cli_history, cli_undo, cli_revert}, import_list::cli_import_list,
+    doctor::cli_doctor, simulate::{cli_simulate, default_attack_sizes},
 };
 
 /**
@@ -12,6 +17,9 @@ use super::commands::{
  */
 pub fn run_cli() {
     const VERSION: &str = env!("CARGO_PKG_VERSION");
+    let default_simulation = SimulationConfig::default();
+    let default_graph = HonestGraphConfig::default();
+    let (default_sybils, default_chain) = default_attack_sizes();
 
     let cmd = clap::Command::new("pknames")
         .version(VERSION)
@@ -32,7 +40,111 @@ pub fn run_cli() {
                 .num_args(0)
                 .help("Show verbose output."),
         )
+        .arg(
+            clap::Arg::new("identity")
+                .short('i')
+                .long("identity")
+                .required(false)
+                .global(true)
+                .help("Identity to act as. A public key, petname or key prefix shows what this key would see, read-only."),
+        )
         .subcommand(clap::Command::new("getinfo").about("General information."))
+        .subcommand(clap::Command::new("doctor").about("Check secrets, lists and the graph for problems."))
+        .subcommand(
+            clap::Command::new("simulate")
+                .about("Run attacks against generated webs of trust and show how often they change the result.")
+                .arg(
+                    clap::Arg::new("trials")
+                        .long("trials")
+                        .required(false)
+                        .default_value(default_simulation.trials.to_string())
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Number of generated webs of trust per attack."),
+                )
+                .arg(
+                    clap::Arg::new("lists")
+                        .long("lists")
+                        .required(false)
+                        .default_value(default_graph.lists.to_string())
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Number of honest lists besides yours."),
+                )
+                .arg(
+                    clap::Arg::new("follows")
+                        .long("follows")
+                        .required(false)
+                        .default_value(default_graph.follows_per_list.to_string())
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Number of lists every list follows."),
+                )
+                .arg(
+                    clap::Arg::new("rewire")
+                        .long("rewire")
+                        .required(false)
+                        .default_value(default_graph.rewire_probability.to_string())
+                        .value_parser(clap::value_parser!(f64))
+                        .help("Probability that a follow goes to a random list instead of a neighbour. 0 to 1."),
+                )
+                .arg(
+                    clap::Arg::new("attesters")
+                        .long("attesters")
+                        .required(false)
+                        .default_value(default_graph.attesters.to_string())
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Number of honest lists that attribute the right key."),
+                )
+                .arg(
+                    clap::Arg::new("sybils")
+                        .long("sybils")
+                        .required(false)
+                        .default_value(default_sybils.to_string())
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Number of keys in the sybil cluster."),
+                )
+                .arg(
+                    clap::Arg::new("chain")
+                        .long("chain")
+                        .required(false)
+                        .default_value(default_chain.to_string())
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Number of keys in the attribution chain."),
+                )
+                .arg(
+                    clap::Arg::new("seed")
+                        .long("seed")
+                        .required(false)
+                        .default_value(default_simulation.seed.to_string())
+                        .value_parser(clap::value_parser!(u64))
+                        .help("Seed of the random generator. Same seed, same graphs."),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("identity")
+                .about("List identities.")
+                .subcommand(
+                    clap::Command::new("create")
+                        .about("Create a new identity with its own keypair and list.")
+                        .arg(clap::Arg::new("name").required(true).help("Name of the identity, for example org.")),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("key")
+                .about("Secret key related commands.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    clap::Command::new("encrypt")
+                        .about("Encrypt the secret key with a passphrase or change the passphrase."),
+                )
+                .subcommand(
+                    clap::Command::new("rotate")
+                        .about("Move to a new keypair. The old key signs that it moved to the new one and publishes it."),
+                )
+                .subcommand(
+                    clap::Command::new("publish-succession")
+                        .about("Publish the succession from the retired key again, for example if `key rotate` failed to."),
+                ),
+        )
         .subcommand(
             clap::Command::new("lookup")
                 .about("Lookup the pubkey of a domain.")
@@ -50,6 +162,43 @@ pub fn run_cli() {
                         .help("Domain to resolve. For example: example.com."),
                 ),
         )
+        .subcommand(
+            clap::Command::new("resolve")
+                .about("Resolve a domain to its pkarr dns records.")
+                .arg(
+                    clap::Arg::new("domain")
+                        .required(true)
+                        .help("Domain to resolve. For example: example.com or www.example.com."),
+                )
+                .arg(
+                    clap::Arg::new("min-probability")
+                        .long("min-probability")
+                        .required(false)
+                        .value_parser(clap::value_parser!(f32))
+                        .help("Minimum probability of the best public key between 0 and 1."),
+                )
+                .arg(
+                    clap::Arg::new("min-margin")
+                        .long("min-margin")
+                        .required(false)
+                        .value_parser(clap::value_parser!(f32))
+                        .help("Minimum probability lead over the second best public key between 0 and 1."),
+                )
+                .arg(
+                    clap::Arg::new("min-trust")
+                        .long("min-trust")
+                        .required(false)
+                        .value_parser(clap::value_parser!(f32))
+                        .help("Minimum total incoming trust of the best public key."),
+                )
+                .arg(
+                    clap::Arg::new("no-cache")
+                        .long("no-cache")
+                        .required(false)
+                        .num_args(0)
+                        .help("Bypass the local packet cache."),
+                ),
+        )
         .subcommand(
             clap::Command::new("ls")
                 .about("List your follow lists.")
@@ -72,31 +221,132 @@ pub fn run_cli() {
         .subcommand(
             clap::Command::new("add")
                 .about("Add a follow to your list.")
-                .arg(clap::Arg::new("pubkey").required(true).help("Public key to add."))
+                .arg(clap::Arg::new("pubkey").required(true).help("Public key to add. Petnames and unambiguous key prefixes work too."))
                 .arg(
                     clap::Arg::new("trust")
                         .required(true)
-                        .help("Trust value between -1 and 1."),
+                        .help("Trust value between -1 and 1. Negative with a domain: This public key is NOT the domain."),
                 )
                 .arg(
                     clap::Arg::new("domain")
                         .required(false)
                         .help("Attribute a domain to this public key."),
+                )
+                .arg(
+                    clap::Arg::new("expires")
+                        .long("expires")
+                        .required(false)
+                        .help("Ignore the follow after this duration, for example 30d."),
+                )
+                .arg(
+                    clap::Arg::new("scope")
+                        .long("scope")
+                        .required(false)
+                        .num_args(1..)
+                        .help("Only trust this list about these domains, for example *.bitcoin or example.com."),
                 ),
         )
         .subcommand(
             clap::Command::new("remove")
                 .about("Remove a follow from your list.")
-                .arg(clap::Arg::new("pubkey").required(true).help("Public key to remove."))
+                .arg(clap::Arg::new("pubkey").required(true).help("Public key to remove. Petnames and unambiguous key prefixes work too."))
                 .arg(clap::Arg::new("domain").required(false).help("Attributed domain.")),
         )
+        .subcommand(
+            clap::Command::new("block")
+                .about("Never trust anything this public key says, nor predict it for any domain.")
+                .arg(clap::Arg::new("pubkey").required(true).help("Public key to block. Petnames and unambiguous key prefixes work too.")),
+        )
+        .subcommand(
+            clap::Command::new("unblock")
+                .about("Remove a block from your list.")
+                .arg(clap::Arg::new("pubkey").required(true).help("Public key to unblock. Petnames and unambiguous key prefixes work too.")),
+        )
+        .subcommand(clap::Command::new("history").about("Show all changes of your list."))
+        .subcommand(clap::Command::new("undo").about("Undo the latest change of your list."))
+        .subcommand(
+            clap::Command::new("revert")
+                .about("Restore your list to the state before a change. Undoes the change and all later ones.")
+                .arg(
+                    clap::Arg::new("change")
+                        .required(true)
+                        .value_parser(clap::value_parser!(u64))
+                        .help("Change number as shown by history."),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("pin")
+                .about("Always resolve a domain to a public key without consulting the web of trust. Lists pins without arguments.")
+                .arg(clap::Arg::new("domain").required(false).help("Domain to pin."))
+                .arg(clap::Arg::new("pubkey").required(false).help("Pkarr public key uri, petname or unambiguous key prefix."))
+                .arg(
+                    clap::Arg::new("expires")
+                        .long("expires")
+                        .required(false)
+                        .help("Remove the pin after this duration, for example 12h or 7d."),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("unpin")
+                .about("Remove a pin.")
+                .arg(clap::Arg::new("domain").required(true).help("Pinned domain.")),
+        )
+        .subcommand(
+            clap::Command::new("petname")
+                .about("Give a public key a local name. Lists petnames without arguments.")
+                .arg(clap::Arg::new("petname").required(false).help("Name, for example dave-laptop."))
+                .arg(clap::Arg::new("pubkey").required(false).help("Public key or unambiguous key prefix."))
+                .arg(
+                    clap::Arg::new("remove")
+                        .long("remove")
+                        .required(false)
+                        .num_args(0)
+                        .help("Remove the petname."),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("export")
+                .about("Export secrets, lists, pins, petnames and config into one file.")
+                .arg(clap::Arg::new("path").required(true).help("File path of the bundle."))
+                .arg(
+                    clap::Arg::new("encrypt")
+                        .long("encrypt")
+                        .required(false)
+                        .num_args(0)
+                        .help("Encrypt plain secrets in the bundle with a passphrase."),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("import")
+                .about("Import a bundle created with export.")
+                .arg(clap::Arg::new("path").required(true).help("File path of the bundle."))
+                .arg(
+                    clap::Arg::new("overwrite")
+                        .long("overwrite")
+                        .required(false)
+                        .num_args(0)
+                        .help("Replace local files instead of merging. Replaced secrets are kept in <directory>/retired."),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("import-list")
+                .about("Import the follow list of somebody else from a file.")
+                .arg(clap::Arg::new("path").required(true).help("File path of the list.")),
+        )
         .subcommand(
             clap::Command::new("pkarr")
                 .about("Pkarr related commands.")
                 .subcommand(
                     clap::Command::new("resolve")
                         .about("Resolve pkarr dns records.")
-                        .arg(clap::Arg::new("pubkey").required(false).help("Pkarr public key uri.")),
+                        .arg(clap::Arg::new("pubkey").required(false).help("Pkarr public key uri, petname or unambiguous key prefix."))
+                        .arg(
+                            clap::Arg::new("no-cache")
+                                .long("no-cache")
+                                .required(false)
+                                .num_args(0)
+                                .help("Bypass the local packet cache."),
+                        ),
                 )
                 .subcommand(
                     clap::Command::new("publish")
@@ -114,11 +364,33 @@ pub fn run_cli() {
                                 .num_args(0)
                                 .help("File path to the dns records csv file."),
                         ),
+                )
+                .subcommand(
+                    clap::Command::new("transport")
+                        .about("Show or set how pkarr packets are published and resolved.")
+                        .arg(
+                            clap::Arg::new("type")
+                                .required(false)
+                                .value_parser(["dht", "relay", "disk"])
+                                .help("Transport type."),
+                        )
+                        .arg(
+                            clap::Arg::new("values")
+                                .required(false)
+                                .num_args(1..)
+                                .help("Relay urls for relay, directory path for disk."),
+                        ),
                 ),
         );
     let matches = cmd.get_matches();
     let verbose: bool = *matches.get_one("verbose").unwrap();
 
+    // The simulation doesn't use the directory. Don't create it.
+    if let Some(("simulate", matches)) = matches.subcommand() {
+        cli_simulate(matches, verbose);
+        return;
+    };
+
     let directory_path = validate_directory(&matches);
     if let Err(e) = directory_path {
         println!("Directory validation failed: {}", e);
@@ -137,15 +409,60 @@ pub fn run_cli() {
         Some(("lookup", matches)) => {
             cli_lookup(matches, folder_buf, verbose);
         }
+        Some(("resolve", matches)) => {
+            cli_resolve_domain(matches, folder_buf, verbose);
+        }
         Some(("getinfo", matches)) => {
             cli_getinfo(matches, folder_buf, verbose);
         }
+        Some(("doctor", matches)) => {
+            cli_doctor(matches, folder_buf, verbose);
+        }
         Some(("add", matches)) => {
             cli_add(matches, folder_buf, verbose);
         }
         Some(("remove", matches)) => {
             cli_remove(matches, folder_buf, verbose);
         }
+        Some(("block", matches)) => {
+            cli_block(matches, folder_buf, verbose);
+        }
+        Some(("unblock", matches)) => {
+            cli_unblock(matches, folder_buf, verbose);
+        }
+        Some(("history", matches)) => {
+            cli_history(matches, folder_buf, verbose);
+        }
+        Some(("undo", matches)) => {
+            cli_undo(matches, folder_buf, verbose);
+        }
+        Some(("revert", matches)) => {
+            cli_revert(matches, folder_buf, verbose);
+        }
+        Some(("pin", matches)) => {
+            cli_pin(matches, folder_buf, verbose);
+        }
+        Some(("unpin", matches)) => {
+            cli_unpin(matches, folder_buf, verbose);
+        }
+        Some(("petname", matches)) => {
+            cli_petname(matches, folder_buf, verbose);
+        }
+        Some(("identity", matches)) => {
+            cli_identity(matches, folder_buf, verbose);
+        }
+        Some(("export", matches)) => {
+            cli_export(matches, folder_buf, verbose);
+        }
+        Some(("import", matches)) => {
+            cli_import(matches, folder_buf, verbose);
+        }
+        Some(("import-list", matches)) => {
+            cli_import_list(matches, folder_buf, verbose);
+        }
+        Some(("key", matches)) => {
+            cli_key(matches, folder_buf, verbose);
+        }
         Some(("pkarr", matches)) => match matches.subcommand() {
             Some(("resolve", matches)) => {
                 cli_resolve(matches, folder_buf, verbose);
@@ -153,6 +470,9 @@ pub fn run_cli() {
             Some(("publish", matches)) => {
                 cli_publish(matches, folder_buf, verbose);
             }
+            Some(("transport", matches)) => {
+                cli_transport(matches, folder_buf, verbose);
+            }
             _ => unimplemented!("command not implemented"),
         },
         _ => {
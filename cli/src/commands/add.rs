@@ -1,31 +1,44 @@
 use std::path::PathBuf;
 use clap::ArgMatches;
-use pknames_core::config_directory::{dirs::main_directory::MainDirectory, follow::Follow};
+use pknames_core::{config_directory::follow::Follow, time::{parse_duration, unix_now}};
+
+use super::{names::{read_key_names, resolve_key_arg}, identity::{open_directory, require_writable}};
 
 
 pub fn cli_add(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
-    let pubkey: &String = matches.get_one("pubkey").unwrap();
+    let raw_pubkey: &String = matches.get_one("pubkey").unwrap();
     let raw_trust: &String = matches.get_one("trust").unwrap();
     let domain = matches.get_one::<String>("domain").map(|s|s.as_str());
     let trust: f32 = raw_trust.parse().expect("trust should be a valid number.");
+    let scope: Vec<String> = matches.get_many::<String>("scope").unwrap_or_default().cloned().collect();
+    let now = unix_now();
+    let expires = match matches.get_one::<String>("expires") {
+        None => None,
+        Some(raw_expires) => {
+            let duration_result = parse_duration(raw_expires);
+            if let Err(e) = duration_result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            };
+            Some(now + duration_result.unwrap())
+        }
+    };
 
-    println!("Add {} {} {:?}", pubkey, trust, domain);
-
-    let dir = MainDirectory::new(directory);
+    let dir = open_directory(matches, directory);
     dir.create_if_it_does_not_exist().unwrap();
+    require_writable(&dir);
 
-    let mut me_list = dir.static_lists_dir.read_list(&dir.get_public_key_uri()).expect("Me list should exist.");
-    let new_follow = Follow::new(pubkey, trust, domain);
-    if me_list.follows.contains(&new_follow) {
-        let index = me_list.follows.iter().position(|x| *x == new_follow).unwrap();
-        me_list.follows.remove(index);
-    }
-    me_list.follows.push(new_follow);
+    let names = read_key_names(&dir);
+    let pubkey = resolve_key_arg(&names, raw_pubkey);
+    println!("Add {} {} {:?}", names.label(&pubkey), trust, domain);
 
-    let result = dir.static_lists_dir.write_list(&dir.get_public_key_uri(), me_list);
+    let new_follow = Follow::new_with_timestamps(pubkey, trust, domain, Some(now), expires).with_scope(scope);
+    let result = dir.upsert_follow(new_follow);
     match result {
         Ok(_) => println!("Success!"),
-        Err(e) => eprintln!("Failed to write list: {}", e)
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
     };
-
-}
\ No newline at end of file
+}
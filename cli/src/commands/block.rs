@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pknames_core::config_directory::list_edit::{ChangeOutcome, ListEditError};
+
+use super::{names::{read_key_names, resolve_key_arg}, identity::{open_directory, require_writable}};
+
+
+pub fn cli_block(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let raw_pubkey: &String = matches.get_one("pubkey").unwrap();
+
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+    require_writable(&dir);
+
+    let names = read_key_names(&dir);
+    let pubkey = &resolve_key_arg(&names, raw_pubkey);
+    println!("Block {}", names.label(pubkey));
+
+    let result = dir.block(pubkey);
+    match result {
+        Ok(ChangeOutcome::Unchanged) => println!("Already blocked."),
+        Ok(_) => println!("Success!"),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    };
+}
+
+
+pub fn cli_unblock(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let raw_pubkey: &String = matches.get_one("pubkey").unwrap();
+
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+    require_writable(&dir);
+
+    let names = read_key_names(&dir);
+    let pubkey = &resolve_key_arg(&names, raw_pubkey);
+    println!("Unblock {}", names.label(pubkey));
+
+    let result = dir.unblock(pubkey);
+    match result {
+        Ok(_) => println!("Success!"),
+        Err(ListEditError::BlockNotFound(_)) => eprintln!("Block not found in my list."),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    };
+}
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+use clap::ArgMatches;
+use pknames_core::config_directory::{bundle::{Bundle, ImportMode}, dirs::main_directory::MainDirectory};
+
+use super::key::prompt_new_passphrase;
+
+
+fn parse_bundle_path(matches: &ArgMatches) -> PathBuf {
+    let unexpanded_path: &String = matches.get_one("path").unwrap();
+    let path_str: String = shellexpand::full(unexpanded_path).expect("Valid shell path.").into();
+    PathBuf::from(Path::new(&path_str))
+}
+
+/**
+ * Writes all secrets, lists, pins, petnames and the config of the directory into one file.
+ */
+pub fn cli_export(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let path = parse_bundle_path(matches);
+    let encrypt: bool = *matches.get_one("encrypt").unwrap();
+    let dir = MainDirectory::new(directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let bundle_result = Bundle::from_directory(&dir);
+    if let Err(e) = bundle_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let mut bundle = bundle_result.unwrap();
+    if encrypt && !bundle.plain_secrets().is_empty() {
+        let passphrase = prompt_new_passphrase();
+        if let Err(e) = bundle.encrypt_secrets(&passphrase) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        };
+    };
+
+    if let Err(e) = bundle.write(&path) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    println!("Exported {} files to {}", bundle.files.len(), path.to_str().unwrap());
+    for file in bundle.files.keys() {
+        println!("- {}", file);
+    };
+    let plain_secrets = bundle.plain_secrets();
+    if !plain_secrets.is_empty() {
+        println!();
+        let names: Vec<&str> = plain_secrets.iter().map(|name| name.as_str()).collect();
+        println!("⚠ Not encrypted: {}. Keep the file safe or export with --encrypt.", names.join(", "));
+    };
+}
+
+/**
+ * Verifies a bundle and merges it into the directory or overwrites the directory with it.
+ */
+pub fn cli_import(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let path = parse_bundle_path(matches);
+    let overwrite: bool = *matches.get_one("overwrite").unwrap();
+    let mode = if overwrite { ImportMode::Overwrite } else { ImportMode::Merge };
+    // Don't create the directory content first. A new random secret would block the secret of the bundle.
+    let dir = MainDirectory::new(directory);
+
+    let bundle_result = Bundle::from_path(&path);
+    if let Err(e) = bundle_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let bundle = bundle_result.unwrap();
+
+    let report_result = bundle.import_into(&dir, mode);
+    if let Err(e) = report_result {
+        eprintln!("Import failed. {}", e);
+        std::process::exit(1);
+    };
+    println!("Imported {}", path.to_str().unwrap());
+    for (file, action) in report_result.unwrap() {
+        println!("- {} {}", file, action);
+    };
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pknames_core::config_directory::doctor::{diagnose, Severity};
+
+use super::identity::open_directory;
+
+
+/**
+ * Checks secrets, lists and the graph. Exits with 1 if an error is found.
+ */
+pub fn cli_doctor(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let dir = open_directory(matches, directory);
+    if !dir.exists() {
+        eprintln!("Directory \"{}\" does not exist.", dir.path.to_str().unwrap());
+        std::process::exit(1);
+    };
+
+    let findings = diagnose(&dir);
+    if findings.is_empty() {
+        println!("No problems found.");
+        return;
+    };
+    for finding in findings.iter() {
+        println!("{}", finding);
+    };
+    let errors = findings.iter().filter(|finding| finding.severity == Severity::Error).count();
+    println!();
+    println!("{} errors, {} warnings.", errors, findings.len() - errors);
+    if errors > 0 {
+        std::process::exit(1);
+    };
+}
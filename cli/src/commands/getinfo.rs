@@ -1,17 +1,25 @@
 use std::path::PathBuf;
 use clap::ArgMatches;
-use pknames_core::config_directory::dirs::main_directory::MainDirectory;
 
+use super::{names::read_key_names, identity::open_directory};
 
-pub fn cli_getinfo(_matches: &ArgMatches, folder_path: PathBuf, _verbose: bool) {
-    let config = MainDirectory::new(folder_path);
+
+pub fn cli_getinfo(matches: &ArgMatches, folder_path: PathBuf, _verbose: bool) {
+    let config = open_directory(matches, folder_path);
     config.create_if_it_does_not_exist().unwrap();
 
     println!("Your public key: {}", config.get_public_key_uri());
+    println!("Perspective: {}", config.perspective);
     println!();
 
     println!("Your follows");
-    let me_list = config.static_lists_dir.read_list(&config.get_public_key_uri()).expect("Me follow list should exist.");
-    println!("{}", me_list);
+    let me_list_result = config.static_lists_dir.read_list(&config.get_public_key());
+    if let Err(e) = me_list_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let me_list = me_list_result.unwrap();
+    let names = read_key_names(&config);
+    println!("{}", me_list.format_with_labels(|pubkey| names.label(pubkey)));
 
 }
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pknames_core::config_directory::{journal::{JournalEntry, ListDiff}, petnames::KeyNames};
+
+use super::{names::read_key_names, identity::{open_directory, require_writable}};
+
+
+fn print_entry(entry: &JournalEntry, names: &KeyNames) {
+    let time = chrono::DateTime::from_timestamp(entry.time as i64, 0).unwrap();
+    println!("#{} {} {}", entry.id, time.format("%Y-%m-%d %H:%M UTC"), entry.reason);
+    for diff in entry.diff() {
+        let line = match diff {
+            ListDiff::Added(follow) => format!("+ {}", follow.format_with_label(&names.label(follow.pubkey()))),
+            ListDiff::Removed(follow) => format!("- {}", follow.format_with_label(&names.label(follow.pubkey()))),
+            ListDiff::Changed { before, after } => format!("~ {} (was {:.2})", after.format_with_label(&names.label(after.pubkey())), before.weight()),
+            ListDiff::Blocked(pubkey) => format!("+ ⛔ {}", names.label(&pubkey)),
+            ListDiff::Unblocked(pubkey) => format!("- ⛔ {}", names.label(&pubkey)),
+        };
+        println!("    {}", line);
+    };
+}
+
+pub fn cli_history(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let dir = open_directory(matches, directory);
+    let history_result = dir.read_history();
+    if let Err(e) = history_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let history = history_result.unwrap();
+    if history.is_empty() {
+        println!("No changes yet.");
+        return;
+    };
+    let names = read_key_names(&dir);
+    for entry in history.iter() {
+        print_entry(entry, &names);
+    };
+}
+
+pub fn cli_undo(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let dir = open_directory(matches, directory);
+    require_writable(&dir);
+    let entry_result = dir.undo();
+    if let Err(e) = entry_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let names = read_key_names(&dir);
+    print_entry(&entry_result.unwrap(), &names);
+}
+
+pub fn cli_revert(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let id: &u64 = matches.get_one("change").unwrap();
+    let dir = open_directory(matches, directory);
+    require_writable(&dir);
+    let entry_result = dir.revert(*id);
+    if let Err(e) = entry_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let names = read_key_names(&dir);
+    print_entry(&entry_result.unwrap(), &names);
+}
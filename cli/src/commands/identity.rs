@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pknames_core::config_directory::{dirs::main_directory::MainDirectory, perspective::Perspective};
+
+use super::names::read_key_names;
+
+
+/**
+ * Opens the directory from the perspective given with the global `--identity` flag.
+ * The flag accepts identity names and, for a read-only perspective, public keys, petnames and key prefixes. Exits on failure.
+ */
+pub fn open_directory(matches: &ArgMatches, directory: PathBuf) -> MainDirectory {
+    let identity = matches.get_one::<String>("identity");
+    if identity.is_none() {
+        return MainDirectory::new(directory);
+    };
+    let identity = identity.unwrap();
+
+    let default_dir = MainDirectory::new(directory.clone());
+    if default_dir.list_identities().contains(identity) {
+        return MainDirectory::new_with_perspective(directory, Perspective::Identity(identity.clone()));
+    };
+
+    default_dir.create_if_it_does_not_exist().unwrap();
+    let names = read_key_names(&default_dir);
+    let pubkey_result = names.resolve_input(identity);
+    if let Err(e) = pubkey_result {
+        eprintln!("\"{}\" is not an identity. {}", identity, e);
+        std::process::exit(1);
+    };
+    MainDirectory::new_with_perspective(directory, Perspective::PublicKey(pubkey_result.unwrap()))
+}
+
+/**
+ * Exits if the perspective has no secret key and therefore no list of its own.
+ */
+pub fn require_writable(dir: &MainDirectory) {
+    if dir.is_read_only() {
+        eprintln!("The perspective is only the {}. Use an identity to change lists or sign packets.", dir.perspective);
+        std::process::exit(1);
+    };
+}
+
+
+pub fn cli_identity(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    match matches.subcommand() {
+        Some(("create", matches)) => cli_identity_create(matches, directory),
+        _ => cli_identity_list(matches, directory),
+    };
+}
+
+fn cli_identity_list(matches: &ArgMatches, directory: PathBuf) {
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    for name in dir.list_identities() {
+        let identity = MainDirectory::new_with_perspective(dir.path.clone(), Perspective::Identity(name.clone()));
+        let current = if identity.perspective == dir.perspective { "*" } else { " " };
+        println!("{} {} {}", current, name, identity.get_public_key());
+    };
+    if dir.is_read_only() {
+        println!("* {}", dir.perspective);
+    };
+}
+
+fn cli_identity_create(matches: &ArgMatches, directory: PathBuf) {
+    let name: &String = matches.get_one("name").unwrap();
+    let dir = MainDirectory::new(directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let keypair_result = dir.create_identity(name);
+    if let Err(e) = keypair_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    println!("Created identity {} {}", name, keypair_result.unwrap().to_uri_string());
+    println!("Use it with --identity {}", name);
+}
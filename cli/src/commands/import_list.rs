@@ -0,0 +1,26 @@
+use std::path::{Path, PathBuf};
+use clap::ArgMatches;
+
+use super::{names::read_key_names, identity::open_directory};
+
+
+/**
+ * Copies the list of somebody else into `static_lists/imported` and records where it came from.
+ */
+pub fn cli_import_list(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let unexpanded_path: &String = matches.get_one("path").unwrap();
+    let path_str: String = shellexpand::full(unexpanded_path).expect("Valid shell path.").into();
+    let path = PathBuf::from(Path::new(&path_str));
+
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let list_result = dir.import_list(&path);
+    if let Err(e) = list_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let list = list_result.unwrap();
+    let names = read_key_names(&dir);
+    println!("Imported list {} with {} follows.", names.label(&list.pubkey), list.follows.len());
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pkarr::Keypair;
+use pknames_core::config_directory::{dirs::main_directory::MainDirectory, follow_list::{FollowList, lists_following_retired_keys}};
+
+use super::{identity::{open_directory, require_writable}, names::read_key_names, pkarr::resolve::create_resolve_transport};
+
+
+/**
+ * Reads the keypair of the perspective. Asks for the passphrase if the secret is encrypted and `PKNAMES_PASSPHRASE` is not set. Exits on failure.
+ */
+pub fn unlock_keypair(dir: &mut MainDirectory) -> Keypair {
+    require_writable(dir);
+    let secret_result = dir.read_secret();
+    if let Err(e) = secret_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    if secret_result.unwrap().is_encrypted() && dir.passphrase.is_none() {
+        dir.passphrase = Some(prompt_passphrase(&format!("Passphrase for {}: ", dir.perspective)));
+    };
+
+    let keypair_result = dir.read_keypair();
+    if let Err(e) = keypair_result {
+        eprintln!("Failed to unlock the secret key. {}", e);
+        std::process::exit(1);
+    };
+    keypair_result.unwrap()
+}
+
+fn prompt_passphrase(prompt: &str) -> String {
+    let passphrase_result = rpassword::prompt_password(prompt);
+    if let Err(e) = passphrase_result {
+        eprintln!("Failed to read the passphrase. {}", e);
+        std::process::exit(1);
+    };
+    passphrase_result.unwrap()
+}
+
+/**
+ * New passphrase asked twice on the terminal.
+ */
+pub fn prompt_new_passphrase() -> String {
+    let passphrase = prompt_passphrase("New passphrase: ");
+    if passphrase != prompt_passphrase("Repeat passphrase: ") {
+        eprintln!("Passphrases don't match.");
+        std::process::exit(1);
+    };
+    passphrase
+}
+
+
+pub fn cli_key(matches: &ArgMatches, directory: PathBuf, verbose: bool) {
+    match matches.subcommand() {
+        Some(("encrypt", matches)) => cli_key_encrypt(matches, directory, verbose),
+        Some(("rotate", matches)) => cli_key_rotate(matches, directory, verbose),
+        Some(("publish-succession", matches)) => cli_key_publish_succession(matches, directory, verbose),
+        _ => unreachable!("clap requires a key subcommand"),
+    };
+}
+
+/**
+ * Encrypts a plain secret with a passphrase or changes the passphrase of an encrypted one.
+ */
+fn cli_key_encrypt(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let mut dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+    let was_encrypted = dir.read_secret().map(|secret| secret.is_encrypted()).unwrap_or(false);
+    unlock_keypair(&mut dir);
+
+    // Migrating plain secrets can take the passphrase from the env variable, for example in scripts.
+    let passphrase = match (&dir.passphrase, was_encrypted) {
+        (Some(passphrase), false) => passphrase.clone(),
+        _ => prompt_new_passphrase(),
+    };
+    if let Err(e) = dir.encrypt_secret(&passphrase) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    if was_encrypted {
+        println!("Changed the passphrase of {}.", dir.perspective);
+    } else {
+        println!("Encrypted the secret key of {}.", dir.perspective);
+    };
+}
+
+/**
+ * Moves the identity to a new keypair. The old key signs a succession statement in its list and publishes it.
+ */
+fn cli_key_rotate(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let mut dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+    let old_pubkey = dir.get_public_key();
+    unlock_keypair(&mut dir);
+
+    let keypair_result = dir.rotate_keypair();
+    if let Err(e) = keypair_result {
+        eprintln!("Failed to rotate the key. {}", e);
+        std::process::exit(1);
+    };
+    let new_keypair = keypair_result.unwrap();
+    println!("Rotated {}", dir.perspective);
+    println!("Old key: {}", old_pubkey);
+    println!("New key: {}", new_keypair.to_uri_string());
+    println!("The old list now points to the new key. Republish your pkarr records with `pkarr publish`.");
+    let transport = create_resolve_transport(&dir, false);
+    match dir.publish_succession(transport.as_ref()) {
+        Ok(_) => println!("Published the succession from the old key."),
+        Err(e) => println!("⚠ Failed to publish the succession from the old key. {} Retry with `key publish-succession`.", e),
+    };
+    print_retired_key_warnings(&dir);
+}
+
+/**
+ * Publishes the succession of the key the identity was rotated from again, for example after `key rotate` was offline.
+ */
+fn cli_key_publish_succession(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let mut dir = open_directory(matches, directory);
+    // The retired secret shares the passphrase of the current one.
+    unlock_keypair(&mut dir);
+    let transport = create_resolve_transport(&dir, false);
+    match dir.publish_succession(transport.as_ref()) {
+        Ok(retired) => println!("Published the succession from {} to {}.", retired, dir.get_public_key()),
+        Err(e) => {
+            eprintln!("Failed to publish the succession. {}", e);
+            std::process::exit(1);
+        },
+    };
+}
+
+/**
+ * Warns about lists that still follow a key that moved to a successor.
+ */
+pub fn print_retired_key_warnings(dir: &MainDirectory) {
+    let loaded = dir.static_lists_dir.load_lists(&dir.authored_pubkeys()).unwrap_or_default();
+    let lists: Vec<FollowList> = loaded.lists.into_iter().map(|stored| stored.list).collect();
+    let stale = lists_following_retired_keys(&lists);
+    if stale.is_empty() && loaded.errors.is_empty() {
+        return;
+    };
+    let names = read_key_names(dir);
+    println!();
+    for (list, retired_pubkey) in stale {
+        println!("⚠ List {} still follows the retired key {}.", names.label(&list.pubkey), names.label(retired_pubkey));
+    };
+    if !loaded.errors.is_empty() {
+        println!("⚠ {} list files can't be read and were not checked for retired keys. Run `doctor` for details.", loaded.errors.len());
+    };
+}
@@ -1,26 +1,47 @@
 use std::path::PathBuf;
 
 use clap::ArgMatches;
-use pknames_core::{prediction::{predictor::WotPredictor, graph::WotGraph}, pruning::prune::prune_graph, config_directory::dirs::main_directory::MainDirectory};
+use pknames_core::{prediction::predictor::{WotPredictor, WotDecision, DecisionThresholds}, pruning::prune::prune_graph};
 
 use crate::visualization::visualization::visualize_graph;
 
+use super::{names::read_perspective_key_names, identity::open_directory};
+
 
 
 pub fn cli_lookup(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
     let domain: &String = matches.get_one("domain").unwrap();
     println!("Lookup {}", domain);
 
-    let dir = MainDirectory::new(directory);
+    let dir = open_directory(matches, directory);
     dir.create_if_it_does_not_exist().unwrap();
 
-    let lists = dir.static_lists_dir.read_valid_lists();
-    if lists.len() == 0 {
-        eprintln!("No lists found in \"{}\".", dir.static_lists_dir.path.to_str().unwrap());
+    let pins_result = dir.read_perspective_pins();
+    if let Err(e) = pins_result {
+        eprintln!("{}", e);
         std::process::exit(1);
     };
+    let pins = pins_result.unwrap();
+    let names = read_perspective_key_names(&dir);
+    if let Some(pin) = pins.get(domain) {
+        println!("{}", pin.format_with_label(&names.label(&pin.pubkey)));
+        println!("Web of trust not consulted. Use `unpin {}` to remove the pin.", pin.domain);
+        return;
+    };
 
-    let graph: WotGraph = lists.into();
+    let graph_result = dir.read_graph_and_lists();
+    if let Err(e) = graph_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let (graph, loaded) = graph_result.unwrap();
+    for problem in loaded.problems() {
+        eprintln!("⚠ {}", problem);
+    };
+    if loaded.lists.is_empty() {
+        eprintln!("No lists found in \"{}\".", dir.static_lists_dir.path.to_str().unwrap());
+        std::process::exit(1);
+    };
 
     if !graph.contains_attribution(domain) {
         eprintln!("Graph does not contain the domain.");
@@ -36,10 +57,18 @@ pub fn cli_lookup(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
     for class in graph.get_classes() {
         let val = result.get_value(&class.pubkey);
         if val.is_some() {
-            println!("- {} {:.2}%", class.pubkey, val.unwrap()*100.0);
+            println!("- {} {:.2}%", names.label(&class.pubkey), val.unwrap()*100.0);
         };
     };
 
+    let config = dir.read_config().unwrap_or_default();
+    let thresholds: DecisionThresholds = (&config.resolve).into();
+    match result.decide(&thresholds) {
+        WotDecision::Resolved(pubkey) => println!("Decision: {}", names.label(&pubkey)),
+        WotDecision::Ambiguous(_) => println!("Decision: ambiguous"),
+        WotDecision::Untrusted => println!("Decision: untrusted"),
+    };
+
     let show_gui: bool = *matches.get_one("ui").unwrap();
     if show_gui {
         visualize_graph(graph, "Lookup domain", Some(&dir.get_public_key_uri()), Some(result));
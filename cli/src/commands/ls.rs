@@ -1,38 +1,59 @@
 use std::path::PathBuf;
 use clap::ArgMatches;
-use pknames_core::{pruning::prune::prune_graph, config_directory::dirs::main_directory::MainDirectory, prediction::graph::WotGraph};
+use pknames_core::{config_directory::{dirs::static_lists_directory::last_good_copy_path, provenance::ListSource}, pruning::prune::prune_graph};
 
 use crate::visualization::visualization::visualize_graph;
 
+use super::{names::read_key_names, identity::open_directory, key::print_retired_key_warnings};
+
 
 
 pub fn cli_ls(matches: &ArgMatches, folder_path: PathBuf, _verbose: bool) {
-    let dir = MainDirectory::new(folder_path);
+    let dir = open_directory(matches, folder_path);
     dir.create_if_it_does_not_exist().unwrap();
 
-    let lists = dir.static_lists_dir.read_lists().expect("Readable directory");
+    let lists = dir.static_lists_dir.read_stored_lists(&dir.authored_pubkeys()).expect("Readable directory");
     if lists.len() == 0 {
         eprintln!("No lists found in \"{}\".", dir.static_lists_dir.path.to_str().unwrap());
         std::process::exit(1);
     };
 
-    for list in lists.iter() {
-        match list {
-            Ok(list) => println!("{}", list),
+    let names = read_key_names(&dir);
+    let lists_config = dir.read_config().unwrap_or_default().lists;
+    for stored in lists.iter() {
+        match stored {
+            Ok(stored) => {
+                println!("{}", stored.list.format_with_labels(|pubkey| names.label(pubkey)));
+                if stored.provenance.source != ListSource::Authored {
+                    let note = if lists_config.accepts(&stored.provenance) { "" } else { " (not used, see config)" };
+                    println!("Source: {}{}", stored.provenance, note);
+                };
+            },
             Err(e) => {
                 println!("{}", e);
             }
         }
         println!("");
     }
+    for (path, e) in dir.static_lists_dir.corrupted_lists() {
+        let backup = last_good_copy_path(&path);
+        if backup.exists() {
+            println!("⚠ {} Using the last good copy. Restore it with `cp {} {}`.", e, backup.to_str().unwrap(), path.to_str().unwrap());
+        };
+    };
+    print_retired_key_warnings(&dir);
 
     let default_value = "".to_string();
     let domain: &String = matches.get_one("domain").unwrap_or(&default_value);
 
     let show_gui: bool = *matches.get_one("ui").unwrap();
     if show_gui {
-        let lists = dir.static_lists_dir.read_valid_lists();
-        let mut graph: WotGraph = lists.into();
+        let graph_result = dir.read_graph();
+        if let Err(e) = graph_result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        };
+        let mut graph = graph_result.unwrap();
         if domain.len() > 0 {
             println!("Prune graph for domain {}", domain);
             graph = prune_graph(graph, &dir.get_public_key_uri(), domain);
@@ -3,4 +3,16 @@ pub mod lookup;
 pub mod ls;
 pub mod getinfo;
 pub mod add;
-pub mod remove;
\ No newline at end of file
+pub mod remove;
+pub mod resolve;
+pub mod block;
+pub mod pin;
+pub mod names;
+pub mod petname;
+pub mod identity;
+pub mod key;
+pub mod bundle;
+pub mod history;
+pub mod import_list;
+pub mod doctor;
+pub mod simulate;
@@ -0,0 +1,38 @@
+use pknames_core::{config_directory::{dirs::main_directory::MainDirectory, petnames::KeyNames}, public_key::PublicKey};
+
+
+/**
+ * Reads petnames and nicknames of the directory. Exits on failure.
+ */
+pub fn read_key_names(dir: &MainDirectory) -> KeyNames {
+    let names_result = dir.read_key_names();
+    if let Err(e) = names_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    names_result.unwrap()
+}
+
+/**
+ * Names from the view of the perspective, see `MainDirectory::read_perspective_key_names`. Exits on failure.
+ */
+pub fn read_perspective_key_names(dir: &MainDirectory) -> KeyNames {
+    let names_result = dir.read_perspective_key_names();
+    if let Err(e) = names_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    names_result.unwrap()
+}
+
+/**
+ * Turns a petname, public key or unambiguous key prefix into a pubkey. Exits on failure.
+ */
+pub fn resolve_key_arg(names: &KeyNames, input: &str) -> PublicKey {
+    let pubkey_result = names.resolve_input(input);
+    if let Err(e) = pubkey_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    pubkey_result.unwrap()
+}
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+
+use super::{names::{read_key_names, resolve_key_arg}, identity::open_directory};
+
+
+pub fn cli_petname(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let names = read_key_names(&dir);
+    let petnames = &names.petnames;
+
+    let petname = matches.get_one::<String>("petname");
+    if petname.is_none() {
+        if petnames.petnames.is_empty() {
+            println!("No petnames.");
+        };
+        for (petname, pubkey) in petnames.petnames.iter() {
+            println!("- {} {}", petname, pubkey);
+        };
+        return;
+    };
+    let petname = petname.unwrap();
+
+    let remove: bool = *matches.get_one("remove").unwrap();
+    let result = if remove {
+        println!("Remove petname {}", petname);
+        dir.update_petnames(|petnames| Ok(petnames.remove(petname).is_some()))
+    } else {
+        let pubkey = matches.get_one::<String>("pubkey");
+        if pubkey.is_none() {
+            match petnames.get_pubkey(petname) {
+                Some(pubkey) => println!("{} {}", petname, pubkey),
+                None => eprintln!("Petname not found."),
+            };
+            return;
+        };
+        let pubkey = resolve_key_arg(&names, pubkey.unwrap());
+        println!("Name {} {}", pubkey, petname);
+        dir.update_petnames(|petnames| petnames.set(petname, &pubkey).map(|_| true))
+    };
+
+    match result {
+        Ok(true) => println!("Success!"),
+        Ok(false) => eprintln!("Petname not found."),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    };
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pknames_core::{config_directory::pins::Pin, time::{parse_duration, unix_now}};
+
+use super::{names::{read_key_names, resolve_key_arg}, identity::open_directory};
+
+
+pub fn cli_pin(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let pins_result = dir.read_pins();
+    if let Err(e) = pins_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let pins = pins_result.unwrap();
+    let names = read_key_names(&dir);
+
+    let domain = matches.get_one::<String>("domain");
+    if domain.is_none() {
+        if pins.pins.is_empty() {
+            println!("No pins.");
+        };
+        for pin in pins.pins.iter() {
+            println!("- {}", pin.format_with_label(&names.label(&pin.pubkey)));
+        };
+        return;
+    };
+    let domain = domain.unwrap();
+
+    let pubkey = matches.get_one::<String>("pubkey");
+    if pubkey.is_none() {
+        eprintln!("Public key missing. Usage: pin <domain> <pubkey>.");
+        std::process::exit(1);
+    };
+    let pubkey = resolve_key_arg(&names, pubkey.unwrap());
+
+    let expires = match matches.get_one::<String>("expires") {
+        None => None,
+        Some(raw) => {
+            let duration_result = parse_duration(raw);
+            if let Err(e) = duration_result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            };
+            Some(unix_now() + duration_result.unwrap())
+        }
+    };
+
+    let pin = Pin::new(domain, &pubkey, expires);
+    println!("Pin {}", pin.format_with_label(&names.label(&pubkey)));
+
+    let result = dir.update_pins(|pins| {
+        pins.pin(pin);
+        Ok(())
+    });
+    match result {
+        Ok(_) => println!("Success!"),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    };
+}
+
+
+pub fn cli_unpin(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let domain: &String = matches.get_one("domain").unwrap();
+    println!("Unpin {}", domain);
+
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let result = dir.update_pins(|pins| Ok(pins.unpin(domain).is_some()));
+    match result {
+        Ok(true) => println!("Success!"),
+        Ok(false) => eprintln!("Pin not found."),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    };
+}
+
@@ -2,3 +2,4 @@ pub mod pkarr_records;
 pub mod pkarr_publisher;
 pub mod publish;
 pub mod resolve;
+pub mod transport;
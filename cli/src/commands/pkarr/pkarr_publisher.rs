@@ -1,32 +1,34 @@
 use std::{sync::mpsc::channel, io::{self, Write}};
 use chrono;
-use pkarr::{PkarrClient, SignedPacket};
+use pkarr::SignedPacket;
+use pknames_core::transport::pkarr_transport::PkarrTransport;
 
 pub struct PkarrPublisher {
-    pub packet: SignedPacket
+    pub packet: SignedPacket,
+    transport: Box<dyn PkarrTransport>
 }
 
 /**
  * Continuously publishes dns records to pkarr
  */
 impl PkarrPublisher {
-    pub fn new(packet: SignedPacket) -> Self {
+    pub fn new(packet: SignedPacket, transport: Box<dyn PkarrTransport>) -> Self {
         PkarrPublisher {
-            packet
+            packet,
+            transport
         }
     }
     
     pub fn run_once(&self) -> () {
-        let client = PkarrClient::new();
         print!("Hang on...");
         io::stdout().flush().unwrap();
-        let result = client.publish(&self.packet);
+        let result = self.transport.publish(&self.packet);
         print!("\r");
         // std::io::stdout().flush();
         if result.is_ok() {
             println!("{} Successfully announced.", chrono::offset::Local::now());
         } else {
-            println!("{} Error {}", chrono::offset::Local::now(), result.unwrap_err().to_string());
+            println!("{} Error {}", chrono::offset::Local::now(), result.unwrap_err());
         };
 
     }
@@ -53,6 +55,7 @@ impl PkarrPublisher {
 mod tests {
     use pkarr::Keypair;
     use simple_dns::Packet;
+    use pknames_core::transport::memory_transport::MemoryTransport;
     use crate::commands::pkarr::pkarr_records::PkarrRecords;
     use super::PkarrPublisher;
 
@@ -80,23 +83,26 @@ mod tests {
         let parsed = PkarrRecords::from_conf(csv).unwrap();
         let packet = parsed.to_signed_packet(&keypair).unwrap();
         dbg!(&packet);
-        let publisher = PkarrPublisher::new(packet);
+        let publisher = PkarrPublisher::new(packet, Box::new(MemoryTransport::new()));
         publisher.run_once();
+        let resolved = publisher.transport.resolve_most_recent(&keypair.public_key()).unwrap();
+        assert!(resolved.is_some());
     }
 
     #[test]
     fn run() {
+        // Columns are separated by spaces like in `run_once`. `PkarrRecords::from_conf` reads commas as part of the value.
         let csv = "
         # Type, Domain, Data, TTL
-        A, pknames.p2p, 127.0.0.1, 10
-        TXT, test, helloworld,
-        TXT, test, helloworld, a
+        A pknames.p2p 127.0.0.1 10
+        TXT test helloworld
+        TXT test helloworld a
         ";
 
         let keypair = get_test_keypair();
         let parsed = PkarrRecords::from_conf(csv).unwrap();
         let packet = parsed.to_signed_packet(&keypair).unwrap();
-        let publisher = PkarrPublisher::new(packet);
+        let publisher = PkarrPublisher::new(packet, Box::new(MemoryTransport::new()));
         // publisher.run(Duration::from_secs(60*60))
     }
 }
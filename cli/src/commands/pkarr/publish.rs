@@ -1,9 +1,9 @@
 use std::path::{PathBuf, Path};
 use chrono::Duration;
 use clap::ArgMatches;
-use pknames_core::config_directory::dirs::main_directory::MainDirectory;
 
 use super::{pkarr_records::PkarrRecords, pkarr_publisher::PkarrPublisher};
+use crate::commands::{identity::open_directory, key::unlock_keypair};
 
 
 
@@ -28,9 +28,15 @@ pub fn cli_publish(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
     };
     let records = records_result.unwrap();
 
-    let dir = MainDirectory::new(directory);
+    let mut dir = open_directory(matches, directory);
     dir.create_if_it_does_not_exist().unwrap();
-    let keypair = dir.read_or_create_keypair();
+    let keypair = unlock_keypair(&mut dir);
+    let config_result = dir.read_config();
+    if let Err(e) = config_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let config = config_result.unwrap();
 
     let packet_result = records.to_signed_packet(&keypair);
 
@@ -43,6 +49,7 @@ pub fn cli_publish(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
     for record in records.records.iter() {
         println!("- {}", record);
     }
+    println!("Transport: {}", config.transport);
     if once {
         println!("Announce once.");
     } else {
@@ -51,7 +58,7 @@ pub fn cli_publish(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
     println!();
 
     let packet = packet_result.unwrap();
-    let publisher = PkarrPublisher::new(packet);
+    let publisher = PkarrPublisher::new(packet, config.transport.create_transport());
 
     if once {
         publisher.run_once();
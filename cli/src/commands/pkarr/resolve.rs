@@ -1,17 +1,17 @@
 use std::path::PathBuf;
 use chrono::{DateTime, Utc};
 use clap::ArgMatches;
-use pkarr::{PkarrClient, PublicKey};
-use pknames_core::config_directory::dirs::main_directory::MainDirectory;
+use pkarr::PublicKey;
+use pknames_core::{config_directory::{dirs::main_directory::MainDirectory, petnames::KeyNames}, transport::{pkarr_transport::PkarrTransport, cached_transport::CachedTransport}};
 
+use crate::commands::{names::{read_key_names, resolve_key_arg}, identity::open_directory};
 use super::pkarr_records::{PkarrRecords, PkarrRecord};
 
-fn resolve_pkarr(uri: &str) -> (PkarrRecords, DateTime<Utc>)  {
-    let client = PkarrClient::new();
+fn resolve_pkarr(uri: &str, transport: &dyn PkarrTransport) -> Result<(PkarrRecords, DateTime<Utc>), String>  {
     let pubkey: PublicKey = uri.try_into().expect("Should be valid pkarr public key.");
-    let res = client.resolve_most_recent(pubkey);
+    let res = transport.resolve_most_recent(&pubkey)?;
     if res.is_none() {
-        return (PkarrRecords{records: vec![]}, DateTime::<Utc>::MIN_UTC);
+        return Ok((PkarrRecords{records: vec![]}, DateTime::<Utc>::MIN_UTC));
     };
     let signed_packet = res.unwrap();
     let timestamp = chrono::DateTime::from_timestamp((signed_packet.timestamp()/1000000).try_into().unwrap(), 0).unwrap();
@@ -27,24 +27,43 @@ fn resolve_pkarr(uri: &str) -> (PkarrRecords, DateTime<Utc>)  {
         Some(record)
     }).collect();
 
-    (PkarrRecords {
+    Ok((PkarrRecords {
         records
-    }, timestamp)
+    }, timestamp))
 }
 
-fn get_arg_pubkey(matches: &ArgMatches, default_uri: &String) -> Option<PublicKey> {
+/**
+ * Transport configured for this directory. Wrapped in the packet cache unless `no_cache`.
+ */
+pub fn create_resolve_transport(dir: &MainDirectory, no_cache: bool) -> Box<dyn PkarrTransport> {
+    let config_result = dir.read_config();
+    if let Err(e) = config_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let config = config_result.unwrap();
+    let transport = config.transport.create_transport();
+    if no_cache {
+        transport
+    } else {
+        Box::new(CachedTransport::new(transport, dir.packet_cache_dir.clone()))
+    }
+}
+
+fn get_arg_pubkey(matches: &ArgMatches, default_uri: &String, names: &KeyNames) -> Option<PublicKey> {
     let uri_arg: &String = matches.get_one("pubkey").unwrap_or(default_uri);
-    let trying: Result<PublicKey, _> = uri_arg.as_str().try_into();
+    let uri = resolve_key_arg(names, uri_arg);
+    let trying: Result<PublicKey, _> = uri.as_str().try_into();
     trying.ok()
 }
 
 
 pub fn cli_resolve(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
-    let dir = MainDirectory::new(directory);
+    let dir = open_directory(matches, directory);
     dir.create_if_it_does_not_exist().unwrap();
-    let keypair = dir.read_or_create_keypair();
-    let default_uri = keypair.to_uri_string();
-    let pubkey_opt = get_arg_pubkey(matches, &default_uri);
+    let default_uri = dir.get_public_key_uri();
+    let names = read_key_names(&dir);
+    let pubkey_opt = get_arg_pubkey(matches, &default_uri, &names);
 
     if pubkey_opt.is_none() {
         eprintln!("pubkey is not a valid pkarr public key.");
@@ -53,11 +72,54 @@ pub fn cli_resolve(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
     let pubkey = pubkey_opt.unwrap();
     let uri = pubkey.to_uri_string();
 
-    println!("Resolve dns records of {}", uri);
-    let (records, timestamp) = resolve_pkarr(&uri);
+    println!("Resolve dns records of {}", names.label(&uri));
+    let no_cache: bool = *matches.get_one("no-cache").unwrap();
+    if !no_cache {
+        if let Ok(Some(_)) = dir.packet_cache_dir.read_fresh_packet(&pubkey) {
+            println!("Served from local cache. Use --no-cache to bypass.");
+        };
+    };
+    let transport = create_resolve_transport(&dir, no_cache);
+    let resolved = resolve_pkarr(&uri, transport.as_ref());
+    if let Err(e) = resolved {
+        eprintln!("Failed to resolve {}. {}", uri, e);
+        std::process::exit(1);
+    };
+    let (records, timestamp) = resolved.unwrap();
 
     for record in records.records.iter() {
         println!("- {}", record);
     }
     println!("Last updated at: {timestamp}");
 }
+
+#[cfg(test)]
+mod tests {
+    use pkarr::Keypair;
+    use pknames_core::transport::{memory_transport::MemoryTransport, pkarr_transport::PkarrTransport};
+    use crate::commands::pkarr::pkarr_records::PkarrRecords;
+    use super::resolve_pkarr;
+
+    #[test]
+    fn resolve_published_records() {
+        let conf = "
+        A  pknames.p2p  127.0.0.1 10
+        TXT test helloworld
+        ";
+        let keypair = Keypair::random();
+        let packet = PkarrRecords::from_conf(conf).unwrap().to_signed_packet(&keypair).unwrap();
+        let transport = MemoryTransport::new();
+        transport.publish(&packet).unwrap();
+
+        let (records, _) = resolve_pkarr(&keypair.to_uri_string(), &transport).unwrap();
+        assert_eq!(records.records.len(), 2);
+    }
+
+    #[test]
+    fn resolve_unknown_key() {
+        let transport = MemoryTransport::new();
+        let keypair = Keypair::random();
+        let (records, _) = resolve_pkarr(&keypair.to_uri_string(), &transport).unwrap();
+        assert_eq!(records.records.len(), 0);
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pknames_core::config_directory::config::TransportConfig;
+use crate::commands::identity::open_directory;
+
+
+pub fn cli_transport(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let config_result = dir.read_config();
+    if let Err(e) = config_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let config = config_result.unwrap();
+
+    let typ = matches.get_one::<String>("type");
+    if typ.is_none() {
+        println!("Transport: {}", config.transport);
+        return;
+    };
+
+    let values: Vec<String> = matches.get_many::<String>("values").unwrap_or_default().cloned().collect();
+    let transport = match typ.unwrap().as_str() {
+        "dht" => TransportConfig::Dht,
+        "relay" => {
+            let relays = if values.is_empty() { vec![pkarr::DEFAULT_PKARR_RELAY.to_string()] } else { values };
+            TransportConfig::Relay { relays }
+        }
+        "disk" => {
+            if values.len() != 1 {
+                eprintln!("Disk transport requires exactly one directory path.");
+                std::process::exit(1);
+            };
+            TransportConfig::Disk { path: values[0].clone() }
+        }
+        other => {
+            eprintln!("Unknown transport \"{}\". Use dht, relay or disk.", other);
+            std::process::exit(1);
+        }
+    };
+
+    let result = dir.update_config(|config| {
+        config.transport = transport.clone();
+        Ok(())
+    });
+    match result {
+        Ok(_) => println!("Transport: {}", transport),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    };
+}
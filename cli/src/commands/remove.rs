@@ -1,35 +1,29 @@
 use std::path::PathBuf;
 use clap::ArgMatches;
-use pknames_core::config_directory::{dirs::main_directory::MainDirectory, follow::Follow};
+use pknames_core::config_directory::list_edit::ListEditError;
+
+use super::{names::{read_key_names, resolve_key_arg}, identity::{open_directory, require_writable}};
 
 
 pub fn cli_remove(matches: &ArgMatches, directory: PathBuf, _verbose: bool) {
-    let pubkey: &String = matches.get_one("pubkey").unwrap();
+    let raw_pubkey: &String = matches.get_one("pubkey").unwrap();
     let domain = matches.get_one::<String>("domain");
 
-    println!("Remove {} {:?} from my list", pubkey, domain);
-
-    let dir = MainDirectory::new(directory);
+    let dir = open_directory(matches, directory);
     dir.create_if_it_does_not_exist().unwrap();
+    require_writable(&dir);
 
-    let mut me_list = dir.static_lists_dir.read_list(&dir.get_public_key_uri()).expect("Me list should exist.");
-    let follow = Follow::new(pubkey, 0.0, domain.map(|s|s.as_str()));
-
-    if !me_list.follows.contains(&follow) {
-        eprintln!("Follow not found in my list.");
-        return
-    }
+    let names = read_key_names(&dir);
+    let pubkey = resolve_key_arg(&names, raw_pubkey);
+    println!("Remove {} {:?} from my list", names.label(&pubkey), domain);
 
-    let index = me_list.follows.iter().position(|x| *x == follow).unwrap();
-    me_list.follows.remove(index);
-
-    let result = dir.static_lists_dir.write_list(&dir.get_public_key_uri(), me_list);
+    let result = dir.remove_follow(&pubkey, domain.map(|s|s.as_str()));
     match result {
         Ok(_) => println!("Success!"),
+        Err(ListEditError::FollowNotFound { .. }) => eprintln!("Follow not found in my list."),
         Err(e) => {
-            eprintln!("Failed to write list: {}", e);
+            eprintln!("{}", e);
             std::process::exit(1)
         }
     };
-
-}
\ No newline at end of file
+}
@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+use clap::ArgMatches;
+use pkarr::{dns::Name, PublicKey};
+use pknames_core::{prediction::predictor::{WotDecision, DecisionThresholds}, resolve::{find_attributed_domain, resolve_with_directory, Resolution}};
+
+use super::{names::read_perspective_key_names, identity::open_directory, pkarr::{pkarr_records::PkarrRecord, resolve::create_resolve_transport}};
+
+
+/**
+ * Explains why a decision did not resolve to a pubkey.
+ */
+fn explain_decision(decision: &WotDecision, thresholds: &DecisionThresholds) -> String {
+    match decision {
+        WotDecision::Resolved(pubkey) => format!("Resolved to {}.", pubkey),
+        WotDecision::Untrusted => format!(
+            "Best candidate is not trusted enough. Required incoming trust: {:.2}.", thresholds.min_trust
+        ),
+        WotDecision::Ambiguous(candidates) => {
+            let listed: Vec<String> = candidates.iter().map(|class| {
+                format!("{} {:.2}%", class.pubkey, class.probability * 100.0)
+            }).collect();
+            format!(
+                "Ambiguous candidates: {}. Required: {:.2}% with a margin of {:.2}%.",
+                listed.join(", "), thresholds.min_probability * 100.0, thresholds.min_margin * 100.0
+            )
+        }
+    }
+}
+
+
+pub fn cli_resolve_domain(matches: &ArgMatches, directory: PathBuf, verbose: bool) {
+    let name: &String = matches.get_one("domain").unwrap();
+    let no_cache: bool = *matches.get_one("no-cache").unwrap();
+    println!("Resolve {}", name);
+
+    let dir = open_directory(matches, directory);
+    dir.create_if_it_does_not_exist().unwrap();
+
+    let config_result = dir.read_config();
+    if let Err(e) = config_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let config = config_result.unwrap();
+    let mut thresholds: DecisionThresholds = (&config.resolve).into();
+    if let Some(min_probability) = matches.get_one::<f32>("min-probability") {
+        thresholds.min_probability = *min_probability;
+    };
+    if let Some(min_margin) = matches.get_one::<f32>("min-margin") {
+        thresholds.min_margin = *min_margin;
+    };
+    if let Some(min_trust) = matches.get_one::<f32>("min-trust") {
+        thresholds.min_trust = *min_trust;
+    };
+
+    let pins_result = dir.read_perspective_pins();
+    if let Err(e) = pins_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let pins = pins_result.unwrap();
+
+    let found = match pins.find(name) {
+        Some((pin, subdomain)) => Some((pin.domain.clone(), subdomain)),
+        None => {
+            let graph_result = dir.read_graph_and_lists();
+            if let Err(e) = graph_result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            };
+            let (graph, loaded) = graph_result.unwrap();
+            for problem in loaded.problems() {
+                eprintln!("⚠ {}", problem);
+            };
+            find_attributed_domain(name, &graph)
+        }
+    };
+    if found.is_none() {
+        eprintln!("Graph does not contain the domain.");
+        std::process::exit(1);
+    };
+    let (domain, subdomain) = found.unwrap();
+
+    let resolution_result = resolve_with_directory(&domain, &dir);
+    if let Err(e) = resolution_result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    let resolution = resolution_result.unwrap();
+    let names = read_perspective_key_names(&dir);
+    if let Resolution::Predicted(prediction) = &resolution {
+        if verbose {
+            for class in prediction.classes.iter() {
+                println!("- {} {:.2}% trust {:.2}", names.label(&class.pubkey), class.probability * 100.0, class.trust);
+            }
+        };
+    };
+
+    let best_pubkey = match resolution.decide(&thresholds) {
+        WotDecision::Resolved(pubkey) => pubkey,
+        decision => {
+            eprintln!("Refusing to resolve {}. {}", domain, explain_decision(&decision, &thresholds));
+            std::process::exit(1);
+        }
+    };
+    match &resolution {
+        Resolution::Pinned(pin) => println!("{}", pin.format_with_label(&names.label(&pin.pubkey))),
+        Resolution::Predicted(prediction) => {
+            let probability = prediction.get_value(&best_pubkey).unwrap_or(0.0);
+            println!("{} -> {} {:.2}%", domain, names.label(&best_pubkey), probability * 100.0);
+        }
+    };
+
+    let pubkey_result: Result<PublicKey, _> = best_pubkey.as_str().try_into();
+    if pubkey_result.is_err() {
+        eprintln!("{} is not a valid pkarr public key.", best_pubkey);
+        std::process::exit(1);
+    };
+    let pubkey = pubkey_result.unwrap();
+
+    let record_name = if subdomain.is_empty() { "@".to_string() } else { subdomain };
+    if record_name != "@" && Name::new(&record_name).is_err() {
+        eprintln!("Invalid name {}.", record_name);
+        std::process::exit(1);
+    };
+
+    let transport = create_resolve_transport(&dir, no_cache);
+    let packet_result = transport.resolve_most_recent(&pubkey);
+    if let Err(e) = packet_result {
+        eprintln!("Failed to resolve {}. {}", best_pubkey, e);
+        std::process::exit(1);
+    };
+    let packet = packet_result.unwrap();
+    if packet.is_none() {
+        eprintln!("No pkarr records published by {}.", best_pubkey);
+        std::process::exit(1);
+    };
+    let packet = packet.unwrap();
+
+    let records: Vec<PkarrRecord> = packet.resource_records(&record_name).filter_map(|record| {
+        record.clone().try_into().ok()
+    }).collect();
+    if records.is_empty() {
+        eprintln!("No records found for {}.", name);
+        std::process::exit(1);
+    };
+    for record in records.iter() {
+        println!("- {}", record);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use pknames_core::prediction::predictor::{WotPrediction, WotClassPrediction, DecisionThresholds};
+    use super::explain_decision;
+
+    fn get_thresholds() -> DecisionThresholds {
+        DecisionThresholds { min_probability: 0.6, min_margin: 0.2, min_trust: 0.1 }
+    }
+
+    #[test]
+    fn explain_ambiguous() {
+        let classes = vec![
+            WotClassPrediction { pubkey: "d1".to_string(), probability: 0.51, trust: 1.0 },
+            WotClassPrediction { pubkey: "d2".to_string(), probability: 0.49, trust: 1.0 },
+        ];
+        let prediction = WotPrediction { classes, nodes: vec![] };
+        let decision = prediction.decide(&get_thresholds());
+        let explanation = explain_decision(&decision, &get_thresholds());
+        assert!(explanation.contains("d1 51.00%, d2 49.00%"));
+    }
+
+    #[test]
+    fn explain_untrusted() {
+        let classes = vec![WotClassPrediction { pubkey: "d1".to_string(), probability: 1.0, trust: 0.01 }];
+        let prediction = WotPrediction { classes, nodes: vec![] };
+        let decision = prediction.decide(&get_thresholds());
+        assert!(explain_decision(&decision, &get_thresholds()).contains("not trusted enough"));
+    }
+}
@@ -0,0 +1,54 @@
+use clap::ArgMatches;
+use pknames_core::simulation::{attack::Attack, generator::HonestGraphConfig, harness::{simulate, SimulationConfig}};
+
+
+/**
+ * Sybil cluster size and attribution chain length of `Attack::all()`. Defaults of the command line arguments.
+ */
+pub fn default_attack_sizes() -> (usize, usize) {
+    let mut sizes = (0, 0);
+    for attack in Attack::all() {
+        match attack {
+            Attack::SybilCluster { size } => sizes.0 = size,
+            Attack::AttributionChain { length } => sizes.1 = length,
+            _ => {},
+        };
+    };
+    sizes
+}
+
+/**
+ * Runs every attack against generated webs of trust and prints how often it flips the result.
+ * Doesn't take a pknames directory. It runs before the directory is created.
+ */
+pub fn cli_simulate(matches: &ArgMatches, _verbose: bool) {
+    let graph = HonestGraphConfig {
+        lists: *matches.get_one("lists").unwrap(),
+        follows_per_list: *matches.get_one("follows").unwrap(),
+        rewire_probability: *matches.get_one("rewire").unwrap(),
+        attesters: *matches.get_one("attesters").unwrap(),
+    };
+    if !(0.0..=1.0).contains(&graph.rewire_probability) {
+        eprintln!("Rewire probability must be between 0 and 1.");
+        std::process::exit(1);
+    };
+    let config = SimulationConfig {
+        graph,
+        trials: *matches.get_one("trials").unwrap(),
+        seed: *matches.get_one("seed").unwrap(),
+    };
+    let sybils: usize = *matches.get_one("sybils").unwrap();
+    let chain: usize = *matches.get_one("chain").unwrap();
+    let attacks: Vec<Attack> = Attack::all().into_iter().map(|attack| match attack {
+        Attack::SybilCluster { .. } => Attack::SybilCluster { size: sybils },
+        Attack::AttributionChain { .. } => Attack::AttributionChain { length: chain },
+        attack => attack,
+    }).collect();
+
+    println!("{} trials with {} lists that follow {} lists each, {} of them attribute the right key.",
+        config.trials, config.graph.lists, config.graph.follows_per_list, config.graph.attesters);
+    println!();
+    for attack in attacks.iter() {
+        println!("{}", simulate(attack, &config));
+    };
+}
@@ -9,7 +9,7 @@ use std::collections::HashMap;
 
 use petgraph::{stable_graph::{NodeIndex, StableGraph, DefaultIx}, Directed};
 
-use pknames_core::prediction::{node::{WotFollow, WotNode}, graph::WotGraph, predictor::WotPrediction};
+use pknames_core::{config_directory::petnames::sanitize_alias, prediction::{node::{WotFollow, WotNode}, graph::WotGraph, predictor::WotPrediction}, public_key::PublicKey};
 
 use super::{node_vis::FancyNodeShape, edge_vis::FancyEdgeShape};
 
@@ -22,7 +22,7 @@ pub struct PredictedVisWotNode {
 
 pub fn wotgraph_into_graph(wotgraph: WotGraph) -> Graph<PredictedVisWotNode, WotFollow, Directed, DefaultIx, FancyNodeShape, FancyEdgeShape> {
     let mut g: StableGraph<PredictedVisWotNode, WotFollow> = StableGraph::new();
-    let mut node_map: HashMap<String, NodeIndex> = HashMap::new();
+    let mut node_map: HashMap<PublicKey, NodeIndex> = HashMap::new();
     for node in wotgraph.nodes.iter() {
         let vis_node = PredictedVisWotNode {
             node: node.clone(),
@@ -54,7 +54,7 @@ pub fn wotgraph_into_graph(wotgraph: WotGraph) -> Graph<PredictedVisWotNode, Wot
         let attribution_alias = Vec::from_iter(attributions.into_iter()).join(", ");
         let mut label = payload.node.pubkey[..8].to_string();
         if payload.node.alias.len() > 0 {
-            label = format!("{} {}", label, payload.node.alias);
+            label = format!("{} {}", label, sanitize_alias(&payload.node.alias));
         };
         if attribution_alias.len() > 0 {
             label = format!("{} ({})", label, attribution_alias);
@@ -69,7 +69,7 @@ pub fn wotgraph_into_graph(wotgraph: WotGraph) -> Graph<PredictedVisWotNode, Wot
 // impl Into<Graph<PredictedVisWotNode, WotFollow, Directed, DefaultIx, FancyNodeShape, FancyEdgeShape>> for WotGraph {
 //     fn into(self) -> Graph<PredictedVisWotNode, WotFollow, Directed, DefaultIx, FancyNodeShape, FancyEdgeShape> {
 //         let mut g: StableGraph<PredictedVisWotNode, WotFollow> = StableGraph::new();
-//         let mut node_map: HashMap<String, NodeIndex> = HashMap::new();
+//         let mut node_map: HashMap<PublicKey, NodeIndex> = HashMap::new();
 //         for node in self.nodes.iter() {
 //             let vis_node = PredictedVisWotNode {
 //                 node: node.clone(),
@@ -167,7 +167,7 @@ pub fn visualize_graph(graph: WotGraph, title: &str, me_pubkey: Option<&str>, re
         if let Some(ref res) = result {
             power_val = res.get_value(&payload.node.pubkey);
         }
-        let is_me = me_pubkey.is_some() && me_pubkey.unwrap() == payload.node.pubkey;
+        let is_me = me_pubkey.is_some() && payload.node.pubkey == me_pubkey.unwrap();
         let internal_data = node.display_mut();
         internal_data.is_me = is_me;
         internal_data.power = power_val;
@@ -0,0 +1,497 @@
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::time::unix_now;
+use super::{
+    config::Config, dirs::main_directory::MainDirectory, follow_list::FollowList, perspective::validate_identity_name,
+    petnames::PetnameBook, pins::PinStore, secret::{EncryptedSecret, SecretFile}, storage::{write_atomic, write_atomic_private, DirectoryLock},
+    provenance::Provenance,
+};
+
+
+const BUNDLE_VERSION: u32 = 1;
+
+/**
+ * Everything needed to move a pknames directory to another machine in a single json file.
+ * Holds secrets of all identities, retired secrets, authored and imported lists, pins, petnames and the config.
+ * The packet cache and fetched lists are left out. Trained weights live in the lists, so there is no separate training state.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bundle {
+    pub version: u32,
+    /// Unix timestamp in seconds.
+    pub created: u64,
+    /// Path relative to the directory -> file content.
+    pub files: BTreeMap<String, String>,
+    /// `sha256:<hex>` over all files. Detects corruption, not tampering. See `Bundle::compute_checksum`.
+    pub checksum: String,
+}
+
+/**
+ * How an imported bundle is combined with an existing directory.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Only adds what is missing. Lists, pins and petnames are merged, local secrets and config win.
+    Merge,
+    /// Bundle files replace local ones. Replaced secrets are kept in the retired directory.
+    Overwrite,
+}
+
+/**
+ * What happened to a single file during an import.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportAction {
+    Added,
+    Merged,
+    Replaced,
+    Unchanged,
+    Skipped(String),
+}
+
+impl fmt::Display for ImportAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportAction::Added => write!(f, "added"),
+            ImportAction::Merged => write!(f, "merged"),
+            ImportAction::Replaced => write!(f, "replaced"),
+            ImportAction::Unchanged => write!(f, "unchanged"),
+            ImportAction::Skipped(reason) => write!(f, "skipped, {}", reason),
+        }
+    }
+}
+
+/**
+ * Kind of a bundle file, derived from its path.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileKind {
+    Secret,
+    List,
+    /// Provenance of an imported list.
+    ListMetadata,
+    Pins,
+    Petnames,
+    Config,
+}
+
+impl Bundle {
+    /**
+     * Collects all files of the directory.
+     */
+    pub fn from_directory(dir: &MainDirectory) -> Result<Self, String> {
+        let mut files = BTreeMap::new();
+        let mut add = |relative: String| -> Result<(), String> {
+            let path = dir.path.join(&relative);
+            if !path.is_file() {
+                return Ok(());
+            };
+            let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read \"{}\". {}", path.to_str().unwrap(), e))?;
+            files.insert(relative, content);
+            Ok(())
+        };
+        for name in ["secret", "pins.json", "petnames.json", "config.json"] {
+            add(name.to_string())?;
+        };
+        for folder in ["identities", "retired", "static_lists", "static_lists/imported"] {
+            let entries = fs::read_dir(dir.path.join(folder));
+            if entries.is_err() {
+                continue;
+            };
+            let mut names: Vec<String> = entries.unwrap()
+                .filter_map(|entry| entry.ok()?.file_name().to_str().map(|name| name.to_string()))
+                .collect();
+            names.sort();
+            for name in names {
+                let relative = format!("{}/{}", folder, name);
+                if file_kind(&relative).is_some() {
+                    add(relative)?;
+                };
+            };
+        };
+
+        let checksum = Bundle::compute_checksum(&files);
+        Ok(Bundle { version: BUNDLE_VERSION, created: unix_now(), files, checksum })
+    }
+
+    /**
+     * Hash over the sorted paths and contents. Not a signature, whoever edits the files can recompute it.
+     */
+    pub fn compute_checksum(files: &BTreeMap<String, String>) -> String {
+        let mut hasher = Sha256::new();
+        for (path, content) in files.iter() {
+            hasher.update(path.as_bytes());
+            hasher.update([0u8]);
+            hasher.update(content.as_bytes());
+            hasher.update([0u8]);
+        };
+        let hex: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("sha256:{}", hex)
+    }
+
+    /**
+     * Checks version, paths, that every file parses and that the checksum matches, so a damaged bundle isn't imported.
+     */
+    pub fn verify(&self) -> Result<(), String> {
+        if self.version != BUNDLE_VERSION {
+            return Err(format!("Unsupported bundle version {}. Expected {}.", self.version, BUNDLE_VERSION));
+        };
+        if Bundle::compute_checksum(&self.files) != self.checksum {
+            return Err("Checksum mismatch. The bundle is corrupted, for example by an incomplete copy.".to_string());
+        };
+        for (path, content) in self.files.iter() {
+            let kind = file_kind(path);
+            if kind.is_none() {
+                return Err(format!("Unexpected file \"{}\" in bundle.", path));
+            };
+            validate_content(path, kind.unwrap(), content).map_err(|e| format!("Invalid file \"{}\" in bundle. {}", path, e))?;
+        };
+        Ok(())
+    }
+
+    /**
+     * Secrets that are stored without passphrase.
+     */
+    pub fn plain_secrets(&self) -> Vec<&String> {
+        self.files.iter()
+            .filter(|(path, content)| file_kind(path) == Some(FileKind::Secret) && SecretFile::parse(content).is_ok_and(|secret| !secret.is_encrypted()))
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /**
+     * Encrypts all plain secrets with `passphrase`. Already encrypted secrets stay as they are.
+     */
+    pub fn encrypt_secrets(&mut self, passphrase: &str) -> Result<(), String> {
+        for path in self.plain_secrets().into_iter().cloned().collect::<Vec<String>>() {
+            let keypair = SecretFile::parse(&self.files[&path])?.unlock(None)?;
+            let encrypted = SecretFile::Encrypted(EncryptedSecret::encrypt(&keypair, passphrase)?);
+            self.files.insert(path, encrypted.to_file_content());
+        };
+        self.checksum = Bundle::compute_checksum(&self.files);
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let str_res = fs::read_to_string(path);
+        if let Err(e) = str_res {
+            return Err(format!("Failed to read bundle \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        let bundle = Bundle::from_json(&str_res.unwrap());
+        if let Err(e) = bundle {
+            return Err(format!("Failed to parse bundle \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        Ok(bundle.unwrap())
+    }
+
+    /**
+     * Writes the bundle readable by the owner only because it contains secrets.
+     */
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        write_atomic_private(path, self.to_json().as_bytes()).map_err(|e| format!("Failed to write bundle \"{}\". {}", path.to_str().unwrap(), e))
+    }
+
+    /**
+     * Verifies the bundle and writes it into the directory. Returns what happened to every file.
+     */
+    pub fn import_into(&self, dir: &MainDirectory, mode: ImportMode) -> Result<Vec<(String, ImportAction)>, String> {
+        self.verify()?;
+        let _lock = DirectoryLock::acquire(&dir.path)?;
+        for folder in ["identities", "retired", "static_lists", "static_lists/imported"] {
+            fs::create_dir_all(dir.path.join(folder)).map_err(|e| e.to_string())?;
+        };
+
+        let mut report = vec![];
+        for (relative, content) in self.files.iter() {
+            let path = dir.path.join(relative);
+            let kind = file_kind(relative).unwrap();
+            let action = if !path.exists() {
+                import_file(dir, relative, kind, content)?;
+                ImportAction::Added
+            } else {
+                let local = fs::read_to_string(&path).map_err(|e| format!("Failed to read \"{}\". {}", path.to_str().unwrap(), e))?;
+                if local == *content {
+                    ImportAction::Unchanged
+                } else if kind == FileKind::Secret {
+                    import_secret(dir, &path, &local, content, mode)?
+                } else if mode == ImportMode::Overwrite {
+                    import_file(dir, relative, kind, content)?;
+                    ImportAction::Replaced
+                } else {
+                    let merged = merge_content(kind, &local, content)?;
+                    match merged {
+                        Some(merged) => {
+                            import_file(dir, relative, kind, &merged)?;
+                            ImportAction::Merged
+                        },
+                        None => ImportAction::Skipped("local version kept".to_string()),
+                    }
+                }
+            };
+            report.push((relative.clone(), action));
+        };
+        Ok(report)
+    }
+}
+
+/**
+ * Only known files are allowed. Also prevents paths that escape the directory.
+ */
+fn file_kind(path: &str) -> Option<FileKind> {
+    match path {
+        "secret" => return Some(FileKind::Secret),
+        "pins.json" => return Some(FileKind::Pins),
+        "petnames.json" => return Some(FileKind::Petnames),
+        "config.json" => return Some(FileKind::Config),
+        _ => {},
+    };
+    let (folder, name) = path.rsplit_once('/')?;
+    let (stem, extension) = name.rsplit_once('.')?;
+    validate_identity_name(stem).ok()?;
+    match (folder, extension) {
+        ("identities", "secret") | ("retired", "secret") => Some(FileKind::Secret),
+        ("static_lists", "json") | ("static_lists/imported", "json") => Some(FileKind::List),
+        ("static_lists/imported", "meta") => Some(FileKind::ListMetadata),
+        _ => None,
+    }
+}
+
+fn validate_content(path: &str, kind: FileKind, content: &str) -> Result<(), String> {
+    match kind {
+        FileKind::Secret => SecretFile::parse(content).map(|_| ()),
+        FileKind::List => {
+            let list = FollowList::from_json(content).map_err(|e| e.to_string())?;
+            let folder = path.rsplit_once('/').unwrap().0;
+            let expected = format!("{}/{}.json", folder, list.pubkey.to_z32());
+            if path != expected {
+                return Err(format!("List of {} must be stored in \"{}\".", list.pubkey, expected));
+            };
+            Ok(())
+        },
+        FileKind::ListMetadata => Provenance::from_json(content).map(|_| ()).map_err(|e| e.to_string()),
+        FileKind::Pins => PinStore::from_json(content).map(|_| ()).map_err(|e| e.to_string()),
+        FileKind::Petnames => PetnameBook::from_json(content).map(|_| ()).map_err(|e| e.to_string()),
+        FileKind::Config => Config::from_json(content).map(|_| ()).map_err(|e| e.to_string()),
+    }
+}
+
+fn write_file(path: &Path, kind: FileKind, content: &str) -> Result<(), String> {
+    let result = match kind {
+        FileKind::Secret => write_atomic_private(path, content.as_bytes()),
+        _ => write_atomic(path, content.as_bytes()),
+    };
+    result.map_err(|e| format!("Failed to write \"{}\". {}", path.to_str().unwrap(), e))
+}
+
+/**
+ * Writes a bundle file into the directory. Authored lists are journaled, so the import can be undone like any other change.
+ */
+fn import_file(dir: &MainDirectory, relative: &str, kind: FileKind, content: &str) -> Result<(), String> {
+    let path = dir.path.join(relative);
+    let is_authored_list = kind == FileKind::List && relative.rsplit_once('/').map(|(folder, _)| folder) == Some("static_lists");
+    if !is_authored_list {
+        return write_file(&path, kind, content);
+    };
+    let after = FollowList::from_json(content).map_err(|e| e.to_string())?;
+    let before = match path.exists() {
+        true => dir.static_lists_dir.read_list(&after.pubkey)?,
+        false => FollowList::new(after.pubkey.clone(), &after.alias),
+    };
+    dir.write_list_with_reason(before, after, "import bundle")?;
+    Ok(())
+}
+
+/**
+ * Never loses a secret. With overwrite, the local secret is moved to the retired directory first.
+ */
+fn import_secret(dir: &MainDirectory, path: &Path, local: &str, content: &str, mode: ImportMode) -> Result<ImportAction, String> {
+    let local_pubkey = SecretFile::parse(local)?.public_key();
+    let bundle_pubkey = SecretFile::parse(content)?.public_key();
+    if local_pubkey == bundle_pubkey && mode == ImportMode::Merge {
+        return Ok(ImportAction::Unchanged); // Same key, for example once encrypted and once plain.
+    };
+    if mode == ImportMode::Merge {
+        return Ok(ImportAction::Skipped(format!("local secret of {} kept", local_pubkey)));
+    };
+    if local_pubkey != bundle_pubkey {
+        let retired_path = dir.get_retired_path().join(format!("{}.secret", local_pubkey.to_z32()));
+        write_file(&retired_path, FileKind::Secret, local)?;
+    };
+    write_file(path, FileKind::Secret, content)?;
+    Ok(ImportAction::Replaced)
+}
+
+/**
+ * Combines a local and a bundle file. None if the local file is kept as it is.
+ */
+fn merge_content(kind: FileKind, local: &str, bundle: &str) -> Result<Option<String>, String> {
+    match kind {
+        FileKind::List => {
+            let mut local = FollowList::from_json(local).map_err(|e| e.to_string())?;
+            let bundle = FollowList::from_json(bundle).map_err(|e| e.to_string())?;
+            for follow in bundle.follows {
+                let exists = local.follows.iter().any(|existing| existing.pubkey() == follow.pubkey() && existing.domain() == follow.domain());
+                if !exists {
+                    local.follows.push(follow);
+                };
+            };
+            for block in bundle.blocks {
+                if !local.blocks.contains(&block) {
+                    local.blocks.push(block);
+                };
+            };
+            if local.succession.is_none() {
+                local.succession = bundle.succession;
+            };
+            Ok(Some(local.to_json()))
+        },
+        FileKind::Pins => {
+            let mut local = PinStore::from_json(local).map_err(|e| e.to_string())?;
+            let bundle = PinStore::from_json(bundle).map_err(|e| e.to_string())?;
+            for pin in bundle.pins {
+                if !local.pins.iter().any(|existing| existing.domain == pin.domain) {
+                    local.pins.push(pin);
+                };
+            };
+            Ok(Some(local.to_json()))
+        },
+        FileKind::Petnames => {
+            let mut local = PetnameBook::from_json(local).map_err(|e| e.to_string())?;
+            let bundle = PetnameBook::from_json(bundle).map_err(|e| e.to_string())?;
+            for (petname, pubkey) in bundle.petnames {
+                let is_taken = local.get_pubkey(&petname).is_some() || local.get_petname(&pubkey).is_some();
+                if !is_taken {
+                    local.petnames.insert(petname, pubkey);
+                };
+            };
+            Ok(Some(local.to_json()))
+        },
+        FileKind::Config | FileKind::Secret | FileKind::ListMetadata => Ok(None),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{config_directory::{dirs::main_directory::MainDirectory, follow::Follow, petnames::PetnameBook, follow_list::FollowList, provenance::ListSource}, public_key::PublicKey};
+    use super::{Bundle, ImportAction, ImportMode};
+
+    const ALICE: &str = "pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy";
+    const DAVE: &str = "pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy";
+
+    fn directory(path: &str) -> MainDirectory {
+        let mut dir = MainDirectory::new_by_string(path);
+        let _ = dir.delete(); // Delete so the test can work again even though it failed before.
+        dir.passphrase = None;
+        dir.create_if_it_does_not_exist().unwrap();
+        dir
+    }
+
+    fn follow(dir: &MainDirectory, pubkey: &str) {
+        let me = dir.get_public_key();
+        let mut list = dir.static_lists_dir.read_list(&me).unwrap();
+        list.follows.push(Follow::new(PublicKey::parse(pubkey).unwrap(), 1.0, None));
+        dir.static_lists_dir.write_list(&me, list).unwrap();
+    }
+
+    #[test]
+    fn export_verify_import() {
+        let source = directory("/tmp/fancydns_bundle_source");
+        follow(&source, ALICE);
+        source.create_identity("org").unwrap();
+        let alice_list_path = source.path.join("alice.json");
+        std::fs::write(&alice_list_path, FollowList::new(PublicKey::parse(ALICE).unwrap(), "alice").to_json()).unwrap();
+        source.import_list(&alice_list_path).unwrap();
+        let bundle = Bundle::from_directory(&source).unwrap();
+        assert!(bundle.files.contains_key("secret"));
+        assert!(bundle.files.contains_key("identities/org.secret"));
+        let alice = PublicKey::parse(ALICE).unwrap();
+        let alice_z32 = alice.to_z32();
+        assert!(bundle.files.contains_key(&format!("static_lists/imported/{}.json", alice_z32)));
+        assert!(bundle.files.contains_key(&format!("static_lists/imported/{}.meta", alice_z32)));
+        assert!(bundle.verify().is_ok());
+
+        let mut corrupted = bundle.clone();
+        corrupted.files.insert("pins.json".to_string(), "{}".to_string());
+        assert!(corrupted.verify().is_err());
+        let mut escaping = bundle.clone();
+        escaping.files.insert("../secret".to_string(), "x".to_string());
+        escaping.checksum = Bundle::compute_checksum(&escaping.files);
+        assert!(escaping.verify().is_err());
+
+        let target = MainDirectory::new_by_string("/tmp/fancydns_bundle_target");
+        let _ = target.delete();
+        std::fs::create_dir(&target.path).unwrap();
+        let report = bundle.import_into(&target, ImportMode::Merge).unwrap();
+        assert!(report.iter().all(|(_, action)| *action == ImportAction::Added));
+        assert_eq!(target.get_public_key(), source.get_public_key());
+        assert_eq!(target.list_identities(), vec!["default", "org"]);
+        assert!(target.read_stored_lists().iter().any(|stored| stored.provenance.source == ListSource::Imported));
+        source.delete().unwrap();
+        target.delete().unwrap();
+    }
+
+    #[test]
+    fn merge_and_overwrite() {
+        let source = directory("/tmp/fancydns_bundle_merge_source");
+        follow(&source, ALICE);
+        let mut petnames = PetnameBook::default();
+        petnames.set("alice", &PublicKey::parse(ALICE).unwrap()).unwrap();
+        source.write_petnames(&petnames).unwrap();
+        let bundle = Bundle::from_directory(&source).unwrap();
+        let source_pubkey = source.get_public_key();
+
+        // Target has its own key and the list of the source with another follow.
+        let target = directory("/tmp/fancydns_bundle_merge_target");
+        let target_pubkey = target.get_public_key();
+        let mut list = FollowList::new(source_pubkey.clone(), "me");
+        list.follows.push(Follow::new(PublicKey::parse(DAVE).unwrap(), 0.5, None));
+        target.static_lists_dir.write_list(&source_pubkey, list).unwrap();
+
+        let report = bundle.import_into(&target, ImportMode::Merge).unwrap();
+        let action = |path: &str| report.iter().find(|(p, _)| p == path).unwrap().1.clone();
+        assert!(matches!(action("secret"), ImportAction::Skipped(_)));
+        assert_eq!(action(&format!("static_lists/{}.json", source_pubkey.to_z32())), ImportAction::Merged);
+        assert_eq!(action("petnames.json"), ImportAction::Added);
+        assert_eq!(target.get_public_key(), target_pubkey);
+        let merged = target.static_lists_dir.read_list(&source_pubkey).unwrap();
+        assert!(merged.is_following(&PublicKey::parse(ALICE).unwrap()));
+        assert!(merged.is_following(&PublicKey::parse(DAVE).unwrap()));
+
+        let report = bundle.import_into(&target, ImportMode::Overwrite).unwrap();
+        assert!(report.iter().any(|(path, action)| path == "secret" && *action == ImportAction::Replaced));
+        assert_eq!(target.get_public_key(), source_pubkey);
+        assert!(target.get_retired_path().join(format!("{}.secret", target_pubkey.to_z32())).exists());
+        assert!(!target.static_lists_dir.read_list(&source_pubkey).unwrap().is_following(&PublicKey::parse(DAVE).unwrap()));
+
+        // Both imports of the list are journaled. Undo brings back the merged list.
+        let history = target.read_history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|entry| entry.reason == "import bundle"));
+        target.undo().unwrap();
+        assert!(target.static_lists_dir.read_list(&source_pubkey).unwrap().is_following(&PublicKey::parse(DAVE).unwrap()));
+        source.delete().unwrap();
+        target.delete().unwrap();
+    }
+
+    #[test]
+    fn encrypt_secrets() {
+        let source = directory("/tmp/fancydns_bundle_encrypt_secrets");
+        let mut bundle = Bundle::from_directory(&source).unwrap();
+        assert_eq!(bundle.plain_secrets().len(), 1);
+        bundle.encrypt_secrets("pass").unwrap();
+        assert!(bundle.plain_secrets().is_empty());
+        assert!(bundle.verify().is_ok());
+        source.delete().unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+use std::{fs, path::Path};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::prediction::predictor::DecisionThresholds;
+use crate::config_directory::provenance::{ListSource, Provenance};
+use crate::transport::{pkarr_transport::PkarrTransport, dht_transport::DhtTransport, relay_transport::RelayTransport, disk_transport::DiskTransport};
+
+
+/**
+ * How pkarr packets are published and resolved.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TransportConfig {
+    /// Mainline DHT over UDP.
+    #[default]
+    Dht,
+    /// Pkarr HTTP relays. At least one.
+    Relay {
+        #[serde(deserialize_with = "deserialize_relays")]
+        relays: Vec<String>
+    },
+    /// Local directory. No network at all.
+    Disk { path: String },
+}
+
+fn deserialize_relays<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let relays: Vec<String> = Vec::deserialize(deserializer)?;
+    if relays.is_empty() {
+        return Err(serde::de::Error::custom("relay transport needs at least one relay"));
+    };
+    Ok(relays)
+}
+
+impl std::fmt::Display for TransportConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportConfig::Dht => write!(f, "dht"),
+            TransportConfig::Relay { relays } => write!(f, "relay {}", relays.join(" ")),
+            TransportConfig::Disk { path } => write!(f, "disk {}", path),
+        }
+    }
+}
+
+impl TransportConfig {
+    pub fn create_transport(&self) -> Box<dyn PkarrTransport> {
+        match self {
+            TransportConfig::Dht => Box::new(DhtTransport::new()),
+            TransportConfig::Relay { relays } => Box::new(RelayTransport::new(relays.clone())),
+            TransportConfig::Disk { path } => {
+                let expanded: String = shellexpand::tilde(path).into();
+                Box::new(DiskTransport::new(expanded.into()))
+            }
+        }
+    }
+}
+
+
+/**
+ * How confident the web of trust must be before a domain is resolved to a public key.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ResolveConfig {
+    /// Minimum probability of the best class. 0 to 1.
+    #[serde(default = "default_min_probability")]
+    pub min_probability: f32,
+    /// Minimum probability difference between the best and the second best class. 0 to 1.
+    #[serde(default = "default_min_margin")]
+    pub min_margin: f32,
+    /// Minimum total incoming trust of the best class.
+    #[serde(default = "default_min_trust")]
+    pub min_trust: f32,
+}
+
+fn default_min_probability() -> f32 {
+    0.6
+}
+
+fn default_min_margin() -> f32 {
+    0.2
+}
+
+fn default_min_trust() -> f32 {
+    0.1
+}
+
+impl Default for ResolveConfig {
+    fn default() -> Self {
+        ResolveConfig {
+            min_probability: default_min_probability(),
+            min_margin: default_min_margin(),
+            min_trust: default_min_trust(),
+        }
+    }
+}
+
+impl From<&ResolveConfig> for DecisionThresholds {
+    fn from(config: &ResolveConfig) -> Self {
+        DecisionThresholds {
+            min_probability: config.min_probability,
+            min_margin: config.min_margin,
+            min_trust: config.min_trust,
+        }
+    }
+}
+
+
+/**
+ * How the age of a follow affects its weight.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct DecayConfig {
+    /// Days after which the weight of a follow is halved. None: Follows don't decay.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub half_life_days: Option<f32>,
+}
+
+impl DecayConfig {
+    pub fn half_life_secs(&self) -> Option<u64> {
+        self.half_life_days.map(|days| (days * 24.0 * 60.0 * 60.0) as u64)
+    }
+}
+
+
+/**
+ * Which lists are used to build the graph. Authored lists are always used.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ListsConfig {
+    /// Sources of lists of others that are used.
+    #[serde(default = "default_sources")]
+    pub sources: Vec<ListSource>,
+}
+
+fn default_sources() -> Vec<ListSource> {
+    vec![ListSource::Imported, ListSource::Fetched]
+}
+
+impl Default for ListsConfig {
+    fn default() -> Self {
+        ListsConfig { sources: default_sources() }
+    }
+}
+
+impl ListsConfig {
+    /**
+     * Whether a list with this provenance is used.
+     */
+    pub fn accepts(&self, provenance: &Provenance) -> bool {
+        provenance.source == ListSource::Authored || self.sources.contains(&provenance.source)
+    }
+}
+
+
+/**
+ * How follow weights are used to build the graph.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct WeightsConfig {
+    /// Scale the list follows of every list so their absolute weights sum up to at most 1. See `WotGraph::normalize_follow_weights`.
+    #[serde(default)]
+    pub normalize: bool,
+}
+
+
+/**
+ * Settings of a pknames directory. Stored in `<dir>/config.json`.
+ */
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct Config {
+    #[serde(default)]
+    pub transport: TransportConfig,
+    #[serde(default)]
+    pub resolve: ResolveConfig,
+    #[serde(default)]
+    pub decay: DecayConfig,
+    #[serde(default)]
+    pub lists: ListsConfig,
+    #[serde(default)]
+    pub weights: WeightsConfig,
+}
+
+impl Config {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let str_res = fs::read_to_string(path);
+        if let Err(e) = str_res {
+            return Err(format!("Failed to read config \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        let config = Config::from_json(&str_res.unwrap());
+        if let Err(e) = config {
+            return Err(format!("Failed to parse config \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        Ok(config.unwrap())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::config_directory::provenance::{ListSource, Provenance};
+    use super::{Config, ResolveConfig, TransportConfig};
+
+    #[test]
+    fn default_is_dht() {
+        let config = Config::from_json("{}").unwrap();
+        assert_eq!(config.transport, TransportConfig::Dht);
+        assert_eq!(config.resolve, ResolveConfig::default());
+    }
+
+    #[test]
+    fn partial_resolve_config() {
+        let config = Config::from_json(r#"{"resolve": {"min_margin": 0.5}}"#).unwrap();
+        assert_eq!(config.resolve.min_margin, 0.5);
+        assert_eq!(config.resolve.min_probability, ResolveConfig::default().min_probability);
+        assert_eq!(config.resolve.min_trust, ResolveConfig::default().min_trust);
+    }
+
+    #[test]
+    fn decay_config() {
+        assert_eq!(Config::from_json("{}").unwrap().decay.half_life_secs(), None);
+        let config = Config::from_json(r#"{"decay": {"half_life_days": 2}}"#).unwrap();
+        assert_eq!(config.decay.half_life_secs(), Some(2 * 24 * 60 * 60));
+    }
+
+    #[test]
+    fn relay_to_json_and_back() {
+        let json = r#"{
+            "transport": {
+                "type": "relay",
+                "relays": ["https://relay.pkarr.org"]
+            }
+        }"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.transport, TransportConfig::Relay { relays: vec!["https://relay.pkarr.org".to_string()] });
+
+        let recovered = Config::from_json(&config.to_json()).unwrap();
+        assert_eq!(recovered.transport, config.transport);
+
+        let empty = Config::from_json(r#"{"transport": {"type": "relay", "relays": []}}"#);
+        assert!(empty.unwrap_err().to_string().contains("at least one relay"));
+    }
+
+    #[test]
+    fn lists_config() {
+        let fetched = Provenance::new(ListSource::Fetched);
+        let imported = Provenance::new(ListSource::Imported);
+        let authored = Provenance::new(ListSource::Authored);
+
+        let config = Config::from_json("{}").unwrap().lists;
+        assert!(config.accepts(&fetched) && config.accepts(&imported) && config.accepts(&authored));
+
+        let config = Config::from_json(r#"{"lists": {"sources": ["imported"]}}"#).unwrap().lists;
+        assert!(!config.accepts(&fetched));
+        assert!(config.accepts(&imported));
+        assert!(config.accepts(&authored));
+
+        let config = Config::from_json(r#"{"lists": {"sources": []}}"#).unwrap().lists;
+        assert!(!config.accepts(&imported));
+        assert!(config.accepts(&authored));
+    }
+}
@@ -1,27 +1,51 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use pkarr::Keypair;
-use super::static_lists_directory::StaticListsDirectory;
-
-
-const SECRET_KEY_LENGTH: usize = 32;
+use crate::{public_key::PublicKey, prediction::graph::WotGraph, time::unix_now};
+use crate::config_directory::follow_list::lists_into_graph;
+use crate::config_directory::{config::Config, pins::PinStore, petnames::{PetnameBook, KeyNames}, perspective::{Perspective, DEFAULT_IDENTITY, validate_identity_name}};
+use crate::config_directory::secret::{SecretFile, EncryptedSecret, PASSPHRASE_ENV, check_permissions};
+use crate::config_directory::storage::{write_atomic, DirectoryLock};
+use crate::transport::pkarr_transport::PkarrTransport;
+use crate::config_directory::{follow::Follow, follow_list::FollowList, succession::{Succession, publish_succession}, list_edit::{ListChange, ChangeOutcome, ListEditError}};
+use crate::config_directory::journal::{Journal, JournalEntry};
+use crate::config_directory::provenance::{ListSource, Provenance, StoredList};
+use super::static_lists_directory::{LoadedLists, StaticListsDirectory};
+use super::packet_cache_directory::PacketCacheDirectory;
 
 pub struct MainDirectory {
     pub path: PathBuf,
-    pub static_lists_dir: StaticListsDirectory
+    pub static_lists_dir: StaticListsDirectory,
+    pub packet_cache_dir: PacketCacheDirectory,
+    pub perspective: Perspective,
+    /// Unlocks encrypted secrets. Defaults to the `PKNAMES_PASSPHRASE` env variable. New secrets are encrypted with it.
+    pub passphrase: Option<String>,
 }
 
 impl MainDirectory {
     /**
-     * Creates new ConfigFolder.
+     * Creates new ConfigFolder from the perspective of the default identity.
      */
     pub fn new(path: PathBuf) -> Self {
+        MainDirectory::new_with_perspective(path, Perspective::default())
+    }
+
+    /**
+     * Creates new ConfigFolder from the perspective of another identity or public key.
+     */
+    pub fn new_with_perspective(path: PathBuf, perspective: Perspective) -> Self {
         let mut static_lists_path = path.clone().into_os_string();
         static_lists_path.push("/static_lists");
         let static_lists_dir = StaticListsDirectory::new(static_lists_path.into());
+        let mut packet_cache_path = path.clone().into_os_string();
+        packet_cache_path.push("/packet_cache");
+        let packet_cache_dir = PacketCacheDirectory::new(packet_cache_path.into());
         MainDirectory { 
             path,
-            static_lists_dir
+            static_lists_dir,
+            packet_cache_dir,
+            perspective,
+            passphrase: std::env::var(PASSPHRASE_ENV).ok().filter(|passphrase| !passphrase.is_empty()),
         }
     }
 
@@ -80,8 +104,13 @@ impl MainDirectory {
      */
     pub fn create_if_it_does_not_exist(&self) -> Result<(), std::io::Error> {
         self.create_main_dir_if_it_does_not_exist()?;
+        self.packet_cache_dir.create_if_it_does_not_exist()?;
 
-        self.static_lists_dir.create_if_it_does_not_exist(&self.get_public_key_uri())
+        match self.perspective {
+            // Never create a list in the name of somebody else.
+            Perspective::PublicKey(_) => self.static_lists_dir.create_if_it_does_not_exist(None),
+            Perspective::Identity(_) => self.static_lists_dir.create_if_it_does_not_exist(Some(&self.get_public_key())),
+        }
     }
 
     /**
@@ -91,35 +120,303 @@ impl MainDirectory {
         fs::remove_dir_all(&self.path)
     }
 
-    pub fn get_keypair_path(&self) -> PathBuf {
-        let mut path = self.path.clone().into_os_string();
-        path.push("/secret");
-        PathBuf::from(path)
+    /**
+     * Exclusive lock on the directory. Hold it while reading, changing and writing files so concurrent processes don't lose updates.
+     * Not reentrant, don't call other locking methods while holding it.
+     */
+    pub fn lock(&self) -> Result<DirectoryLock, String> {
+        DirectoryLock::acquire(&self.path)
     }
 
     /**
-     * Reads the keypair from the disk
+     * Secret of the identity. The default identity lives in `<dir>/secret`, all others in `<dir>/identities/<name>.secret`.
      */
-    pub fn read_keypair(&self) -> Result<Keypair, String> {
+    pub fn get_identity_path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_IDENTITY {
+            return self.path.join("secret");
+        };
+        self.get_identities_path().join(format!("{}.secret", name))
+    }
+
+    pub fn get_identities_path(&self) -> PathBuf {
+        self.path.join("identities")
+    }
+
+    /**
+     * Secret of the current perspective. None if the perspective is only a public key.
+     */
+    pub fn get_keypair_path(&self) -> Option<PathBuf> {
+        match &self.perspective {
+            Perspective::Identity(name) => Some(self.get_identity_path(name)),
+            Perspective::PublicKey(_) => None,
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.perspective, Perspective::PublicKey(_))
+    }
+
+    /**
+     * Reads the secret of the current perspective without unlocking it.
+     */
+    pub fn read_secret(&self) -> Result<SecretFile, String> {
         let path = self.get_keypair_path();
+        if path.is_none() {
+            return Err(format!("No secret key. The perspective is only the {}.", self.perspective));
+        };
+        SecretFile::from_path(&path.unwrap())
+    }
 
-        let read_result = fs::read_to_string(path);
-        if let Err(e) = read_result {
-            return Err(e.to_string());
+    /**
+     * Reads the keypair from the disk. Encrypted secrets are unlocked with `passphrase`.
+     */
+    pub fn read_keypair(&self) -> Result<Keypair, String> {
+        let secret = self.read_secret()?;
+        check_permissions(&self.get_keypair_path().unwrap())?;
+        secret.unlock(self.passphrase.as_deref())
+    }
+
+    /**
+     * Encrypts the secret of the current perspective with a new passphrase.
+     * Migrates plain secrets and changes the passphrase of encrypted ones. The current passphrase is needed for the latter.
+     */
+    pub fn encrypt_secret(&self, new_passphrase: &str) -> Result<(), String> {
+        if new_passphrase.is_empty() {
+            return Err("Passphrase must not be empty.".to_string());
+        };
+        let _lock = self.lock()?;
+        let keypair = self.read_keypair()?;
+        let encrypted = EncryptedSecret::encrypt(&keypair, new_passphrase)?;
+        SecretFile::Encrypted(encrypted).write(&self.get_keypair_path().unwrap())
+    }
+
+    /**
+     * Old secrets of rotated keys are kept in `<dir>/retired/<z32 pubkey>.secret`.
+     */
+    pub fn get_retired_path(&self) -> PathBuf {
+        self.path.join("retired")
+    }
+
+    /**
+     * Replaces the keypair of the current identity with a new random one.
+     * The list moves to the new key and the old list gets a succession statement signed by the old key.
+     * The old secret is kept in the retired directory. Both list changes are journaled. Returns the new keypair.
+     */
+    pub fn rotate_keypair(&self) -> Result<Keypair, String> {
+        let _lock = self.lock()?;
+        let old = self.read_keypair()?;
+        let old_pubkey = PublicKey::from(&old.public_key());
+        let new = Keypair::random();
+        let new_pubkey = PublicKey::from(&new.public_key());
+
+        let old_list_result = self.static_lists_dir.read_list(&old_pubkey);
+        let old_before = old_list_result.unwrap_or_else(|_| FollowList::new(old_pubkey.clone(), "me"));
+        if old_before.succession.is_some() {
+            return Err(format!("{} has already been rotated.", old_pubkey));
         };
-        
-        let file_content = read_result.unwrap();
-        let decode_result = zbase32::decode_full_bytes_str(&file_content);
-        if let Err(e) = decode_result {
+        let mut old_list = old_before.clone();
+        let mut new_list = FollowList::new_with_follows(new_pubkey.clone(), &old_list.alias, std::mem::take(&mut old_list.follows));
+        new_list.blocks = old_list.blocks.clone();
+        old_list.succession = Some(Succession::sign(&old, &new_pubkey, unix_now()));
+
+        // Keep the old secret first so a failure later never loses it.
+        if let Err(e) = fs::create_dir_all(self.get_retired_path()) {
             return Err(e.to_string());
         };
+        let secret_path = self.get_keypair_path().unwrap();
+        let retired_path = self.get_retired_path().join(format!("{}.secret", old_pubkey.to_z32()));
+        SecretFile::from_path(&secret_path)?.write(&retired_path)?;
+
+        // Then the new secret, written atomically. If a list fails afterwards, the follows are still in the old list.
+        let secret = match &self.passphrase {
+            Some(passphrase) => SecretFile::Encrypted(EncryptedSecret::encrypt(&new, passphrase)?),
+            None => SecretFile::Plain(Keypair::from_secret_key(&new.secret_key())),
+        };
+        secret.write(&secret_path)?;
+
+        let new_before = FollowList::new(new_pubkey.clone(), &new_list.alias);
+        self.write_list_with_reason(new_before, new_list, &format!("rotate from {}", old_pubkey))?;
+        self.write_list_with_reason(old_before, old_list, &format!("rotate to {}", new_pubkey))?;
+        Ok(new)
+    }
+
+    /**
+     * Publishes the succession of the key the current identity was rotated from, see `succession::publish_succession`.
+     * Unlocks the retired secret with the passphrase of this directory. Returns the retired key.
+     */
+    pub fn publish_succession(&self, transport: &dyn PkarrTransport) -> Result<PublicKey, String> {
+        let current = self.get_public_key();
+        let entries = fs::read_dir(self.get_retired_path()).map_err(|e| format!("{} has no retired keys. {}", self.perspective, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("secret") {
+                continue;
+            };
+            let secret = SecretFile::from_path(&path)?;
+            let retired = secret.public_key();
+            let list = match self.static_lists_dir.read_list(&retired) {
+                Ok(list) if list.verified_successor() == Some(&current) => list,
+                _ => continue,
+            };
+            let keypair = secret.unlock(self.passphrase.as_deref())?;
+            publish_succession(transport, &keypair, list.succession.as_ref().unwrap())?;
+            return Ok(retired);
+        };
+        Err(format!("{} was not rotated from another key.", self.perspective))
+    }
 
-        let plain_secret = decode_result.unwrap();
-        if plain_secret.len() != SECRET_KEY_LENGTH {
-            return Err(format!("Secret not {} bytes long.", SECRET_KEY_LENGTH));
+    /**
+     * Applies all changes to the list of the current identity or none. See `FollowList::bulk_apply`.
+     */
+    pub fn bulk_apply(&self, changes: Vec<ListChange>) -> Result<Vec<ChangeOutcome>, ListEditError> {
+        let reason = changes.iter().map(|change| change.to_string()).collect::<Vec<String>>().join("; ");
+        self.bulk_apply_with_reason(changes, &reason)
+    }
+
+    /**
+     * Like `bulk_apply` but journals the change with the given reason, for example `training feedback for example.com`.
+     */
+    pub fn bulk_apply_with_reason(&self, changes: Vec<ListChange>, reason: &str) -> Result<Vec<ChangeOutcome>, ListEditError> {
+        if self.is_read_only() {
+            return Err(ListEditError::ReadOnly(format!("The perspective is only the {}. It has no list of its own.", self.perspective)));
+        };
+        let me_pubkey = self.get_public_key();
+        let _lock = self.lock().map_err(ListEditError::Storage)?;
+        let mut me_list = self.static_lists_dir.read_list(&me_pubkey).map_err(ListEditError::Storage)?;
+        let before = me_list.clone();
+        let outcomes = me_list.bulk_apply(changes)?;
+        if outcomes.iter().any(|outcome| outcome.is_change()) {
+            self.write_list_with_reason(before, me_list, reason).map_err(ListEditError::Storage)?;
+        };
+        Ok(outcomes)
+    }
+
+    /**
+     * Writes an authored list and journals the change, so it can be undone. Callers must hold the lock.
+     * All changes of authored lists go through here: edits, reverts, rotations and bundle imports.
+     */
+    pub fn write_list_with_reason(&self, before: FollowList, after: FollowList, reason: &str) -> Result<JournalEntry, String> {
+        if let Err(e) = self.static_lists_dir.write_list(&after.pubkey, after.clone()) {
+            return Err(format!("Failed to write list: {}", e));
+        };
+        self.journal().append(unix_now(), reason, before, after)
+    }
+
+    fn apply_change(&self, change: ListChange) -> Result<ChangeOutcome, ListEditError> {
+        match self.bulk_apply(vec![change]) {
+            Ok(mut outcomes) => Ok(outcomes.remove(0)),
+            Err(ListEditError::Bulk { error, .. }) => Err(*error),
+            Err(e) => Err(e),
         }
-        let slice: &[u8; SECRET_KEY_LENGTH] = &plain_secret[0..SECRET_KEY_LENGTH].try_into().unwrap();
-        let keypair = Keypair::from_secret_key(slice);
+    }
+
+    pub fn upsert_follow(&self, follow: Follow) -> Result<ChangeOutcome, ListEditError> {
+        self.apply_change(ListChange::Upsert(follow))
+    }
+
+    pub fn remove_follow(&self, pubkey: &PublicKey, domain: Option<&str>) -> Result<ChangeOutcome, ListEditError> {
+        self.apply_change(ListChange::Remove { pubkey: pubkey.clone(), domain: domain.map(|domain| domain.to_string()) })
+    }
+
+    pub fn set_weight(&self, pubkey: &PublicKey, domain: Option<&str>, weight: f32) -> Result<ChangeOutcome, ListEditError> {
+        self.apply_change(ListChange::SetWeight { pubkey: pubkey.clone(), domain: domain.map(|domain| domain.to_string()), weight })
+    }
+
+    pub fn block(&self, pubkey: &PublicKey) -> Result<ChangeOutcome, ListEditError> {
+        self.apply_change(ListChange::Block(pubkey.clone()))
+    }
+
+    pub fn unblock(&self, pubkey: &PublicKey) -> Result<ChangeOutcome, ListEditError> {
+        self.apply_change(ListChange::Unblock(pubkey.clone()))
+    }
+
+    pub fn get_journal_path(&self) -> PathBuf {
+        let mut path = self.path.clone();
+        path.push("journal.jsonl");
+        path
+    }
+
+    /**
+     * Journal of all me list changes of all identities.
+     */
+    pub fn journal(&self) -> Journal {
+        Journal::new(self.get_journal_path())
+    }
+
+    /**
+     * Journal entries of the me list of this perspective, oldest first.
+     */
+    pub fn read_history(&self) -> Result<Vec<JournalEntry>, String> {
+        let me_pubkey = self.get_public_key();
+        let entries = self.journal().read()?;
+        Ok(entries.into_iter().filter(|entry| entry.list == me_pubkey).collect())
+    }
+
+    /**
+     * Restores the me list to the state before change `id` which undoes it and all later changes.
+     * The revert is journaled too, so it can be undone again.
+     */
+    pub fn revert(&self, id: u64) -> Result<JournalEntry, String> {
+        if self.is_read_only() {
+            return Err(format!("The perspective is only the {}. It has no list of its own.", self.perspective));
+        };
+        let me_pubkey = self.get_public_key();
+        let _lock = self.lock()?;
+        let entry = self.read_history()?.into_iter().find(|entry| entry.id == id);
+        let entry = entry.ok_or(format!("Change {} not found in the history of {}.", id, me_pubkey))?;
+        let current = self.static_lists_dir.read_list(&me_pubkey)?;
+        self.write_list_with_reason(current, entry.before, &format!("revert {}", id))
+    }
+
+    /**
+     * Reverts the latest change of the me list.
+     */
+    pub fn undo(&self) -> Result<JournalEntry, String> {
+        let latest = self.read_history()?.pop();
+        let latest = latest.ok_or("No changes to undo.".to_string())?;
+        self.revert(latest.id)
+    }
+
+    /**
+     * Names of all identities with a secret key. Sorted, the default identity first.
+     */
+    pub fn list_identities(&self) -> Vec<String> {
+        let mut names = vec![];
+        if self.get_identity_path(DEFAULT_IDENTITY).exists() {
+            names.push(DEFAULT_IDENTITY.to_string());
+        };
+        let entries = fs::read_dir(self.get_identities_path());
+        if let Ok(entries) = entries {
+            let mut others: Vec<String> = entries.filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "secret" {
+                    return None;
+                };
+                Some(path.file_stem()?.to_str()?.to_string())
+            }).collect();
+            others.sort();
+            names.extend(others);
+        };
+        names
+    }
+
+    /**
+     * Creates a new identity with a random keypair and an empty me list.
+     */
+    pub fn create_identity(&self, name: &str) -> Result<Keypair, String> {
+        validate_identity_name(name)?;
+        let _lock = self.lock()?;
+        let path = self.get_identity_path(name);
+        if path.exists() {
+            return Err(format!("Identity \"{}\" already exists.", name));
+        };
+        if let Err(e) = fs::create_dir_all(self.get_identities_path()) {
+            return Err(e.to_string());
+        };
+        let keypair = write_random_keypair(&path, self.passphrase.as_deref())?;
+        if let Err(e) = self.static_lists_dir.create_if_it_does_not_exist(Some(&PublicKey::from(&keypair.public_key()))) {
+            return Err(e.to_string());
+        };
         Ok(keypair)
     }
 
@@ -127,40 +424,299 @@ impl MainDirectory {
      * Creates a random keypair and writes it to the disk
      */
     pub fn create_random_keypair(&self) -> Result<Keypair, String> {
-        let keypair = Keypair::random();
-        let encoded = zbase32::encode_full_bytes(&keypair.secret_key());
-
         let path = self.get_keypair_path();
-        let result = fs::write(path, encoded);
-        
-        match result {
-            Ok(_) => Ok(keypair),
-            Err(e) => Err(e.to_string())
-        }
+        if path.is_none() {
+            return Err(format!("Can't create a secret key. The perspective is only the {}.", self.perspective));
+        };
+        write_random_keypair(&path.unwrap(), self.passphrase.as_deref())
     }
 
     /**
      * Reads the keypair from the disk or if it does not exist, creates one.
+     * Only the default identity is created on the fly. Other identities must be created with `create_identity`.
      */
     pub fn read_or_create_keypair(&self) -> Keypair {
-        if self.get_keypair_path().exists() {
-            self.read_keypair().unwrap()
-        } else {
-            self.create_random_keypair().unwrap()
-        }
+        let is_default = self.perspective == Perspective::default();
+        if is_default && !self.get_identity_path(DEFAULT_IDENTITY).exists() {
+            self.create_default_secret().unwrap();
+        };
+        self.read_keypair().unwrap()
+    }
+
+    /**
+     * Creates the secret of the default identity if it still doesn't exist once the lock is held.
+     * Concurrent first runs would otherwise each write their own key and disagree on who me is.
+     */
+    fn create_default_secret(&self) -> Result<(), String> {
+        let _lock = self.lock()?;
+        let path = self.get_identity_path(DEFAULT_IDENTITY);
+        if path.exists() {
+            return Ok(());
+        };
+        write_random_keypair(&path, self.passphrase.as_deref())?;
+        Ok(())
+    }
+
+    pub fn get_config_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push("/config.json");
+        PathBuf::from(path)
+    }
+
+    /**
+     * Reads the config from the disk. Falls back to the default config if the file does not exist.
+     */
+    pub fn read_config(&self) -> Result<Config, String> {
+        let path = self.get_config_path();
+        if !path.exists() {
+            return Ok(Config::default());
+        };
+        Config::from_path(&path)
+    }
+
+    /**
+     * Writes the config to the disk. Callers must hold the lock, see `update_config`.
+     */
+    pub fn write_config(&self, config: &Config) -> Result<(), std::io::Error> {
+        write_atomic(&self.get_config_path(), config.to_json().as_bytes())
+    }
+
+    /**
+     * Changes the config under the directory lock. Nothing is written if `change` fails.
+     */
+    pub fn update_config<T>(&self, change: impl FnOnce(&mut Config) -> Result<T, String>) -> Result<T, String> {
+        let _lock = self.lock()?;
+        let mut config = self.read_config()?;
+        let result = change(&mut config)?;
+        self.write_config(&config).map_err(|e| format!("Failed to write config: {}", e))?;
+        Ok(result)
+    }
+
+    pub fn get_pins_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push("/pins.json");
+        PathBuf::from(path)
+    }
+
+    /**
+     * Reads the pins from the disk. Returns an empty store if the file does not exist.
+     */
+    pub fn read_pins(&self) -> Result<PinStore, String> {
+        let path = self.get_pins_path();
+        if !path.exists() {
+            return Ok(PinStore::default());
+        };
+        PinStore::from_path(&path)
+    }
+
+    /**
+     * Writes the pins to the disk. Callers must hold the lock, see `update_pins`.
+     */
+    pub fn write_pins(&self, pins: &PinStore) -> Result<(), std::io::Error> {
+        write_atomic(&self.get_pins_path(), pins.to_json().as_bytes())
+    }
+
+    /**
+     * Changes the pins under the directory lock. Nothing is written if `change` fails.
+     */
+    pub fn update_pins<T>(&self, change: impl FnOnce(&mut PinStore) -> Result<T, String>) -> Result<T, String> {
+        let _lock = self.lock()?;
+        let mut pins = self.read_pins()?;
+        let result = change(&mut pins)?;
+        self.write_pins(&pins).map_err(|e| format!("Failed to write pins: {}", e))?;
+        Ok(result)
+    }
+
+    pub fn get_petnames_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push("/petnames.json");
+        PathBuf::from(path)
+    }
+
+    /**
+     * Reads the petname book from the disk. Returns an empty book if the file does not exist.
+     */
+    pub fn read_petnames(&self) -> Result<PetnameBook, String> {
+        let path = self.get_petnames_path();
+        if !path.exists() {
+            return Ok(PetnameBook::default());
+        };
+        PetnameBook::from_path(&path)
+    }
+
+    /**
+     * Writes the petname book to the disk. Callers must hold the lock, see `update_petnames`.
+     */
+    pub fn write_petnames(&self, petnames: &PetnameBook) -> Result<(), std::io::Error> {
+        write_atomic(&self.get_petnames_path(), petnames.to_json().as_bytes())
+    }
+
+    /**
+     * Changes the petname book under the directory lock. Nothing is written if `change` fails.
+     */
+    pub fn update_petnames<T>(&self, change: impl FnOnce(&mut PetnameBook) -> Result<T, String>) -> Result<T, String> {
+        let _lock = self.lock()?;
+        let mut petnames = self.read_petnames()?;
+        let result = change(&mut petnames)?;
+        self.write_petnames(&petnames).map_err(|e| format!("Failed to write petnames: {}", e))?;
+        Ok(result)
+    }
+
+    /**
+     * Petnames and identities plus the nicknames and pubkeys of all valid lists.
+     */
+    pub fn read_key_names(&self) -> Result<KeyNames, String> {
+        let petnames = self.read_petnames()?;
+        let lists = self.static_lists_dir.read_valid_lists();
+        let mut names = KeyNames::new(&self.get_public_key(), petnames, &lists);
+        for name in self.list_identities() {
+            let secret = SecretFile::from_path(&self.get_identity_path(&name))?;
+            names.add_identity(&name, &secret.public_key());
+        };
+        Ok(names)
+    }
+
+    /**
+     * Pins that apply to the perspective. Pins are yours, so a public key perspective has none.
+     * It shows what the key would see without your local choices.
+     */
+    pub fn read_perspective_pins(&self) -> Result<PinStore, String> {
+        if self.is_read_only() {
+            return Ok(PinStore::default());
+        };
+        self.read_pins()
+    }
+
+    /**
+     * Like `read_key_names` but a public key perspective only gets the nicknames of the lists, not your petnames and identities.
+     */
+    pub fn read_perspective_key_names(&self) -> Result<KeyNames, String> {
+        if self.is_read_only() {
+            let lists = self.static_lists_dir.read_valid_lists();
+            return Ok(KeyNames::new(&self.get_public_key(), PetnameBook::default(), &lists));
+        };
+        self.read_key_names()
+    }
+
+    /**
+     * Public keys of all local identities, including retired ones. Their lists count as authored.
+     */
+    pub fn authored_pubkeys(&self) -> Vec<PublicKey> {
+        let mut pubkeys: Vec<PublicKey> = self.list_identities().iter()
+            .filter_map(|name| SecretFile::from_path(&self.get_identity_path(name)).ok())
+            .map(|secret| secret.public_key())
+            .collect();
+        if let Ok(entries) = fs::read_dir(self.get_retired_path()) {
+            pubkeys.extend(entries.filter_map(|entry| SecretFile::from_path(&entry.ok()?.path()).ok()).map(|secret| secret.public_key()));
+        };
+        pubkeys
+    }
+
+    /**
+     * All valid lists with their provenance.
+     */
+    pub fn read_stored_lists(&self) -> Vec<StoredList> {
+        let lists = self.static_lists_dir.read_stored_lists(&self.authored_pubkeys());
+        if lists.is_err() {
+            return vec![];
+        };
+        lists.unwrap().into_iter().filter_map(|list| list.ok()).collect()
+    }
+
+    /**
+     * Imports the list of somebody else from a file. Replaces an earlier import of the same list.
+     */
+    pub fn import_list(&self, path: &Path) -> Result<FollowList, String> {
+        let list = FollowList::from_path(path)?;
+        if self.authored_pubkeys().contains(&list.pubkey) {
+            return Err(format!("{} is the list of a local identity. Only lists of others can be imported.", list.pubkey));
+        };
+        let origin = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+        let provenance = Provenance {
+            origin: Some(origin.to_string_lossy().to_string()),
+            fetched: Some(unix_now()),
+            ..Provenance::new(ListSource::Imported)
+        };
+        let _lock = self.lock()?;
+        self.static_lists_dir.write_imported_list(&list, &provenance).map_err(|e| format!("Failed to write list: {}", e))?;
+        Ok(list)
+    }
+
+    /**
+     * Graph of all valid lists the config accepts. Drops expired follows, applies the configured decay and normalization.
+     * The list of the perspective is always used.
+     */
+    pub fn read_graph(&self) -> Result<WotGraph, String> {
+        Ok(self.read_graph_and_lists()?.0)
+    }
+
+    /**
+     * Like `read_graph`. Also returns what was read to build it: all valid lists, whether the config accepts them or not,
+     * the files that failed to parse and the ignored ones. See `LoadedLists::problems`.
+     */
+    pub fn read_graph_and_lists(&self) -> Result<(WotGraph, LoadedLists), String> {
+        let config = self.read_config()?;
+        let me_pubkey = self.get_public_key();
+        let loaded = self.static_lists_dir.load_lists(&self.authored_pubkeys())
+            .map_err(|e| format!("Failed to read lists \"{}\". {}", self.static_lists_dir.path.to_str().unwrap(), e))?;
+        let lists: Vec<FollowList> = loaded.lists.iter()
+            .filter(|stored| stored.list.pubkey == me_pubkey || config.lists.accepts(&stored.provenance))
+            .map(|stored| stored.list.clone())
+            .collect();
+        let mut graph = lists_into_graph(lists, unix_now(), config.decay.half_life_secs());
+        if config.weights.normalize {
+            graph.normalize_follow_weights();
+        };
+        Ok((graph, loaded))
     }
 
     /**
      * zbase32 public key in format pk:...
      */
     pub fn get_public_key_uri(&self) -> String {
-        let pair = self.read_or_create_keypair();
-        pair.to_uri_string()
+        self.get_public_key().to_string()
+    }
+
+    /**
+     * Public key of the perspective. The root of the web of trust.
+     * Doesn't need the passphrase, encrypted secrets store the public key in plain text.
+     */
+    pub fn get_public_key(&self) -> PublicKey {
+        if let Perspective::Identity(name) = &self.perspective {
+            if name == DEFAULT_IDENTITY && !self.get_identity_path(name).exists() {
+                self.create_default_secret().unwrap();
+            };
+        };
+        self.read_public_key().unwrap()
+    }
+
+    /**
+     * Like `get_public_key` but never creates a secret. Fails if the secret is missing or corrupt.
+     */
+    pub fn read_public_key(&self) -> Result<PublicKey, String> {
+        match &self.perspective {
+            Perspective::PublicKey(pubkey) => Ok(pubkey.clone()),
+            Perspective::Identity(_) => Ok(self.read_secret()?.public_key()),
+        }
     }
 }
 
+/**
+ * Writes a new keypair readable by the owner only. Encrypted if a passphrase is given.
+ */
+fn write_random_keypair(path: &Path, passphrase: Option<&str>) -> Result<Keypair, String> {
+    let keypair = Keypair::random();
+    let secret = match passphrase {
+        Some(passphrase) => SecretFile::Encrypted(EncryptedSecret::encrypt(&keypair, passphrase)?),
+        None => SecretFile::Plain(Keypair::from_secret_key(&keypair.secret_key())),
+    };
+    secret.write(path)?;
+    Ok(keypair)
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::{config_directory::{config::{Config, TransportConfig}, pins::Pin, perspective::Perspective, follow::Follow, list_edit::{ListChange, ChangeOutcome, ListEditError}, follow_list::FollowList, provenance::ListSource, succession::Succession}, public_key::PublicKey, transport::{memory_transport::MemoryTransport, pkarr_transport::PkarrTransport}};
     use super::MainDirectory;
 
     #[test]
@@ -172,6 +728,7 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(config.path.exists(), true);
         assert_eq!(config.static_lists_dir.path.exists(), true);
+        assert!(config.packet_cache_dir.path.exists());
         config.delete().unwrap();
         assert_eq!(config.path.exists(), false);
     }
@@ -186,10 +743,304 @@ mod tests {
         assert_eq!(keypair.secret_key(), read_keypair.secret_key());
     }
 
+    #[test]
+    fn write_and_read_config() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_write_and_read_config");
+        let _ = main.delete();
+        main.create_if_it_does_not_exist().unwrap();
+        assert_eq!(main.read_config().unwrap().transport, TransportConfig::Dht);
+
+        let config = Config { transport: TransportConfig::Relay { relays: vec!["http://localhost:6881".to_string()] }, ..Default::default() };
+        main.write_config(&config).unwrap();
+        assert_eq!(main.read_config().unwrap().transport, config.transport);
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn write_and_read_pins() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_write_and_read_pins");
+        let _ = main.delete();
+        main.create_if_it_does_not_exist().unwrap();
+        assert!(main.read_pins().unwrap().pins.is_empty());
+
+        let mut pins = main.read_pins().unwrap();
+        pins.pin(Pin::new("example.com", "pk:abc", None));
+        main.write_pins(&pins).unwrap();
+        assert_eq!(main.read_pins().unwrap().get("example.com").unwrap().pubkey, "pk:abc");
+        main.delete().unwrap();
+    }
+
     #[test]
     fn read_or_create_keypair() {
-        let main = MainDirectory::new_by_string("/tmp/fancydns827209438");
+        let main = MainDirectory::new_by_string("/tmp/fancydns_read_or_create_keypair");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.create_if_it_does_not_exist().unwrap();
         let keypair = main.read_or_create_keypair();
         println!("{}", keypair.to_z32())
     }
+
+    #[test]
+    fn create_and_list_identities() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_create_and_list_identities");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.create_if_it_does_not_exist().unwrap();
+        let keypair = main.create_identity("org").unwrap();
+        assert!(main.create_identity("org").is_err());
+        assert!(main.create_identity("../org").is_err());
+        assert_eq!(main.list_identities(), vec!["default", "org"]);
+
+        let org = MainDirectory::new_with_perspective(main.path.clone(), Perspective::Identity("org".to_string()));
+        assert_eq!(org.get_public_key_uri(), keypair.to_uri_string());
+        assert_ne!(org.get_public_key(), main.get_public_key());
+        assert!(org.static_lists_dir.read_list(&org.get_public_key()).is_ok());
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn public_key_perspective() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_public_key_perspective");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let dir = MainDirectory::new_with_perspective(main.path.clone(), Perspective::PublicKey(alice.clone()));
+        dir.create_if_it_does_not_exist().unwrap();
+        assert!(dir.is_read_only());
+        assert_eq!(dir.get_public_key(), alice);
+        assert!(dir.read_keypair().is_err());
+        assert!(dir.static_lists_dir.read_list(&alice).is_err()); // No list is created in the name of somebody else.
+        assert!(main.list_identities().is_empty());
+
+        // Pins and petnames of the directory are not applied to the perspective.
+        let mut pins = main.read_pins().unwrap();
+        pins.pin(Pin::new("example.com", "pk:abc", None));
+        main.write_pins(&pins).unwrap();
+        let mut petnames = main.read_petnames().unwrap();
+        petnames.set("alice", &alice).unwrap();
+        main.write_petnames(&petnames).unwrap();
+        assert!(main.read_perspective_pins().unwrap().get("example.com").is_some());
+        assert!(dir.read_perspective_pins().unwrap().get("example.com").is_none());
+        assert!(dir.read_perspective_key_names().unwrap().resolve_input("alice").is_err());
+        assert!(main.read_perspective_key_names().unwrap().resolve_input("alice").is_ok());
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn encrypt_secret() {
+        let mut main = MainDirectory::new_by_string("/tmp/fancydns_encrypt_secret");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.passphrase = None;
+        main.create_if_it_does_not_exist().unwrap();
+        let pubkey = main.get_public_key();
+        assert!(!main.read_secret().unwrap().is_encrypted());
+
+        main.encrypt_secret("correct horse").unwrap();
+        assert!(main.read_secret().unwrap().is_encrypted());
+        assert_eq!(main.get_public_key(), pubkey); // No passphrase needed.
+        assert!(main.read_keypair().is_err());
+
+        main.passphrase = Some("correct horse".to_string());
+        assert_eq!(PublicKey::from(&main.read_keypair().unwrap().public_key()), pubkey);
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn rotate_keypair() {
+        let mut main = MainDirectory::new_by_string("/tmp/fancydns_rotate_keypair");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.passphrase = None;
+        main.create_if_it_does_not_exist().unwrap();
+        let old_pubkey = main.get_public_key();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let mut list = main.static_lists_dir.read_list(&old_pubkey).unwrap();
+        list.follows.push(Follow::new(alice.clone(), 1.0, None));
+        main.static_lists_dir.write_list(&old_pubkey, list).unwrap();
+
+        let new = main.rotate_keypair().unwrap();
+        let new_pubkey = main.get_public_key();
+        assert_eq!(new_pubkey, new.to_uri_string());
+        assert_ne!(new_pubkey, old_pubkey);
+        assert!(main.get_retired_path().join(format!("{}.secret", old_pubkey.to_z32())).exists());
+
+        let old_list = main.static_lists_dir.read_list(&old_pubkey).unwrap();
+        assert_eq!(old_list.verified_successor(), Some(&new_pubkey));
+        assert!(old_list.follows.is_empty());
+        assert!(main.static_lists_dir.read_list(&new_pubkey).unwrap().is_following(&alice));
+
+        let graph = main.read_graph().unwrap();
+        assert!(graph.get_follow(&old_pubkey, &new_pubkey).is_some());
+
+        // Both lists are journaled.
+        let history = main.read_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].reason, format!("rotate from {}", old_pubkey));
+        let journal = main.journal().read().unwrap();
+        assert!(journal.iter().any(|entry| entry.list == old_pubkey && entry.after.succession.is_some() && entry.before.is_following(&alice)));
+
+        // The succession goes out signed by the old key.
+        let transport = MemoryTransport::new();
+        assert_eq!(main.publish_succession(&transport), Ok(old_pubkey.clone()));
+        let packet = transport.resolve_most_recent(&old_pubkey.to_pkarr().unwrap()).unwrap().unwrap();
+        assert_eq!(Succession::from_packet(&packet).unwrap().unwrap().successor, new_pubkey);
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn edit_me_list() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_edit_me_list");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.create_if_it_does_not_exist().unwrap();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        assert_eq!(main.upsert_follow(Follow::new(alice.clone(), 1.0, None)), Ok(ChangeOutcome::Added));
+        assert!(main.set_weight(&alice, None, 0.5).is_ok());
+        assert_eq!(main.remove_follow(&alice, Some("example.com")), Err(ListEditError::FollowNotFound { pubkey: alice.clone(), domain: Some("example.com".to_string()) }));
+        let me_list = main.static_lists_dir.read_list(&main.get_public_key()).unwrap();
+        assert_eq!(*me_list.follows[0].weight(), 0.5);
+
+        let read_only = MainDirectory::new_with_perspective(main.path.clone(), Perspective::PublicKey(alice.clone()));
+        assert!(matches!(read_only.block(&alice), Err(ListEditError::ReadOnly(_))));
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn history_undo_and_revert() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_history_undo_and_revert");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.create_if_it_does_not_exist().unwrap();
+        assert!(main.undo().is_err());
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let dave = PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap();
+        main.upsert_follow(Follow::new(alice.clone(), 1.0, None)).unwrap();
+        main.set_weight(&alice, None, 0.5).unwrap();
+        main.set_weight(&alice, None, 0.5).unwrap(); // Unchanged, not journaled.
+        main.bulk_apply_with_reason(vec![ListChange::Upsert(Follow::new(dave.clone(), -1.0, None))], "training feedback").unwrap();
+
+        let history = main.read_history().unwrap();
+        assert_eq!(history.iter().map(|entry| entry.id).collect::<Vec<u64>>(), vec![1, 2, 3]);
+        assert_eq!(history[1].reason, format!("set weight {} 0.5", alice));
+        assert_eq!(history[2].reason, "training feedback");
+
+        let read_list = || main.static_lists_dir.read_list(&main.get_public_key()).unwrap();
+        main.undo().unwrap();
+        assert_eq!(read_list().follows.len(), 1);
+        main.undo().unwrap(); // Undoing the undo restores the change.
+        assert_eq!(read_list().follows.len(), 2);
+
+        main.revert(2).unwrap();
+        let me_list = read_list();
+        assert_eq!(me_list.follows.len(), 1);
+        assert_eq!(*me_list.follows[0].weight(), 1.0);
+        assert_eq!(main.read_history().unwrap().last().unwrap().reason, "revert 2");
+        assert!(main.revert(99).is_err());
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn import_list_and_filter_graph() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_import_list_and_filter_graph");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.create_if_it_does_not_exist().unwrap();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let dave = PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap();
+        main.upsert_follow(Follow::new(alice.clone(), 1.0, None)).unwrap();
+        let list_path = main.path.join("alice.json");
+        std::fs::write(&list_path, FollowList::new_with_follows(alice.clone(), "alice", vec![Follow::new(dave.clone(), 1.0, Some("example.com"))]).to_json()).unwrap();
+        main.import_list(&list_path).unwrap();
+
+        let own_list_path = main.path.join("own.json");
+        std::fs::write(&own_list_path, FollowList::new(main.get_public_key(), "me").to_json()).unwrap();
+        assert!(main.import_list(&own_list_path).is_err());
+
+        let stored = main.read_stored_lists();
+        assert_eq!(stored.iter().map(|stored| stored.provenance.source).collect::<Vec<ListSource>>(), vec![ListSource::Authored, ListSource::Imported]);
+        assert!(stored[1].provenance.origin.as_ref().unwrap().ends_with("alice.json"));
+        assert!(main.read_graph().unwrap().get_follow(&alice, &dave).is_some());
+
+        let mut config = main.read_config().unwrap();
+        config.lists.sources = vec![ListSource::Fetched];
+        main.write_config(&config).unwrap();
+        let graph = main.read_graph().unwrap();
+        assert!(graph.get_follow(&main.get_public_key(), &alice).is_some());
+        assert!(graph.get_follow(&alice, &dave).is_none());
+
+        // A list of dave named after alice is reported and not used.
+        std::fs::write(main.static_lists_dir.path.join(format!("{}.json", alice.to_z32())), FollowList::new(dave.clone(), "dave").to_json()).unwrap();
+        let (graph, loaded) = main.read_graph_and_lists().unwrap();
+        assert_eq!(loaded.rejected.len(), 1);
+        assert_eq!(loaded.rejected[0].path(), main.static_lists_dir.path.join(format!("{}.json", alice.to_z32())));
+        assert!(graph.get_follow(&main.get_public_key(), &alice).is_some());
+
+        // Files that fail to parse are returned too.
+        std::fs::write(main.static_lists_dir.path.join("broken.json"), "[").unwrap();
+        let (_, loaded) = main.read_graph_and_lists().unwrap();
+        assert_eq!(loaded.errors.len(), 1);
+        assert_eq!(loaded.problems().len(), 2);
+
+        // Failing to read the lists is an error, not an empty graph.
+        std::fs::remove_dir_all(&main.static_lists_dir.path).unwrap();
+        std::fs::write(&main.static_lists_dir.path, "").unwrap();
+        assert!(main.read_graph().is_err());
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn concurrent_pins_petnames_and_config_are_not_lost() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_concurrent_pins_petnames_and_config_are_not_lost");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.create_if_it_does_not_exist().unwrap();
+        let threads: Vec<_> = (0..8).map(|i| {
+            let path = main.path.clone();
+            std::thread::spawn(move || {
+                let dir = MainDirectory::new(path);
+                let pubkey = PublicKey::from(&pkarr::Keypair::random().public_key());
+                dir.update_pins(|pins| { pins.pin(Pin::new(&format!("example{}.com", i), &pubkey, None)); Ok(()) }).unwrap();
+                dir.update_petnames(|petnames| petnames.set(&format!("name{}", i), &pubkey)).unwrap();
+                dir.update_config(|config| { config.resolve.min_trust += 1.0; Ok(()) }).unwrap();
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        };
+        assert_eq!(main.read_pins().unwrap().pins.len(), 8);
+        assert_eq!(main.read_petnames().unwrap().petnames.len(), 8);
+        assert_eq!(main.read_config().unwrap().resolve.min_trust, Config::default().resolve.min_trust + 8.0);
+
+        // A failed change writes nothing.
+        assert!(main.update_pins(|pins| { pins.pins.clear(); Err::<(), String>("abort".to_string()) }).is_err());
+        assert_eq!(main.read_pins().unwrap().pins.len(), 8);
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn concurrent_edits_are_not_lost() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_concurrent_edits_are_not_lost");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        main.create_if_it_does_not_exist().unwrap();
+        let threads: Vec<_> = (0..8).map(|_| {
+            let path = main.path.clone();
+            std::thread::spawn(move || {
+                let dir = MainDirectory::new(path);
+                let pubkey = PublicKey::from(&pkarr::Keypair::random().public_key());
+                dir.upsert_follow(Follow::new(pubkey, 1.0, None)).unwrap();
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        };
+        let me_list = main.static_lists_dir.read_list(&main.get_public_key()).unwrap();
+        assert_eq!(me_list.follows.len(), 8);
+        main.delete().unwrap();
+    }
+
+    #[test]
+    fn concurrent_first_runs_agree_on_the_key() {
+        let main = MainDirectory::new_by_string("/tmp/fancydns_concurrent_first_runs_agree_on_the_key");
+        let _ = main.delete(); // Delete so the test can work again even though it failed before.
+        std::fs::create_dir(&main.path).unwrap();
+        let threads: Vec<_> = (0..8).map(|_| {
+            let path = main.path.clone();
+            std::thread::spawn(move || MainDirectory::new(path).get_public_key())
+        }).collect();
+        let pubkeys: Vec<PublicKey> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+        assert!(pubkeys.iter().all(|pubkey| *pubkey == main.get_public_key()));
+        main.delete().unwrap();
+    }
 }
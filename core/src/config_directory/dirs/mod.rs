@@ -1,2 +1,3 @@
 pub mod main_directory;
-pub mod static_lists_directory;
\ No newline at end of file
+pub mod static_lists_directory;
+pub mod packet_cache_directory;
@@ -0,0 +1,184 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use pkarr::{PublicKey, SignedPacket};
+
+use crate::config_directory::storage::write_atomic;
+use crate::transport::pkarr_transport::{packet_from_bytes, packet_to_bytes};
+
+
+/**
+ * Persistent cache of resolved signed packets. One `<z32 pubkey>.packet` file per key.
+ * The modification time of a file is the time the packet was last fetched.
+ */
+#[derive(Clone)]
+pub struct PacketCacheDirectory {
+    pub path: PathBuf,
+}
+
+impl PacketCacheDirectory {
+    pub fn new(path: PathBuf) -> Self {
+        PacketCacheDirectory { path }
+    }
+
+    /**
+     * Creates the directory if it does not exist.
+     */
+    pub fn create_if_it_does_not_exist(&self) -> Result<(), std::io::Error> {
+        if self.path.exists() && self.path.is_file() {
+            return Err(std::io::Error::other("Expected directory."));
+        } else if !self.path.exists() {
+            fs::create_dir(&self.path)?;
+        };
+        Ok(())
+    }
+
+    /**
+     * Deletes the whole directory tree. Careful!
+     */
+    pub fn delete(&self) -> Result<(), std::io::Error> {
+        fs::remove_dir_all(&self.path)
+    }
+
+    fn derive_filename(&self, pubkey: &PublicKey) -> PathBuf {
+        self.path.join(format!("{}.packet", pubkey.to_z32()))
+    }
+
+    /**
+     * Reads a cached packet and verifies its signature. Ok(None) if the key is not cached.
+     */
+    pub fn read_packet(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        let path = self.derive_filename(pubkey);
+        if !path.exists() {
+            return Ok(None);
+        };
+        let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+        let packet = packet_from_bytes(pubkey, &bytes);
+        if let Err(e) = packet {
+            return Err(format!("Invalid cached packet \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        Ok(Some(packet.unwrap()))
+    }
+
+    /**
+     * Reads a cached packet only if all of its records are still within their TTL.
+     */
+    pub fn read_fresh_packet(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        let packet = self.read_packet(pubkey)?;
+        if packet.is_none() {
+            return Ok(None);
+        };
+        let packet = packet.unwrap();
+        let fetched_at = fs::metadata(self.derive_filename(pubkey))
+            .and_then(|meta| meta.modified())
+            .map_err(|e| e.to_string())?;
+        let age = SystemTime::now().duration_since(fetched_at).unwrap_or(Duration::ZERO);
+        if age < Self::min_ttl(&packet) {
+            Ok(Some(packet))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /**
+     * Lowest TTL of all records in the packet. Zero for an empty packet.
+     */
+    pub fn min_ttl(packet: &SignedPacket) -> Duration {
+        let ttl = packet.packet().answers.iter().map(|answer| answer.ttl).min().unwrap_or(0);
+        Duration::from_secs(ttl.into())
+    }
+
+    /**
+     * Writes a packet to the cache and marks it as freshly fetched.
+     * Refuses to replace a cached packet with an older timestamp. Returns false in that case.
+     * The file is replaced atomically so a crash never leaves a truncated packet behind.
+     */
+    pub fn write_packet(&self, packet: &SignedPacket) -> Result<bool, String> {
+        if let Ok(Some(existing)) = self.read_packet(packet.public_key()) {
+            if existing.timestamp() > packet.timestamp() {
+                return Ok(false);
+            }
+        };
+        let path = self.derive_filename(packet.public_key());
+        write_atomic(&path, &packet_to_bytes(packet)).map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use pkarr::{dns::{Name, Packet, ResourceRecord, rdata::{RData, A}, CLASS}, Keypair, SignedPacket};
+    use super::PacketCacheDirectory;
+
+    fn create_packet(keypair: &Keypair, ttl: u32) -> SignedPacket {
+        let mut packet = Packet::new_reply(0);
+        packet.answers.push(ResourceRecord::new(Name::new("test").unwrap(), CLASS::IN, ttl, RData::A(A { address: 0x7f000001 })));
+        SignedPacket::from_packet(keypair, &packet).unwrap()
+    }
+
+    fn create_cache(name: &str) -> PacketCacheDirectory {
+        let cache = PacketCacheDirectory::new(PathBuf::from(format!("/tmp/pknames_packet_cache_{}", name)));
+        let _ = cache.delete(); // Delete so the test can work again even though it failed before.
+        cache.create_if_it_does_not_exist().unwrap();
+        cache
+    }
+
+    #[test]
+    fn write_and_read() {
+        let cache = create_cache("write_and_read");
+        let keypair = Keypair::random();
+        assert!(cache.read_packet(&keypair.public_key()).unwrap().is_none());
+
+        let packet = create_packet(&keypair, 60);
+        assert!(cache.write_packet(&packet).unwrap());
+        let cached = cache.read_packet(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(cached.timestamp(), packet.timestamp());
+        cache.delete().unwrap();
+    }
+
+    #[test]
+    fn refuse_older_packet() {
+        let cache = create_cache("refuse_older_packet");
+        let keypair = Keypair::random();
+        let older = create_packet(&keypair, 60);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let newer = create_packet(&keypair, 60);
+
+        assert!(cache.write_packet(&newer).unwrap());
+        assert!(!cache.write_packet(&older).unwrap());
+        let cached = cache.read_packet(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(cached.timestamp(), newer.timestamp());
+        cache.delete().unwrap();
+    }
+
+    #[test]
+    fn reject_tampered_packet() {
+        let cache = create_cache("reject_tampered_packet");
+        let keypair = Keypair::random();
+        cache.write_packet(&create_packet(&keypair, 60)).unwrap();
+
+        let path = cache.derive_filename(&keypair.public_key());
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(cache.read_packet(&keypair.public_key()).is_err());
+        cache.delete().unwrap();
+    }
+
+    #[test]
+    fn freshness_by_ttl() {
+        let cache = create_cache("freshness_by_ttl");
+        let fresh_keypair = Keypair::random();
+        cache.write_packet(&create_packet(&fresh_keypair, 60)).unwrap();
+        assert!(cache.read_fresh_packet(&fresh_keypair.public_key()).unwrap().is_some());
+
+        let expired_keypair = Keypair::random();
+        cache.write_packet(&create_packet(&expired_keypair, 0)).unwrap();
+        assert!(cache.read_fresh_packet(&expired_keypair.public_key()).unwrap().is_none());
+        assert!(cache.read_packet(&expired_keypair.public_key()).unwrap().is_some());
+        cache.delete().unwrap();
+    }
+}
@@ -1,9 +1,21 @@
-use std::fs;
+use std::{collections::HashMap, fmt, fs};
 use std::path::PathBuf;
 
-use crate::config_directory::follow_list::FollowList;
+use std::path::Path;
+use crate::config_directory::{follow_list::FollowList, storage::write_atomic};
+use crate::config_directory::provenance::{metadata_path, ListSource, Provenance, StoredList};
+use crate::public_key::PublicKey;
 
 
+/**
+ * Lists of the web of trust, stored by where they come from:
+ * - `<z32>.json` Lists authored by the local identities. Lists of others copied in by hand count as imported.
+ * - `imported/<z32>.json` Lists imported with `import-list`.
+ * - `fetched/<z32>.json` Downloaded lists, a cache.
+ *
+ * Imported and fetched lists keep their `Provenance` in `<z32>.meta`. Lists must be named after their public key,
+ * see `load_lists`.
+ */
 pub struct StaticListsDirectory {
     pub path: PathBuf,
 }
@@ -17,9 +29,9 @@ impl StaticListsDirectory {
     }
 
     /**
-     * Creates the directory if it does not exist.
+     * Creates the directory if it does not exist. Also creates an empty me list for `me_pubkey` if it is given.
      */
-    pub fn create_if_it_does_not_exist(&self, me_pubkey: &str) -> Result<(), std::io::Error> {
+    pub fn create_if_it_does_not_exist(&self, me_pubkey: Option<&PublicKey>) -> Result<(), std::io::Error> {
         if self.path.exists() && self.path.is_file() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
@@ -31,10 +43,12 @@ impl StaticListsDirectory {
 
 
         // Create default me list
-        let me_list_path = self.derive_filename(me_pubkey);
-        if !me_list_path.exists() {
-            let new_list = FollowList::new(me_pubkey, "me");
-            self.write_list(me_pubkey, new_list)?;
+        if let Some(me_pubkey) = me_pubkey {
+            let me_list_path = self.derive_filename(me_pubkey);
+            if !me_list_path.exists() {
+                let new_list = FollowList::new(me_pubkey.clone(), "me");
+                self.write_list(me_pubkey, new_list)?;
+            };
         };
 
         Ok(())
@@ -48,7 +62,7 @@ impl StaticListsDirectory {
     }
 
     /**
-     * Returns the lists that can be read sucessfully.
+     * Returns the lists that can be read sucessfully. Skips the others, use `load_lists` to report them.
      */
     pub fn read_valid_lists(&self) -> Vec<FollowList> {
         let result = self.read_lists();
@@ -68,88 +82,278 @@ impl StaticListsDirectory {
         valid_lists
     }
 
+    /**
+     * All list files. Files that fail to parse or are rejected by `load_lists` are returned as error.
+     */
     pub fn read_lists(&self) -> Result<Vec<Result<FollowList, String>>, std::io::Error> {
-        let paths = fs::read_dir(&self.path)?;
-
-        let paths: Vec<PathBuf> = paths
-            .map(|entry| {
-                let path = entry.unwrap().path();
-                path
-            })
-            .filter(|path| {
-                let extension_opt = path.extension();
-                if let None = extension_opt {
-                    return false;
-                };
-                let extension_opt = extension_opt.unwrap().to_str();
-                if let None = extension_opt {
-                    return false;
-                };
-                let extension = extension_opt.unwrap();
-
-                let matching = extension == "json";
-                matching
-            })
-            .collect();
-
-        let lists: Vec<Result<FollowList, String>> = paths
+        let lists = self.read_stored_lists(&[])?
             .into_iter()
-            .map(|path| {
-                FollowList::from_path(&path)
-            })
+            .map(|stored| stored.map(|stored| stored.list))
             .collect();
+        Ok(lists)
+    }
 
+    /**
+     * Like `read_lists` but with the provenance of every list. Lists in the main folder are authored if their pubkey is in `authored`.
+     */
+    pub fn read_stored_lists(&self, authored: &[PublicKey]) -> Result<Vec<Result<StoredList, String>>, std::io::Error> {
+        let loaded = self.load_lists(authored)?;
+        let mut lists: Vec<Result<StoredList, String>> = loaded.lists.into_iter().map(Ok).collect();
+        lists.extend(loaded.errors.into_iter().map(Err));
+        lists.extend(loaded.rejected.into_iter().map(|rejected| Err(rejected.to_string())));
         Ok(lists)
     }
 
-    fn derive_filename(&self, pubkey: &str) -> PathBuf {
-        let pubkey_without_pk = pubkey.replace("pk:", "");
-        let path = self.path.clone().join(format!("{}.json", pubkey_without_pk));
+    /**
+     * Reads all lists and makes sure there is at most one list per public key.
+     * - A list whose file name is not `<z32 pubkey>.json` is rejected, the file name must not lie about the list.
+     * - If multiple files hold the list of the same public key, one is chosen deterministically. See `preference_key`.
+     */
+    pub fn load_lists(&self, authored: &[PublicKey]) -> Result<LoadedLists, std::io::Error> {
+        let mut loaded = LoadedLists { lists: vec![], errors: vec![], rejected: vec![] };
+        let mut candidates: Vec<(StoredList, ListSource)> = vec![];
+        for (path, folder) in self.list_paths()? {
+            let list = read_list_or_last_good_copy(&path);
+            if let Err(e) = list {
+                loaded.errors.push(e);
+                continue;
+            };
+            let list = list.unwrap();
+            let expected_name = format!("{}.json", list.pubkey.to_z32());
+            if path.file_name().and_then(|name| name.to_str()) != Some(expected_name.as_str()) {
+                loaded.rejected.push(RejectedList::NameMismatch { path, pubkey: list.pubkey });
+                continue;
+            };
+            let provenance = match folder {
+                ListSource::Authored if authored.contains(&list.pubkey) => Provenance::new(ListSource::Authored),
+                ListSource::Authored => Provenance::new(ListSource::Imported),
+                source => {
+                    // The folder decides the source, the metadata only adds details.
+                    let provenance = Provenance::from_path(&metadata_path(&path)).unwrap_or(Provenance::new(source));
+                    Provenance { source, ..provenance }
+                },
+            };
+            candidates.push((StoredList { list, provenance, path }, folder));
+        };
+
+        candidates.sort_by_cached_key(|(stored, folder)| preference_key(stored, *folder));
+        let mut chosen: HashMap<PublicKey, PathBuf> = HashMap::new();
+        let mut lists = vec![];
+        for (stored, folder) in candidates {
+            if let Some(preferred) = chosen.get(&stored.list.pubkey) {
+                loaded.rejected.push(RejectedList::Duplicate { path: stored.path, pubkey: stored.list.pubkey, preferred: preferred.clone() });
+                continue;
+            };
+            chosen.insert(stored.list.pubkey.clone(), stored.path.clone());
+            lists.push((stored, folder));
+        };
+        lists.sort_by(|(a, a_folder), (b, b_folder)| (a_folder, &a.path).cmp(&(b_folder, &b.path)));
+        loaded.lists = lists.into_iter().map(|(stored, _)| stored).collect();
+        loaded.rejected.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(loaded)
+    }
+
+    pub fn get_imported_path(&self) -> PathBuf {
+        self.path.join("imported")
+    }
+
+    pub fn get_fetched_path(&self) -> PathBuf {
+        self.path.join("fetched")
+    }
+
+    /**
+     * Paths of all list files, sorted, with the source derived from the folder.
+     * The main folder must exist, the imported and fetched folders are optional.
+     */
+    fn list_paths(&self) -> Result<Vec<(PathBuf, ListSource)>, std::io::Error> {
+        let mut paths = vec![];
+        for (folder, source) in [(self.path.clone(), ListSource::Authored), (self.get_imported_path(), ListSource::Imported), (self.get_fetched_path(), ListSource::Fetched)] {
+            let entries = fs::read_dir(&folder);
+            if let Err(e) = entries {
+                if source == ListSource::Authored {
+                    return Err(e);
+                };
+                continue;
+            };
+            let mut folder_paths: Vec<PathBuf> = entries.unwrap()
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "json"))
+                .collect();
+            folder_paths.sort();
+            paths.extend(folder_paths.into_iter().map(|path| (path, source)));
+        };
+        Ok(paths)
+    }
+
+    /**
+     * Lists that fail to parse, whether a last good copy exists or not.
+     * Returns the path and the parse error.
+     */
+    pub fn corrupted_lists(&self) -> Vec<(PathBuf, String)> {
+        let paths = self.list_paths();
+        if paths.is_err() {
+            return vec![];
+        };
+        paths.unwrap()
+            .into_iter()
+            .filter_map(|(path, _)| FollowList::from_path(&path).err().map(|e| (path, e)))
+            .collect()
+    }
+
+    fn derive_filename(&self, pubkey: &PublicKey) -> PathBuf {
+        let path = self.path.clone().join(format!("{}.json", pubkey.to_z32()));
         path
     }
 
     /**
-     * Read list from disk.
+     * Read list from disk. Falls back to the last good copy if the list is corrupted.
      */
-    pub fn read_list(&self, pubkey: &str) -> Result<FollowList, String> {
+    pub fn read_list(&self, pubkey: &PublicKey) -> Result<FollowList, String> {
         let path = self.derive_filename(pubkey);
-        FollowList::from_path(&path)
+        read_list_or_last_good_copy(&path)
     }
 
     /**
-     * Write list to disk
+     * Write list to disk. The write is atomic and a last good copy is kept in `<name>.json.bak`.
+     * `read_list` falls back to it if the list gets corrupted later, for example by a manual edit.
      */
-    pub fn write_list(&self, pubkey: &str, list: FollowList) -> Result<(),std::io::Error> {
+    pub fn write_list(&self, pubkey: &PublicKey, list: FollowList) -> Result<(),std::io::Error> {
         let path = self.derive_filename(pubkey);
         let str = list.to_json();
-        fs::write(path, str)
+        write_atomic(&path, str.as_bytes())?;
+        write_atomic(&last_good_copy_path(&path), str.as_bytes())
+    }
+
+    /**
+     * Stores the list of somebody else in `imported/`. Replaces an earlier import of the same list.
+     */
+    pub fn write_imported_list(&self, list: &FollowList, provenance: &Provenance) -> Result<(), std::io::Error> {
+        let folder = self.get_imported_path();
+        fs::create_dir_all(&folder)?;
+        let path = folder.join(format!("{}.json", list.pubkey.to_z32()));
+        write_atomic(&path, list.to_json().as_bytes())?;
+        write_atomic(&metadata_path(&path), provenance.to_json().as_bytes())
     }
 }
 
+/**
+ * Result of `StaticListsDirectory::load_lists`.
+ */
+#[derive(Default)]
+pub struct LoadedLists {
+    /// At most one list per public key. Sorted by folder, the main folder first, then by path.
+    pub lists: Vec<StoredList>,
+    /// Files that can't be read or parsed.
+    pub errors: Vec<String>,
+    pub rejected: Vec<RejectedList>,
+}
+
+impl LoadedLists {
+    /**
+     * Messages for all files that are not used, the ones that fail to parse first.
+     */
+    pub fn problems(&self) -> Vec<String> {
+        self.errors.iter().cloned().chain(self.rejected.iter().map(|rejected| rejected.to_string())).collect()
+    }
+}
+
+/**
+ * A list file that parses but is not used.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum RejectedList {
+    /// The file name is not `<z32 pubkey>.json`.
+    NameMismatch { path: PathBuf, pubkey: PublicKey },
+    /// Another list of the same public key is preferred.
+    Duplicate { path: PathBuf, pubkey: PublicKey, preferred: PathBuf },
+}
+
+impl RejectedList {
+    pub fn path(&self) -> &Path {
+        match self {
+            RejectedList::NameMismatch { path, .. } => path,
+            RejectedList::Duplicate { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for RejectedList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectedList::NameMismatch { path, pubkey } => write!(f, "List \"{}\" holds the list of {} but is not named {}.json. Ignored.", path.to_str().unwrap(), pubkey, pubkey.to_z32()),
+            RejectedList::Duplicate { path, pubkey, preferred } => write!(f, "List \"{}\" is another list of {}. Using \"{}\" instead.", path.to_str().unwrap(), pubkey, preferred.to_str().unwrap()),
+        }
+    }
+}
+
+/**
+ * Order of preference if multiple files hold the list of the same public key. Lowest wins:
+ * 1. Lists in the main folder. Your own lists live there and are never replaced by a copy.
+ * 2. Imported lists, you added them yourself. Then fetched lists.
+ * 3. Path, so the choice is always the same.
+ *
+ * Only the location of the file counts. `<z32>.meta` can be edited by anybody who can write a list, so it isn't used.
+ */
+fn preference_key(stored: &StoredList, folder: ListSource) -> (ListSource, PathBuf) {
+    (folder, stored.path.clone())
+}
+
+/**
+ * Last good copy of a list file, `<name>.json.bak`.
+ */
+pub fn last_good_copy_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+fn read_list_or_last_good_copy(path: &Path) -> Result<FollowList, String> {
+    let result = FollowList::from_path(path);
+    if result.is_ok() {
+        return result;
+    };
+    let backup_path = last_good_copy_path(path);
+    if !backup_path.exists() {
+        return result;
+    };
+    FollowList::from_path(&backup_path).map_err(|_| result.err().unwrap())
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
-    use crate::config_directory::{follow_list::FollowList, follow::Follow};
-    use super::StaticListsDirectory;
+    use crate::{config_directory::{follow_list::FollowList, follow::Follow, provenance::{metadata_path, ListSource, Provenance, SignatureStatus, StoredList}}, public_key::PublicKey};
+    use super::{RejectedList, StaticListsDirectory};
+
+    const ME: &str = "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso";
 
+    /**
+     * Puts a list in `fetched/` like a download would.
+     */
+    fn write_fetched_list(config: &StaticListsDirectory, list: &FollowList, provenance: &Provenance) {
+        std::fs::create_dir_all(config.get_fetched_path()).unwrap();
+        let path = config.get_fetched_path().join(format!("{}.json", list.pubkey.to_z32()));
+        std::fs::write(&path, list.to_json()).unwrap();
+        std::fs::write(metadata_path(&path), provenance.to_json()).unwrap();
+    }
 
     #[test]
     fn read_lists() {
-        let config = StaticListsDirectory::new(PathBuf::from("/tmp/fancydns827209438"));
+        let config = StaticListsDirectory::new(PathBuf::from("/tmp/fancydns_read_lists"));
         let _ = config.delete(); // Delete so the test can work again even though it failed before.
-        let _ = config.create_if_it_does_not_exist("test");
+        let _ = config.create_if_it_does_not_exist(Some(&PublicKey::parse(ME).unwrap()));
 
         let list = FollowList::new_with_follows(
-            "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso",
+            PublicKey::parse(ME).unwrap(),
             "myList",
             vec![
                 Follow::new(
-                    "pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy",
+                    PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap(),
                     1.0 / 3.0,
                     None
                 ),
                 Follow::new(
-                    "pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy",
+                    PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap(),
                     -1.0,
                     Some("example.com"),
                 ),
@@ -161,22 +365,112 @@ mod tests {
         path.push("myList.json");
         std::fs::write(path, json).unwrap();
 
+        // myList.json holds the list of me but is not named after it.
         let lists = config.read_lists().unwrap();
-        assert_eq!(lists.len(), 2)
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists.iter().filter(|list| list.is_err()).count(), 1);
     }
 
     #[test]
     fn read_empty_me_list() {
         let config = StaticListsDirectory::new(PathBuf::from("/tmp/fancydns827209438"));
         let _ = config.delete(); // Delete so the test can work again even though it failed before.
-        let res = config.create_if_it_does_not_exist("test").unwrap();
+        let me = PublicKey::parse(ME).unwrap();
+        let res = config.create_if_it_does_not_exist(Some(&me)).unwrap();
 
-        let list_result = config.read_list("test");
+        let list_result = config.read_list(&me);
 
         assert!( list_result.is_ok());
         let list = list_result.unwrap();
-        assert_eq!(list.pubkey, "test");
+        assert_eq!(list.pubkey, ME);
         assert_eq!(list.alias, "me");
         assert_eq!(list.follows.len(), 0);
     }
+
+    #[test]
+    fn recover_last_good_copy() {
+        let config = StaticListsDirectory::new(PathBuf::from("/tmp/fancydns_recover_last_good_copy"));
+        let _ = config.delete(); // Delete so the test can work again even though it failed before.
+        let me = PublicKey::parse(ME).unwrap();
+        config.create_if_it_does_not_exist(Some(&me)).unwrap();
+        let mut list = config.read_list(&me).unwrap();
+        list.follows.push(Follow::new(PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap(), 1.0, None));
+        config.write_list(&me, list).unwrap();
+        assert!(config.corrupted_lists().is_empty());
+
+        // Simulate a broken manual edit.
+        std::fs::write(config.path.join(format!("{}.json", me.to_z32())), "{ \"pubkey\": ").unwrap();
+        assert_eq!(config.corrupted_lists().len(), 1);
+        assert_eq!(config.read_list(&me).unwrap().follows.len(), 1);
+        assert_eq!(config.read_valid_lists().len(), 1);
+
+        // Without a last good copy the error is returned.
+        std::fs::write(config.path.join("broken.json"), "[").unwrap();
+        assert_eq!(config.read_lists().unwrap().iter().filter(|list| list.is_err()).count(), 1);
+        config.delete().unwrap();
+    }
+
+    #[test]
+    fn read_stored_lists() {
+        let config = StaticListsDirectory::new(PathBuf::from("/tmp/fancydns_read_stored_lists"));
+        let _ = config.delete(); // Delete so the test can work again even though it failed before.
+        let me = PublicKey::parse(ME).unwrap();
+        config.create_if_it_does_not_exist(Some(&me)).unwrap();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let dave = PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap();
+        let bob = PublicKey::parse("pk:1ezgw4isz8safcahyt1pa8a5wpk3eyuhm1qpyoeya3pudehhbw9y").unwrap();
+
+        // Copied in by hand.
+        std::fs::write(config.path.join(format!("{}.json", bob.to_z32())), FollowList::new(bob.clone(), "bob").to_json()).unwrap();
+        let imported = Provenance { origin: Some("/home/me/alice.json".to_string()), fetched: Some(1700000000), ..Provenance::new(ListSource::Imported) };
+        config.write_imported_list(&FollowList::new(alice.clone(), "alice"), &imported).unwrap();
+        let fetched = Provenance { origin: Some("https://example.com/dave.json".to_string()), ..Provenance::new(ListSource::Imported) };
+        write_fetched_list(&config, &FollowList::new(dave.clone(), "dave"), &fetched);
+
+        let lists: Vec<StoredList> = config.read_stored_lists(std::slice::from_ref(&me)).unwrap().into_iter().map(|list| list.unwrap()).collect();
+        let provenance = |pubkey: &PublicKey| lists.iter().find(|stored| stored.list.pubkey == *pubkey).unwrap().provenance.clone();
+        assert_eq!(lists.len(), 4);
+        assert_eq!(provenance(&me), Provenance::new(ListSource::Authored));
+        assert_eq!(provenance(&bob), Provenance::new(ListSource::Imported));
+        assert_eq!(provenance(&alice), imported);
+        assert_eq!(provenance(&dave).source, ListSource::Fetched); // The folder wins over the metadata.
+        assert_eq!(provenance(&dave).origin, fetched.origin);
+        assert_eq!(provenance(&dave).signature, SignatureStatus::Unsigned);
+        assert_eq!(config.read_lists().unwrap().len(), 4);
+        config.delete().unwrap();
+    }
+
+    #[test]
+    fn resolve_name_mismatches_and_duplicates() {
+        let config = StaticListsDirectory::new(PathBuf::from("/tmp/fancydns_resolve_name_mismatches_and_duplicates"));
+        let _ = config.delete(); // Delete so the test can work again even though it failed before.
+        let me = PublicKey::parse(ME).unwrap();
+        config.create_if_it_does_not_exist(Some(&me)).unwrap();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let dave = PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap();
+
+        // A file named after alice that holds the list of dave.
+        std::fs::write(config.path.join(format!("{}.json", alice.to_z32())), FollowList::new(dave.clone(), "dave").to_json()).unwrap();
+        // A copy of my own list is never preferred over my list.
+        write_fetched_list(&config, &FollowList::new(me.clone(), "copy"), &Provenance { fetched: Some(3000), ..Provenance::new(ListSource::Fetched) });
+        // Imported and fetched lists of dave. The imported one wins, even if the metadata claims the fetched one is newer.
+        let old = Provenance { fetched: Some(1000), ..Provenance::new(ListSource::Imported) };
+        let new = Provenance { fetched: Some(2000), ..Provenance::new(ListSource::Fetched) };
+        config.write_imported_list(&FollowList::new(dave.clone(), "imported"), &old).unwrap();
+        write_fetched_list(&config, &FollowList::new(dave.clone(), "fetched"), &new);
+
+        let loaded = config.load_lists(std::slice::from_ref(&me)).unwrap();
+        let aliases: Vec<&str> = loaded.lists.iter().map(|stored| stored.list.alias.as_str()).collect();
+        assert_eq!(aliases, vec!["me", "imported"]);
+        assert_eq!(loaded.rejected.len(), 3);
+        assert!(loaded.rejected.contains(&RejectedList::NameMismatch { path: config.path.join(format!("{}.json", alice.to_z32())), pubkey: dave.clone() }));
+        assert!(loaded.rejected.iter().any(|rejected| matches!(rejected, RejectedList::Duplicate { pubkey, .. } if *pubkey == me)));
+
+        // Without the import the fetched list is used.
+        std::fs::remove_file(config.get_imported_path().join(format!("{}.json", dave.to_z32()))).unwrap();
+        let loaded = config.load_lists(std::slice::from_ref(&me)).unwrap();
+        assert!(loaded.lists.iter().any(|stored| stored.list.alias == "fetched"));
+        assert_eq!(config.read_lists().unwrap().iter().filter(|list| list.is_err()).count(), 2);
+        config.delete().unwrap();
+    }
 }
@@ -0,0 +1,344 @@
+use std::{collections::{HashMap, HashSet}, fmt, fs, path::{Path, PathBuf}};
+
+use crate::public_key::PublicKey;
+use super::{
+    dirs::{main_directory::MainDirectory, static_lists_directory::{last_good_copy_path, RejectedList}}, follow::Follow,
+    follow_list::FollowList, list_edit::validate_weight, provenance::{ListSource, StoredList},
+    secret::{check_permissions, SecretFile},
+};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Works, but probably not as intended.
+    Warning,
+    /// Something is broken or ignored.
+    Error,
+}
+
+/**
+ * A problem found in the directory and how to fix it.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub problem: String,
+    pub fix: String,
+}
+
+impl Finding {
+    fn error(problem: String, fix: String) -> Self {
+        Finding { severity: Severity::Error, problem, fix }
+    }
+
+    fn warning(problem: String, fix: String) -> Self {
+        Finding { severity: Severity::Warning, problem, fix }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let emoji = match self.severity {
+            Severity::Error => "❌",
+            Severity::Warning => "⚠",
+        };
+        write!(f, "{} {}\n   Fix: {}", emoji, self.problem, self.fix)
+    }
+}
+
+/**
+ * Checks secrets, lists and the graph of the directory. Returns all problems, an empty vec if everything is fine.
+ */
+pub fn diagnose(dir: &MainDirectory) -> Vec<Finding> {
+    let mut findings = check_secrets(dir);
+    findings.extend(check_corrupted_lists(dir));
+
+    let loaded = dir.static_lists_dir.load_lists(&dir.authored_pubkeys());
+    if let Err(e) = loaded {
+        findings.push(Finding::error(
+            format!("Failed to read \"{}\". {}", dir.static_lists_dir.path.to_str().unwrap(), e),
+            "Check that the directory exists and is readable.".to_string(),
+        ));
+        return findings;
+    };
+    let loaded = loaded.unwrap();
+    findings.extend(loaded.rejected.iter().map(check_rejected_list));
+
+    // Never create a key here. Without me, only the checks that don't need it are done.
+    let me_pubkey = dir.read_public_key();
+    for list in loaded.lists.iter() {
+        let is_me_list = me_pubkey.as_ref().is_ok_and(|me_pubkey| list.list.pubkey == *me_pubkey) && !dir.is_read_only();
+        findings.extend(check_follows(list, is_me_list));
+    };
+    if let Err(e) = me_pubkey {
+        findings.push(Finding::warning(
+            format!("Skipped the checks of the graph and of unused lists because the secret of {} can't be read. {}", dir.perspective, e),
+            "Fix or restore the secret, then run doctor again.".to_string(),
+        ));
+        return findings;
+    };
+    let me_pubkey = me_pubkey.unwrap();
+    findings.extend(check_graph(dir));
+    findings.extend(check_reachability(dir, &me_pubkey, &loaded.lists));
+    findings
+}
+
+fn check_secrets(dir: &MainDirectory) -> Vec<Finding> {
+    let mut paths: Vec<PathBuf> = dir.list_identities().iter().map(|name| dir.get_identity_path(name)).collect();
+    if let Ok(entries) = fs::read_dir(dir.get_retired_path()) {
+        let mut retired: Vec<PathBuf> = entries.filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "secret"))
+            .collect();
+        retired.sort();
+        paths.extend(retired);
+    };
+
+    let mut findings = vec![];
+    for path in paths {
+        let path_str = path.to_str().unwrap();
+        let secret = SecretFile::from_path(&path);
+        if let Err(e) = secret {
+            findings.push(Finding::error(
+                format!("Secret \"{}\" is corrupt. {}", path_str, e),
+                "Restore it from a backup or an export bundle. Without it the list can't be changed or published.".to_string(),
+            ));
+            continue;
+        };
+        let secret = secret.unwrap();
+        if let (true, Some(passphrase)) = (secret.is_encrypted(), &dir.passphrase) {
+            if let Err(e) = secret.unlock(Some(passphrase)) {
+                findings.push(Finding::error(
+                    format!("Secret \"{}\" can't be unlocked. {}", path_str, e),
+                    "Check the passphrase in the PKNAMES_PASSPHRASE environment variable.".to_string(),
+                ));
+            };
+        };
+        if check_permissions(&path).is_err() {
+            findings.push(Finding::warning(
+                format!("Secret \"{}\" is accessible by other users.", path_str),
+                format!("chmod 600 {}", path_str),
+            ));
+        };
+    };
+    findings
+}
+
+fn check_corrupted_lists(dir: &MainDirectory) -> Vec<Finding> {
+    dir.static_lists_dir.corrupted_lists().into_iter().map(|(path, e)| {
+        let backup = last_good_copy_path(&path);
+        if backup.exists() {
+            Finding::warning(
+                format!("{} Using the last good copy.", e),
+                format!("cp {} {}", backup.to_str().unwrap(), path.to_str().unwrap()),
+            )
+        } else {
+            Finding::error(
+                format!("{} The list is ignored.", e),
+                format!("Fix the json or delete \"{}\".", path.to_str().unwrap()),
+            )
+        }
+    }).collect()
+}
+
+fn check_rejected_list(rejected: &RejectedList) -> Finding {
+    match rejected {
+        RejectedList::NameMismatch { path, pubkey } => Finding::error(
+            rejected.to_string(),
+            format!("Rename \"{}\" to {}.json or delete it.", path.to_str().unwrap(), pubkey.to_z32()),
+        ),
+        RejectedList::Duplicate { path, .. } => Finding::warning(
+            rejected.to_string(),
+            format!("Delete \"{}\" if it is outdated.", path.to_str().unwrap()),
+        ),
+    }
+}
+
+/**
+ * Command that fixes a follow in the me list, or a manual edit for all other lists.
+ */
+fn follow_fix(is_me_list: bool, path: &Path, command: String) -> String {
+    if is_me_list {
+        format!("pknamescli {}", command)
+    } else {
+        format!("Edit \"{}\" or ask the owner of the list to fix it.", path.to_str().unwrap())
+    }
+}
+
+fn format_domain(follow: &Follow) -> String {
+    follow.domain().map(|domain| format!(" {}", domain)).unwrap_or_default()
+}
+
+fn check_follows(stored: &StoredList, is_me_list: bool) -> Vec<Finding> {
+    let list = &stored.list;
+    let mut findings = vec![];
+    for follow in list.follows.iter() {
+        if *follow.pubkey() == list.pubkey && follow.domain().is_none() {
+            findings.push(Finding::warning(
+                format!("List {} follows itself.", list.pubkey),
+                follow_fix(is_me_list, &stored.path, format!("remove {}", follow.pubkey())),
+            ));
+        };
+        if validate_weight(*follow.weight()).is_err() {
+            let clamped = if follow.weight().is_nan() { 0.0 } else { follow.weight().clamp(-1.0, 1.0) };
+            findings.push(Finding::error(
+                format!("List {} follows {}{} with weight {}. Weights must be between -1 and 1.", list.pubkey, follow.pubkey(), format_domain(follow), follow.weight()),
+                follow_fix(is_me_list, &stored.path, format!("add {} {}{}", follow.pubkey(), clamped, format_domain(follow))),
+            ));
+        };
+    };
+
+    let mut weights: HashMap<&Follow, Vec<f32>> = HashMap::new();
+    for follow in list.follows.iter() {
+        weights.entry(follow).or_default().push(*follow.weight());
+    };
+    let mut duplicates: Vec<(&Follow, Vec<f32>)> = weights.into_iter()
+        .filter(|(_, weights)| weights.iter().any(|weight| *weight != weights[0]))
+        .collect();
+    duplicates.sort_by_key(|(follow, _)| (follow.pubkey().to_string(), follow.domain().map(|domain| domain.to_string())));
+    for (follow, weights) in duplicates {
+        let weights: Vec<String> = weights.iter().map(|weight| weight.to_string()).collect();
+        findings.push(Finding::warning(
+            format!("List {} follows {}{} {} times with different weights {}. Only one of them is used.", list.pubkey, follow.pubkey(), format_domain(follow), weights.len(), weights.join(", ")),
+            format!("Edit \"{}\" and keep one of the follows.", stored.path.to_str().unwrap()),
+        ));
+    };
+    findings
+}
+
+fn check_graph(dir: &MainDirectory) -> Option<Finding> {
+    let graph = dir.read_graph();
+    if let Err(e) = graph {
+        return Some(Finding::error(e, "Fix the config.".to_string()));
+    };
+    let graph = graph.unwrap();
+    let result = graph.validate();
+    if let Err(e) = result {
+        return Some(Finding::error(
+            format!("Invalid graph. {}", e),
+            "Fix the other problems first.".to_string(),
+        ));
+    };
+    None
+}
+
+/**
+ * Lists of others that are never used because no chain of positive list follows leads from the me list to them.
+ */
+fn check_reachability(dir: &MainDirectory, me_pubkey: &PublicKey, stored: &[StoredList]) -> Vec<Finding> {
+    let config = dir.read_config().unwrap_or_default();
+    let lists: HashMap<&PublicKey, &FollowList> = stored.iter()
+        .filter(|stored| stored.list.pubkey == *me_pubkey || config.lists.accepts(&stored.provenance))
+        .map(|stored| (&stored.list.pubkey, &stored.list))
+        .collect();
+    let blocks: HashSet<&PublicKey> = lists.get(me_pubkey).map(|list| list.blocks.iter().collect()).unwrap_or_default();
+
+    let mut reached: HashSet<&PublicKey> = HashSet::new();
+    let mut queue = vec![me_pubkey];
+    while let Some(pubkey) = queue.pop() {
+        if !reached.insert(pubkey) {
+            continue;
+        };
+        let list = lists.get(pubkey);
+        if list.is_none() {
+            continue;
+        };
+        let list = list.unwrap();
+        if let Some(successor) = list.verified_successor() {
+            queue.push(successor);
+            continue;
+        };
+        for follow in list.follows.iter() {
+            if follow.domain().is_none() && *follow.weight() > 0.0 && !blocks.contains(follow.pubkey()) {
+                queue.push(follow.pubkey());
+            };
+        };
+    };
+
+    let mut unreachable: Vec<&StoredList> = stored.iter()
+        .filter(|stored| stored.provenance.source != ListSource::Authored && !reached.contains(&stored.list.pubkey))
+        .collect();
+    unreachable.sort_by_key(|stored| stored.path.clone());
+    unreachable.into_iter().map(|stored| Finding::warning(
+        format!("List {} is not followed by your list or any list you follow. It is never used.", stored.list.pubkey),
+        format!("pknamescli add {} 1 to trust it or delete \"{}\".", stored.list.pubkey, stored.path.to_str().unwrap()),
+    )).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{config_directory::{dirs::main_directory::MainDirectory, follow::Follow, follow_list::FollowList}, public_key::PublicKey};
+    use super::{diagnose, Severity};
+
+    const ALICE: &str = "pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy";
+    const DAVE: &str = "pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy";
+
+    #[test]
+    fn healthy_directory() {
+        let dir = MainDirectory::new_by_string("/tmp/fancydns_doctor_healthy_directory");
+        let _ = dir.delete(); // Delete so the test can work again even though it failed before.
+        dir.create_if_it_does_not_exist().unwrap();
+        let alice = PublicKey::parse(ALICE).unwrap();
+        dir.upsert_follow(Follow::new(alice.clone(), 1.0, None)).unwrap();
+        dir.static_lists_dir.write_list(&alice, FollowList::new(alice.clone(), "alice")).unwrap();
+        assert_eq!(diagnose(&dir), vec![]);
+        dir.delete().unwrap();
+    }
+
+    #[test]
+    fn finds_problems() {
+        let dir = MainDirectory::new_by_string("/tmp/fancydns_doctor_finds_problems");
+        let _ = dir.delete(); // Delete so the test can work again even though it failed before.
+        dir.create_if_it_does_not_exist().unwrap();
+        let me = dir.get_public_key();
+        let alice = PublicKey::parse(ALICE).unwrap();
+        let dave = PublicKey::parse(DAVE).unwrap();
+        let me_list = FollowList::new_with_follows(me.clone(), "me", vec![
+            Follow::new(me.clone(), 1.0, None),
+            Follow::new(alice.clone(), 2.0, None),
+            Follow::new(alice.clone(), 0.5, None),
+        ]);
+        dir.static_lists_dir.write_list(&me, me_list).unwrap();
+        let lists_path = &dir.static_lists_dir.path;
+        std::fs::write(lists_path.join(format!("{}.json", dave.to_z32())), FollowList::new(dave.clone(), "dave").to_json()).unwrap();
+        std::fs::write(lists_path.join("copy.json"), FollowList::new(dave.clone(), "dave").to_json()).unwrap();
+        std::fs::write(lists_path.join("broken.json"), "[").unwrap();
+        std::fs::create_dir(dir.get_identities_path()).unwrap();
+        std::fs::write(dir.get_identity_path("org"), "too short").unwrap();
+
+        let findings = diagnose(&dir);
+        let has = |severity: Severity, text: &str| findings.iter().any(|finding| finding.severity == severity && finding.problem.contains(text));
+        assert!(has(Severity::Error, "is corrupt"));
+        assert!(has(Severity::Error, "broken.json"));
+        assert!(has(Severity::Error, "but is not named"));
+        assert!(has(Severity::Warning, "follows itself"));
+        assert!(has(Severity::Error, "with weight 2"));
+        assert!(has(Severity::Warning, "different weights 2, 0.5"));
+        assert!(has(Severity::Warning, &format!("List {} is not followed", dave)));
+        let weight_fix = &findings.iter().find(|finding| finding.problem.contains("with weight 2")).unwrap().fix;
+        assert_eq!(*weight_fix, format!("pknamescli add {} 1", alice));
+        dir.delete().unwrap();
+    }
+
+    #[test]
+    fn corrupt_default_secret() {
+        let dir = MainDirectory::new_by_string("/tmp/fancydns_doctor_corrupt_default_secret");
+        let _ = dir.delete(); // Delete so the test can work again even though it failed before.
+        dir.create_if_it_does_not_exist().unwrap();
+        let secret_path = dir.get_identity_path("default");
+        std::fs::write(&secret_path, "too short").unwrap();
+
+        let findings = diagnose(&dir);
+        let has = |severity: Severity, text: &str| findings.iter().any(|finding| finding.severity == severity && finding.problem.contains(text));
+        assert!(has(Severity::Error, "is corrupt"));
+        assert!(has(Severity::Warning, "Skipped the checks"));
+        assert_eq!(std::fs::read_to_string(&secret_path).unwrap(), "too short");
+
+        // A missing secret is reported too and not created.
+        std::fs::remove_file(&secret_path).unwrap();
+        let findings = diagnose(&dir);
+        assert!(findings.iter().any(|finding| finding.problem.contains("Skipped the checks")));
+        assert!(!secret_path.exists());
+        dir.delete().unwrap();
+    }
+}
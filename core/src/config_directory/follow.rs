@@ -1,24 +1,72 @@
 use std::hash::{Hash, Hasher};
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{ser::SerializeTuple, Deserialize, Serialize, Serializer};
 
+use crate::{public_key::PublicKey, time::{format_duration, unix_now}};
+use super::list_edit::clamp_weight;
 
-#[derive(Serialize, Deserialize)]
+
+/**
+ * Serialized as json array `[pubkey, weight, domain, created, expires, scope]`.
+ * Trailing empty elements are omitted so lists without timestamps keep the short `[pubkey, weight, domain]` form.
+ *
+ * Weights are within [-1, 1]:
+ * - Without domain: How much the list of `pubkey` is trusted. 1 is full trust, 0 none. Negative weights lower the
+ *   trust others put into the list but never below zero.
+ * - With domain: How sure the list is that `pubkey` owns the domain (positive) or doesn't (negative).
+ *
+ * Edits are validated with `validate_weight`. Lists of others may contain anything, `weight_at` clamps their weights.
+ */
+#[derive(Deserialize, Debug, Clone)]
 pub struct Follow(
-    pub String, // pubkey
-    #[serde(serialize_with = "serialize_weight")]
+    pub PublicKey, // pubkey
     pub f32, // weight
     #[serde(default = "default_domain")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub Option<String> // domain
+    pub Option<String>, // domain
+    #[serde(default)]
+    pub Option<u64>, // created, unix timestamp in seconds
+    #[serde(default)]
+    pub Option<u64>, // expires, unix timestamp in seconds
+    #[serde(default)]
+    pub Vec<String>, // scope, domains this list is trusted about. Empty: All domains.
 );
 
-fn serialize_weight<S>(weight: &f32, s: S) -> Result<S::Ok, S::Error> where S: Serializer {
+fn round_weight(weight: f32) -> f32 {
     let accuracy_after_comma = 3;
     let base: f32 = 10.0;
     let divider = base.powi(accuracy_after_comma);
-    let clone = weight.clone();
-    let rounded: f32 = (clone*divider).round()/divider;
-    s.serialize_f32(rounded)
+    (weight*divider).round()/divider
+}
+
+impl Serialize for Follow {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let len = if !self.5.is_empty() {
+            6
+        } else if self.4.is_some() {
+            5
+        } else if self.3.is_some() {
+            4
+        } else if self.2.is_some() {
+            3
+        } else {
+            2
+        };
+        let mut tuple = s.serialize_tuple(len)?;
+        tuple.serialize_element(&self.0)?;
+        tuple.serialize_element(&round_weight(self.1))?;
+        if len > 2 {
+            tuple.serialize_element(&self.2)?;
+        };
+        if len > 3 {
+            tuple.serialize_element(&self.3)?;
+        };
+        if len > 4 {
+            tuple.serialize_element(&self.4)?;
+        };
+        if len > 5 {
+            tuple.serialize_element(&self.5)?;
+        };
+        tuple.end()
+    }
 }
 
 fn default_domain() -> Option<String> {
@@ -27,26 +75,30 @@ fn default_domain() -> Option<String> {
 
 impl std::fmt::Display for Follow {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let emoji = match self.domain() {
-            Some(_) => "🅰️ ",
-            None => "📃"
-        };
-        let mut name = format!("{} {} {:.2}", emoji, self.pubkey(), self.1);
-
-        if let Some(domain) = self.domain() {
-            name = format!("{} {}", name, domain);
-        };
-        write!(f, "{}", name)
+        write!(f, "{}", self.format_with_label(self.pubkey()))
     }
 }
 
 impl Follow {
-    pub fn new(target_pubkey: &str, weight: f32, domain: Option<&str>) -> Self {
+    pub fn new(target_pubkey: PublicKey, weight: f32, domain: Option<&str>) -> Self {
         let domain = domain.map(|val| val.to_string());
-        Follow(target_pubkey.to_string(), weight, domain)
+        Follow(target_pubkey, weight, domain, None, None, vec![])
     }
 
-    pub fn pubkey(&self) -> &String {
+    pub fn new_with_timestamps(target_pubkey: PublicKey, weight: f32, domain: Option<&str>, created: Option<u64>, expires: Option<u64>) -> Self {
+        let domain = domain.map(|val| val.to_string());
+        Follow(target_pubkey, weight, domain, created, expires, vec![])
+    }
+
+    /**
+     * Limits the follow to domains matching `scope`, for example `*.bitcoin`. See `prediction::node::scope_matches`.
+     */
+    pub fn with_scope(mut self, scope: Vec<String>) -> Self {
+        self.5 = scope;
+        self
+    }
+
+    pub fn pubkey(&self) -> &PublicKey {
         &self.0
     }
 
@@ -59,6 +111,84 @@ impl Follow {
         self.2.as_ref().map(|val| val.as_str())
     }
 
+    pub fn scope(&self) -> &[String] {
+        &self.5
+    }
+
+    pub fn created(&self) -> Option<u64> {
+        self.3
+    }
+
+    pub fn expires(&self) -> Option<u64> {
+        self.4
+    }
+
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        match self.expires() {
+            None => false,
+            Some(expires) => expires <= now,
+        }
+    }
+
+    /**
+     * Seconds since the follow was created. None if the follow has no `created` timestamp.
+     */
+    pub fn age_at(&self, now: u64) -> Option<u64> {
+        self.created().map(|created| now.saturating_sub(created))
+    }
+
+    /**
+     * Weight after halving it every `half_life` seconds of age. None if the follow is expired or its weight is not finite.
+     * Follows without `created` timestamp don't decay. Out-of-range weights are clamped, see `clamp_weight`.
+     */
+    pub fn weight_at(&self, now: u64, half_life: Option<u64>) -> Option<f32> {
+        if self.is_expired_at(now) {
+            return None;
+        };
+        let weight = clamp_weight(self.1)?;
+        let (age, half_life) = match (self.age_at(now), half_life) {
+            (Some(age), Some(half_life)) if half_life > 0 => (age, half_life),
+            _ => return Some(weight),
+        };
+        let factor = 0.5_f64.powf(age as f64 / half_life as f64);
+        Some((weight as f64 * factor) as f32)
+    }
+
+    /**
+     * Like `Display` but with `label` instead of the pubkey. See `KeyNames::label`.
+     */
+    pub fn format_with_label(&self, label: &str) -> String {
+        let emoji = match self.domain() {
+            Some(_) => "🅰️ ",
+            None => "📃"
+        };
+        let mut name = format!("{} {} {:.2}", emoji, label, self.1);
+
+        if let Some(domain) = self.domain() {
+            name = format!("{} {}", name, domain);
+        };
+        if !self.scope().is_empty() {
+            name = format!("{} only {}", name, self.scope().join(", "));
+        };
+
+        let now = unix_now();
+        let mut times = vec![];
+        if let Some(age) = self.age_at(now) {
+            times.push(format!("{} old", format_duration(age)));
+        };
+        if let Some(expires) = self.expires() {
+            if expires <= now {
+                times.push("expired".to_string());
+            } else {
+                times.push(format!("expires in {}", format_duration(expires - now)));
+            };
+        };
+        if !times.is_empty() {
+            name = format!("{} ({})", name, times.join(", "));
+        };
+        name
+    }
+
 }
 
 impl PartialEq for Follow {
@@ -74,3 +204,65 @@ impl Hash for Follow {
         self.domain().hash(state);
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::public_key::PublicKey;
+    use super::Follow;
+
+    const KEY: &str = "pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy";
+
+    fn key() -> PublicKey {
+        PublicKey::parse(KEY).unwrap()
+    }
+
+    #[test]
+    fn json_without_timestamps() {
+        let follow = Follow::new(key(), 1.0 / 3.0, None);
+        assert_eq!(serde_json::to_string(&follow).unwrap(), format!("[\"{}\",0.333]", KEY));
+        let follow = Follow::new(key(), 1.0, Some("example.com"));
+        assert_eq!(serde_json::to_string(&follow).unwrap(), format!("[\"{}\",1.0,\"example.com\"]", KEY));
+    }
+
+    #[test]
+    fn json_with_timestamps() {
+        let follow = Follow::new_with_timestamps(key(), 1.0, None, Some(10), Some(20));
+        let json = serde_json::to_string(&follow).unwrap();
+        assert_eq!(json, format!("[\"{}\",1.0,null,10,20]", KEY));
+        let recovered: Follow = serde_json::from_str(&json).unwrap();
+        assert_eq!(recovered.created(), Some(10));
+        assert_eq!(recovered.expires(), Some(20));
+        assert!(recovered.domain().is_none());
+    }
+
+    #[test]
+    fn json_with_scope() {
+        let follow = Follow::new(key(), 1.0, None).with_scope(vec!["*.bitcoin".to_string()]);
+        let json = serde_json::to_string(&follow).unwrap();
+        assert_eq!(json, format!("[\"{}\",1.0,null,null,null,[\"*.bitcoin\"]]", KEY));
+        let recovered: Follow = serde_json::from_str(&json).unwrap();
+        assert_eq!(recovered.scope(), ["*.bitcoin"]);
+    }
+
+    #[test]
+    fn weight_at() {
+        let follow = Follow::new_with_timestamps(key(), -1.0, None, Some(100), Some(300));
+        assert_eq!(follow.weight_at(100, Some(50)), Some(-1.0));
+        assert_eq!(follow.weight_at(200, Some(50)), Some(-0.25));
+        assert_eq!(follow.weight_at(200, None), Some(-1.0));
+        assert_eq!(follow.weight_at(300, None), None);
+    }
+
+    #[test]
+    fn weight_at_clamps() {
+        assert_eq!(Follow::new(key(), 1000.0, None).weight_at(0, None), Some(1.0));
+        assert_eq!(Follow::new(key(), -5.0, Some("example.com")).weight_at(0, None), Some(-1.0));
+        assert_eq!(Follow::new(key(), f32::NAN, None).weight_at(0, None), None);
+        assert_eq!(Follow::new(key(), f32::INFINITY, None).weight_at(0, None), None);
+
+        // 1e300 overflows f32 when parsed.
+        let follow: Follow = serde_json::from_str(&format!("[\"{}\", 1e300]", KEY)).unwrap();
+        assert_eq!(follow.weight_at(0, None), None);
+    }
+}
@@ -1,17 +1,25 @@
 use std::{collections::HashSet, fs, path::Path};
 use serde::{Deserialize, Serialize};
 
-use crate::prediction::{graph::WotGraph, node::{WotFollow, WotNode}};
+use crate::{prediction::{graph::WotGraph, node::{WotFollow, WotNode}}, public_key::PublicKey, time::unix_now};
 
-use super::follow::Follow;
+use super::{follow::Follow, petnames::sanitize_alias, succession::Succession, list_edit::{ListChange, ChangeOutcome, ListEditError, validate_weight}};
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FollowList {
-    pub pubkey: String,
+    pub pubkey: PublicKey,
     #[serde(default = "default_alias")]
     pub alias: String,
     pub follows: Vec<Follow>,
+    /// Pubkeys that are never trusted, neither as list nor as class.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<PublicKey>,
+    /// Set when the key of this list was rotated. See `Succession`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub succession: Option<Succession>,
 }
 
 fn default_alias() -> String{
@@ -20,35 +28,56 @@ fn default_alias() -> String{
 
 impl std::fmt::Display for FollowList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut name = self.pubkey.clone();
-        if self.alias.len() > 0 {
-            name = format!("{} ({})", name, self.alias);
-        }
-        let follow_strings: Vec<String> = self
-            .get_unique_follows()
-            .iter()
-            .map(|follow| format!("- {}", { follow }))
-            .collect();
-        write!(f, "List {}\n{}", name, follow_strings.join("\n"))
+        let label = |pubkey: &str| {
+            if self.pubkey == pubkey && !self.alias.is_empty() {
+                format!("{} ({})", pubkey, sanitize_alias(&self.alias))
+            } else {
+                pubkey.to_string()
+            }
+        };
+        write!(f, "{}", self.format_with_labels(label))
     }
 }
 
 impl FollowList {
-    pub fn new(pubkey: &str, alias: &str) -> Self {
+    pub fn new(pubkey: PublicKey, alias: &str) -> Self {
         FollowList {
-            pubkey: pubkey.to_string(),
+            pubkey,
             alias: alias.to_string(),
             follows: vec![],
+            blocks: vec![],
+            succession: None,
         }
     }
-    pub fn new_with_follows(pubkey: &str, alias: &str , follows: Vec<Follow>) -> Self {
+    pub fn new_with_follows(pubkey: PublicKey, alias: &str , follows: Vec<Follow>) -> Self {
         FollowList {
-            pubkey: pubkey.to_string(),
+            pubkey,
             alias: alias.to_string(),
             follows: follows,
+            blocks: vec![],
+            succession: None,
         }
     }
 
+    /**
+     * Like `Display` but every pubkey is replaced by `label(pubkey)`. See `KeyNames::label`.
+     * The alias of the list is only shown if `label` adds it.
+     */
+    pub fn format_with_labels(&self, label: impl Fn(&str) -> String) -> String {
+        let name = label(&self.pubkey);
+        let mut lines: Vec<String> = self
+            .get_unique_follows()
+            .iter()
+            .map(|follow| format!("- {}", follow.format_with_label(&label(follow.pubkey()))))
+            .collect();
+        lines.extend(self.blocks.iter().map(|pubkey| format!("- ⛔ {}", label(pubkey))));
+        if let Some(succession) = &self.succession {
+            let note = if self.verified_successor().is_some() { "" } else { " (invalid signature, ignored)" };
+            lines.insert(0, format!("- ➡ moved to {}{}", label(&succession.successor), note));
+        };
+        format!("List {}\n{}", name, lines.join("\n"))
+    }
+
     pub fn from_path(path: &Path) -> Result<Self, String> {
         let str_res = fs::read_to_string(path);
         if let Err(e) = str_res {
@@ -70,12 +99,127 @@ impl FollowList {
     /**
      * Retuns all pubkeys in this list
      */
-    pub fn get_all_pubkeys(&self) -> HashSet<String> {
-        let mut set: HashSet<String> = self.follows.iter().map(|follow| follow.pubkey().clone()).collect();
+    pub fn get_all_pubkeys(&self) -> HashSet<PublicKey> {
+        let mut set: HashSet<PublicKey> = self.follows.iter().map(|follow| follow.pubkey().clone()).collect();
         set.insert(self.pubkey.clone());
         set
     }
 
+    /**
+     * Successor key if the list has a succession statement with a valid signature.
+     */
+    pub fn verified_successor(&self) -> Option<&PublicKey> {
+        let succession = self.succession.as_ref()?;
+        succession.verify(&self.pubkey).ok()?;
+        Some(&succession.successor)
+    }
+
+    /**
+     * True if any follow points to `pubkey`.
+     */
+    pub fn is_following(&self, pubkey: &PublicKey) -> bool {
+        self.follows.iter().any(|follow| follow.pubkey() == pubkey)
+    }
+
+    /**
+     * Adds the follow or replaces the follow with the same pubkey and domain.
+     */
+    pub fn upsert_follow(&mut self, follow: Follow) -> Result<ChangeOutcome, ListEditError> {
+        validate_weight(*follow.weight())?;
+        if follow.domain().is_none() && *follow.pubkey() == self.pubkey {
+            return Err(ListEditError::SelfFollow);
+        };
+        if follow.domain().is_some_and(|domain| domain.trim().is_empty()) {
+            return Err(ListEditError::EmptyDomain);
+        };
+        if follow.domain().is_some() && !follow.scope().is_empty() {
+            return Err(ListEditError::ScopeWithDomain);
+        };
+        let index = self.follows.iter().position(|existing| *existing == follow);
+        match index {
+            Some(index) => {
+                let previous = std::mem::replace(&mut self.follows[index], follow);
+                Ok(ChangeOutcome::Updated { previous })
+            },
+            None => {
+                self.follows.push(follow);
+                Ok(ChangeOutcome::Added)
+            },
+        }
+    }
+
+    /**
+     * Removes the follow with this pubkey and domain. Returns the removed follow.
+     */
+    pub fn remove_follow(&mut self, pubkey: &PublicKey, domain: Option<&str>) -> Result<ChangeOutcome, ListEditError> {
+        let index = self.follows.iter().position(|existing| existing.pubkey() == pubkey && existing.domain() == domain);
+        match index {
+            Some(index) => Ok(ChangeOutcome::Removed(self.follows.remove(index))),
+            None => Err(ListEditError::FollowNotFound { pubkey: pubkey.clone(), domain: domain.map(|domain| domain.to_string()) }),
+        }
+    }
+
+    /**
+     * Changes the weight of an existing follow. Timestamps and scope are kept.
+     */
+    pub fn set_weight(&mut self, pubkey: &PublicKey, domain: Option<&str>, weight: f32) -> Result<ChangeOutcome, ListEditError> {
+        validate_weight(weight)?;
+        let follow = self.follows.iter_mut().find(|existing| existing.pubkey() == pubkey && existing.domain() == domain);
+        if follow.is_none() {
+            return Err(ListEditError::FollowNotFound { pubkey: pubkey.clone(), domain: domain.map(|domain| domain.to_string()) });
+        };
+        let follow = follow.unwrap();
+        if follow.1 == weight {
+            return Ok(ChangeOutcome::Unchanged);
+        };
+        let previous = follow.clone();
+        follow.1 = weight;
+        Ok(ChangeOutcome::Updated { previous })
+    }
+
+    pub fn block(&mut self, pubkey: &PublicKey) -> Result<ChangeOutcome, ListEditError> {
+        if *pubkey == self.pubkey {
+            return Err(ListEditError::SelfBlock);
+        };
+        if self.blocks.contains(pubkey) {
+            return Ok(ChangeOutcome::Unchanged);
+        };
+        self.blocks.push(pubkey.clone());
+        Ok(ChangeOutcome::Blocked)
+    }
+
+    pub fn unblock(&mut self, pubkey: &PublicKey) -> Result<ChangeOutcome, ListEditError> {
+        if !self.blocks.contains(pubkey) {
+            return Err(ListEditError::BlockNotFound(pubkey.clone()));
+        };
+        self.blocks.retain(|blocked| blocked != pubkey);
+        Ok(ChangeOutcome::Unblocked)
+    }
+
+    pub fn apply(&mut self, change: ListChange) -> Result<ChangeOutcome, ListEditError> {
+        match change {
+            ListChange::Upsert(follow) => self.upsert_follow(follow),
+            ListChange::Remove { pubkey, domain } => self.remove_follow(&pubkey, domain.as_deref()),
+            ListChange::SetWeight { pubkey, domain, weight } => self.set_weight(&pubkey, domain.as_deref(), weight),
+            ListChange::Block(pubkey) => self.block(&pubkey),
+            ListChange::Unblock(pubkey) => self.unblock(&pubkey),
+        }
+    }
+
+    /**
+     * Applies all changes or none. On failure the list is left untouched.
+     */
+    pub fn bulk_apply(&mut self, changes: Vec<ListChange>) -> Result<Vec<ChangeOutcome>, ListEditError> {
+        let mut edited = self.clone();
+        let mut outcomes = vec![];
+        for (index, change) in changes.into_iter().enumerate() {
+            let outcome = edited.apply(change).map_err(|error| ListEditError::Bulk { index, error: Box::new(error) })?;
+            outcomes.push(outcome);
+        };
+        *self = edited;
+        Ok(outcomes)
+    }
+
     pub fn to_json(&self) -> String {
         serde_json::to_string_pretty(&self).unwrap()
     }
@@ -89,42 +233,87 @@ impl FollowList {
 
 impl Into<WotGraph> for Vec<FollowList> {
     fn into(self) -> WotGraph {
-        let mut list_nodes: Vec<WotNode> = self.iter().map(|list| {
-            let follows: Vec<WotFollow> = list.get_unique_follows().iter().map(|follow| {
-                WotFollow::new(list.pubkey.as_str(), follow.pubkey(), follow.weight().clone(), follow.domain())
-            }).collect();
-            let node = WotNode::new_list(&list.pubkey, &list.alias, follows);
-            node
+        lists_into_graph(self, unix_now(), None)
+    }
+}
+
+/**
+ * Builds the graph at time `now`. Expired follows are dropped.
+ * With `half_life` in seconds, weights decay by age. See `Follow::weight_at`.
+ * A list with a verified succession only follows its successor. The other follows of a retired key are ignored.
+ */
+pub fn lists_into_graph(lists: Vec<FollowList>, now: u64, half_life: Option<u64>) -> WotGraph {
+    let mut list_nodes: Vec<WotNode> = lists.iter().map(|list| {
+        if let Some(successor) = list.verified_successor() {
+            let follow = WotFollow {
+                source_pubkey: list.pubkey.clone(),
+                target_pubkey: successor.clone(),
+                weight: 1.0,
+                attribution: None,
+                scope: vec![],
+            };
+            return WotNode { pubkey: list.pubkey.clone(), alias: list.alias.clone(), follows: vec![follow] };
+        };
+        let follows: Vec<WotFollow> = list.get_unique_follows().iter().filter_map(|follow| {
+            let weight = follow.weight_at(now, half_life)?;
+            Some(WotFollow {
+                source_pubkey: list.pubkey.clone(),
+                target_pubkey: follow.pubkey().clone(),
+                weight,
+                attribution: follow.domain().map(|domain| domain.to_string()),
+                scope: follow.scope().to_vec(),
+            })
         }).collect();
+        WotNode { pubkey: list.pubkey.clone(), alias: list.alias.clone(), follows }
+    }).collect();
 
-        let existing_pubkeys: HashSet<String> = list_nodes.iter().map(|node| node.pubkey.clone()).collect();
-        let all_pubkeys: HashSet<String> = self.iter().map(|list| list.get_all_pubkeys()).flatten().collect();
+    let existing_pubkeys: HashSet<PublicKey> = list_nodes.iter().map(|node| node.pubkey.clone()).collect();
+    let followed_pubkeys: HashSet<PublicKey> = list_nodes.iter()
+        .flat_map(|node| node.follows.iter().map(|follow| follow.target_pubkey.clone()))
+        .collect();
 
-        let diff: Vec<String> = all_pubkeys.difference(&existing_pubkeys).map(|pubkey| pubkey.clone()).collect();
-        let mut missing_nodes = diff.into_iter().map(|pubkey| WotNode::new_list(&pubkey, "", vec![])).collect::<Vec<WotNode>>();
-        let mut result = vec![];
-        result.append(&mut list_nodes);
-        result.append(&mut missing_nodes);
+    let diff: Vec<PublicKey> = followed_pubkeys.difference(&existing_pubkeys).cloned().collect();
+    let mut missing_nodes = diff.into_iter().map(|pubkey| WotNode { pubkey, alias: "".to_string(), follows: vec![] }).collect::<Vec<WotNode>>();
+    let mut result = vec![];
+    result.append(&mut list_nodes);
+    result.append(&mut missing_nodes);
 
-        WotGraph::new(result)
-    }
+    let mut graph = WotGraph::new(result);
+    for list in lists.iter().filter(|list| !list.blocks.is_empty()) {
+        graph.blocks.insert(list.pubkey.clone(), list.blocks.iter().cloned().collect());
+    };
+    graph
+}
+
+/**
+ * Lists with a follow to a retired key that has a verified successor. These lists should follow the successor instead.
+ * Returns pairs of (list, retired key).
+ */
+pub fn lists_following_retired_keys(lists: &[FollowList]) -> Vec<(&FollowList, &PublicKey)> {
+    let retired: Vec<&PublicKey> = lists.iter().filter(|list| list.verified_successor().is_some()).map(|list| &list.pubkey).collect();
+    lists.iter().flat_map(|list| {
+        retired.iter()
+            .filter(|retired_pubkey| **retired_pubkey != &list.pubkey && list.is_following(retired_pubkey))
+            .map(move |retired_pubkey| (list, *retired_pubkey))
+    }).collect()
 }
 
 
 
 #[cfg(test)]
 mod tests {
-    use crate::{config_directory::{follow_list::FollowList, follow::Follow}, prediction::graph::WotGraph};
+    use crate::public_key::PublicKey;
+    use crate::{config_directory::{follow_list::{FollowList, lists_into_graph, lists_following_retired_keys}, follow::Follow, succession::Succession, list_edit::{ListChange, ChangeOutcome, ListEditError}}, prediction::graph::WotGraph};
 
 
     #[test]
     fn to_json_and_back() {
         let list = FollowList::new_with_follows(
-            "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso",
+            PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap(),
             "myList",
             vec![
-                Follow::new("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy", 1.0/3.0, None),
-                Follow::new("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy", -1.0, Some("example.com"))
+                Follow::new(PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap(), 1.0/3.0, None),
+                Follow::new(PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap(), -1.0, Some("example.com"))
             ],
         );
 
@@ -156,15 +345,39 @@ mod tests {
         assert_eq!(list.follows[0].0, "pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy");
     }
 
+    #[test]
+    fn from_json_canonicalizes_pubkeys() {
+        let json = r#"{
+            "pubkey": "rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso",
+            "follows": [
+              ["kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy", 1.0],
+              ["pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy", 1.0]
+            ]
+          }"#;
+        let list = FollowList::from_json(json).unwrap();
+        assert_eq!(list.pubkey, "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso");
+        assert_eq!(list.get_unique_follows().len(), 1);
+    }
+
+    #[test]
+    fn from_json_rejects_malformed_pubkeys() {
+        let json = r#"{
+            "pubkey": "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso",
+            "follows": [["pk:hello", 1.0]]
+          }"#;
+        let result = FollowList::from_json(json);
+        assert!(result.is_err_and(|e| e.to_string().contains("not a valid public key")));
+    }
+
     #[test]
     fn unique_follows() {
         let follows = vec![
-            Follow::new("1", 1.0, None),
-            Follow::new("1", 1.0, Some("example.com")),
-            Follow::new("1", 0.1, Some("example.com")),
+            Follow::new(PublicKey::new_unchecked("1"), 1.0, None),
+            Follow::new(PublicKey::new_unchecked("1"), 1.0, Some("example.com")),
+            Follow::new(PublicKey::new_unchecked("1"), 0.1, Some("example.com")),
         ];
         
-        let list = FollowList::new_with_follows("me", "", follows);
+        let list = FollowList::new_with_follows(PublicKey::new_unchecked("me"), "", follows);
         let unique: Vec<&Follow> = Vec::from_iter(list.get_unique_follows().into_iter());
         assert_eq!(unique.len(), 2);
     }
@@ -172,25 +385,25 @@ mod tests {
     #[test]
     fn transform_nodes() {
         let list1 = FollowList::new_with_follows(
-            "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso",
+            PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap(),
             "me",
             vec![
-                Follow::new("pk:1bdbmmxenbxuybfai88f1xg1djrpujxix5hw6fh9am7f4x5wapey", 1.0, None),
-                Follow::new("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy", 1.0/3.0, None),
-                Follow::new("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy", -1.0, Some("example.com1")),
-                Follow::new("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy", 1.0/3.0, None),
-                Follow::new("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy", -1.0, Some("example.com1")),
-                Follow::new("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy", -1.0, Some("example.com2"))
+                Follow::new(PublicKey::parse("pk:1bdbmmxenbxuybfai88f1xg1djrpujxix5hw6fh9am7f4x5wapey").unwrap(), 1.0, None),
+                Follow::new(PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap(), 1.0/3.0, None),
+                Follow::new(PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap(), -1.0, Some("example.com1")),
+                Follow::new(PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap(), 1.0/3.0, None),
+                Follow::new(PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap(), -1.0, Some("example.com1")),
+                Follow::new(PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap(), -1.0, Some("example.com2"))
             ],
         );
         let list2 = FollowList::new_with_follows(
-            "pk:1bdbmmxenbxuybfai88f1xg1djrpujxix5hw6fh9am7f4x5wapey",
+            PublicKey::parse("pk:1bdbmmxenbxuybfai88f1xg1djrpujxix5hw6fh9am7f4x5wapey").unwrap(),
             "Alice",
             vec![
-                Follow::new("pk:s9y93dtpoibsfcnct35onkeyuiup9dfxwpftgerdqd7u84jcmkfy", 1.0/3.0, None),
-                Follow::new("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy", -1.0, Some("example.com1")),
-                Follow::new("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy", 1.0/3.0, None),
-                Follow::new("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso", 1.0/3.0, None),
+                Follow::new(PublicKey::parse("pk:s9y93dtpoibsfcnct35onkeyuiup9dfxwpftgerdqd7u84jcmkfy").unwrap(), 1.0/3.0, None),
+                Follow::new(PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap(), -1.0, Some("example.com1")),
+                Follow::new(PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap(), 1.0/3.0, None),
+                Follow::new(PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap(), 1.0/3.0, None),
             ],
         );
         let graph: WotGraph = vec![list1, list2].into();
@@ -207,18 +420,18 @@ mod tests {
     #[test]
     fn pubkey_is_list_and_domain() {
         let list1 = FollowList::new_with_follows(
-            "me",
+            PublicKey::new_unchecked("me"),
             "me",
             vec![
-                Follow::new("d2", 1.0, None),
-                Follow::new("d2", 0.5, Some("example.com")),
+                Follow::new(PublicKey::new_unchecked("d2"), 1.0, None),
+                Follow::new(PublicKey::new_unchecked("d2"), 0.5, Some("example.com")),
             ],
         );
         let list2 = FollowList::new_with_follows(
-            "d2",
+            PublicKey::new_unchecked("d2"),
             "d2",
             vec![
-                Follow::new("d1", 0.5, Some("example.com")),
+                Follow::new(PublicKey::new_unchecked("d1"), 0.5, Some("example.com")),
             ],
         );
 
@@ -226,4 +439,114 @@ mod tests {
 
         assert_eq!(graph.nodes.len(), 3);
     }
+
+    #[test]
+    fn drop_expired_and_decay() {
+        let day = 24 * 60 * 60;
+        let now = 100 * day;
+        let list = FollowList::new_with_follows(PublicKey::new_unchecked("me"), "", vec![
+            Follow::new_with_timestamps(PublicKey::new_unchecked("fresh"), 1.0, None, Some(now), None),
+            Follow::new_with_timestamps(PublicKey::new_unchecked("old"), 1.0, None, Some(now - 30 * day), None),
+            Follow::new_with_timestamps(PublicKey::new_unchecked("expired"), 1.0, None, Some(now - day), Some(now - 1)),
+            Follow::new(PublicKey::new_unchecked("undated"), 1.0, None),
+        ]);
+        let graph = lists_into_graph(vec![list], now, Some(30 * day));
+        assert!(graph.get_node("expired").is_none());
+        let me = graph.get_node("me").unwrap();
+        assert_eq!(me.follows.len(), 3);
+        let weight = |pubkey: &str| me.follows.iter().find(|follow| follow.target_pubkey == pubkey).unwrap().weight;
+        assert_eq!(weight("fresh"), 1.0);
+        assert!((weight("old") - 0.5).abs() < 0.001);
+        assert_eq!(weight("undated"), 1.0);
+    }
+
+    #[test]
+    fn succession_edge() {
+        let old = pkarr::Keypair::random();
+        let old_pubkey = PublicKey::from(&old.public_key());
+        let new_pubkey = PublicKey::from(&pkarr::Keypair::random().public_key());
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let me = PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap();
+
+        let succession = Succession::sign(&old, &new_pubkey, 1700000000);
+        let lists = |succession: &Succession| {
+            let mut old_list = FollowList::new_with_follows(old_pubkey.clone(), "", vec![Follow::new(alice.clone(), 1.0, Some("example.com"))]);
+            old_list.succession = Some(succession.clone());
+            let me_list = FollowList::new_with_follows(me.clone(), "me", vec![Follow::new(old_pubkey.clone(), 0.5, None)]);
+            vec![old_list, me_list]
+        };
+        assert_eq!(lists_following_retired_keys(&lists(&succession)).len(), 1);
+
+        let graph = lists_into_graph(lists(&succession), 0, None);
+        let follows = &graph.get_node(&old_pubkey).unwrap().follows;
+        assert_eq!(follows.len(), 1); // Follows of the retired key are ignored.
+        assert_eq!(follows[0].target_pubkey, new_pubkey);
+        assert_eq!(follows[0].weight, 1.0);
+        assert!(graph.get_node(&new_pubkey).is_some());
+
+        // A forged statement is ignored and the list stays as it is.
+        let forged = Succession { successor: alice.clone(), ..succession };
+        assert!(lists(&forged)[0].verified_successor().is_none());
+        assert!(lists_following_retired_keys(&lists(&forged)).is_empty());
+        let graph = lists_into_graph(lists(&forged), 0, None);
+        assert_eq!(graph.get_node(&old_pubkey).unwrap().follows[0].attribution, Some("example.com".to_string()));
+    }
+
+    #[test]
+    fn edit_follows() {
+        let me = PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let mut list = FollowList::new(me.clone(), "me");
+
+        assert_eq!(list.upsert_follow(Follow::new(alice.clone(), 0.5, None)), Ok(ChangeOutcome::Added));
+        assert!(matches!(list.upsert_follow(Follow::new(alice.clone(), 0.8, None)), Ok(ChangeOutcome::Updated { .. })));
+        assert_eq!(list.upsert_follow(Follow::new(alice.clone(), 1.0, Some("example.com"))), Ok(ChangeOutcome::Added));
+        assert_eq!(list.follows.len(), 2);
+        assert_eq!(*list.follows[0].weight(), 0.8);
+
+        assert!(matches!(list.upsert_follow(Follow::new(alice.clone(), f32::NAN, None)), Err(ListEditError::InvalidWeight(_))));
+        assert!(matches!(list.upsert_follow(Follow::new(alice.clone(), 2.0, None)), Err(ListEditError::InvalidWeight(_))));
+        assert_eq!(list.upsert_follow(Follow::new(me.clone(), 1.0, None)), Err(ListEditError::SelfFollow));
+        assert_eq!(list.upsert_follow(Follow::new(alice.clone(), 1.0, Some("a.com")).with_scope(vec!["b.com".to_string()])), Err(ListEditError::ScopeWithDomain));
+
+        let created = Follow::new_with_timestamps(alice.clone(), 0.5, None, Some(1000), None);
+        list.upsert_follow(created).unwrap();
+        assert!(matches!(list.set_weight(&alice, None, 0.25), Ok(ChangeOutcome::Updated { .. })));
+        assert_eq!(list.set_weight(&alice, None, 0.25), Ok(ChangeOutcome::Unchanged));
+        assert_eq!(list.follows[0].created(), Some(1000));
+        assert_eq!(*list.follows[0].weight(), 0.25);
+
+        assert!(matches!(list.remove_follow(&alice, Some("example.com")), Ok(ChangeOutcome::Removed(_))));
+        assert!(matches!(list.remove_follow(&alice, Some("example.com")), Err(ListEditError::FollowNotFound { .. })));
+
+        assert_eq!(list.block(&me), Err(ListEditError::SelfBlock));
+        assert_eq!(list.block(&alice), Ok(ChangeOutcome::Blocked));
+        assert_eq!(list.block(&alice), Ok(ChangeOutcome::Unchanged));
+        assert_eq!(list.unblock(&alice), Ok(ChangeOutcome::Unblocked));
+        assert_eq!(list.unblock(&alice), Err(ListEditError::BlockNotFound(alice.clone())));
+    }
+
+    #[test]
+    fn bulk_apply_is_atomic() {
+        let me = PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let dave = PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap();
+        let mut list = FollowList::new(me, "me");
+
+        let result = list.bulk_apply(vec![
+            ListChange::Upsert(Follow::new(alice.clone(), 1.0, None)),
+            ListChange::Remove { pubkey: dave.clone(), domain: None },
+        ]);
+        assert!(matches!(result, Err(ListEditError::Bulk { index: 1, .. })));
+        assert!(list.follows.is_empty());
+
+        let outcomes = list.bulk_apply(vec![
+            ListChange::Upsert(Follow::new(alice.clone(), 1.0, None)),
+            ListChange::Upsert(Follow::new(dave.clone(), -0.5, None)),
+            ListChange::SetWeight { pubkey: alice.clone(), domain: None, weight: 0.5 },
+        ]).unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(list.follows.len(), 2);
+        assert_eq!(*list.follows[0].weight(), 0.5);
+    }
 }
@@ -0,0 +1,235 @@
+use std::{fmt, fs::{self, File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+
+use crate::public_key::PublicKey;
+use super::{follow::Follow, follow_list::FollowList, list_edit::ListChange};
+
+
+/**
+ * One change of a list with the complete list before and after the change, so any state can be restored.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    /// Sequence number, starts at 1.
+    pub id: u64,
+    /// Unix timestamp in seconds.
+    pub time: u64,
+    pub list: PublicKey,
+    /// Human readable description, for example `add pk:... 0.5`.
+    pub reason: String,
+    pub before: FollowList,
+    pub after: FollowList,
+}
+
+/**
+ * Difference between the list before and after a journal entry.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListDiff {
+    Added(Follow),
+    Removed(Follow),
+    Changed { before: Follow, after: Follow },
+    Blocked(PublicKey),
+    Unblocked(PublicKey),
+}
+
+impl JournalEntry {
+    pub fn diff(&self) -> Vec<ListDiff> {
+        let mut diff = vec![];
+        for follow in self.after.follows.iter() {
+            match self.before.follows.iter().find(|before| *before == follow) {
+                None => diff.push(ListDiff::Added(follow.clone())),
+                Some(before) => {
+                    let is_changed = before.weight() != follow.weight() || before.expires() != follow.expires() || before.scope() != follow.scope();
+                    if is_changed {
+                        diff.push(ListDiff::Changed { before: before.clone(), after: follow.clone() });
+                    };
+                },
+            };
+        };
+        for follow in self.before.follows.iter().filter(|before| !self.after.follows.contains(before)) {
+            diff.push(ListDiff::Removed(follow.clone()));
+        };
+        for pubkey in self.after.blocks.iter().filter(|pubkey| !self.before.blocks.contains(pubkey)) {
+            diff.push(ListDiff::Blocked(pubkey.clone()));
+        };
+        for pubkey in self.before.blocks.iter().filter(|pubkey| !self.after.blocks.contains(pubkey)) {
+            diff.push(ListDiff::Unblocked(pubkey.clone()));
+        };
+        diff
+    }
+}
+
+impl fmt::Display for ListChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let domain = |domain: &Option<String>| domain.as_ref().map(|domain| format!(" {}", domain)).unwrap_or_default();
+        match self {
+            ListChange::Upsert(follow) => write!(f, "add {} {}{}", follow.pubkey(), follow.weight(), domain(&follow.2)),
+            ListChange::Remove { pubkey, domain: d } => write!(f, "remove {}{}", pubkey, domain(d)),
+            ListChange::SetWeight { pubkey, domain: d, weight } => write!(f, "set weight {}{} {}", pubkey, domain(d), weight),
+            ListChange::Block(pubkey) => write!(f, "block {}", pubkey),
+            ListChange::Unblock(pubkey) => write!(f, "unblock {}", pubkey),
+        }
+    }
+}
+
+
+/**
+ * Append-only journal of list changes. Stored in `<dir>/journal.jsonl`, one json entry per line.
+ */
+pub struct Journal {
+    pub path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: PathBuf) -> Self {
+        Journal { path }
+    }
+
+    /**
+     * All entries, oldest first. Empty if the journal does not exist yet.
+     */
+    pub fn read(&self) -> Result<Vec<JournalEntry>, String> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        };
+        let content = fs::read_to_string(&self.path).map_err(|e| format!("Failed to read journal \"{}\". {}", self.path.to_str().unwrap(), e))?;
+        content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()).map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| format!("Failed to parse line {} of journal \"{}\". {}", index + 1, self.path.to_str().unwrap(), e))
+        }).collect()
+    }
+
+    /**
+     * Id of the newest entry. 0 if the journal is empty.
+     * Only reads the last line, so appending doesn't get slower as the journal grows.
+     */
+    fn last_id(&self) -> Result<u64, String> {
+        let file = File::open(&self.path);
+        if let Err(e) = &file {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                return Ok(0);
+            };
+        };
+        let line = file.and_then(|mut file| read_last_line(&mut file))
+            .map_err(|e| format!("Failed to read journal \"{}\". {}", self.path.to_str().unwrap(), e))?;
+        if line.is_none() {
+            return Ok(0);
+        };
+        let entry: EntryId = serde_json::from_str(&line.unwrap())
+            .map_err(|e| format!("Failed to parse the last line of journal \"{}\". {}", self.path.to_str().unwrap(), e))?;
+        Ok(entry.id)
+    }
+
+    /**
+     * Appends a change and returns the new entry. Callers must hold the directory lock.
+     */
+    pub fn append(&self, time: u64, reason: &str, before: FollowList, after: FollowList) -> Result<JournalEntry, String> {
+        let id = self.last_id()? + 1;
+        let entry = JournalEntry { id, time, list: after.pubkey.clone(), reason: reason.to_string(), before, after };
+        append_line(&self.path, &serde_json::to_string(&entry).unwrap())
+            .map_err(|e| format!("Failed to write journal \"{}\". {}", self.path.to_str().unwrap(), e))?;
+        Ok(entry)
+    }
+}
+
+/**
+ * Only the id of an entry. Parsing it skips the lists.
+ */
+#[derive(Deserialize)]
+struct EntryId {
+    id: u64,
+}
+
+/**
+ * Last non-empty line of the file. Reads backwards in chunks until the line start is found.
+ */
+fn read_last_line(file: &mut File) -> Result<Option<String>, std::io::Error> {
+    const CHUNK_SIZE: u64 = 4096;
+    let mut end = file.metadata()?.len();
+    let mut tail: Vec<u8> = vec![];
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK_SIZE);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend(tail);
+        tail = chunk;
+        end = start;
+
+        let trimmed = tail.trim_ascii_end();
+        if let Some(newline) = trimmed.iter().rposition(|byte| *byte == b'\n') {
+            return Ok(Some(String::from_utf8_lossy(&trimmed[newline + 1..]).to_string()));
+        };
+    };
+    let trimmed = tail.trim_ascii_end();
+    if trimmed.is_empty() {
+        return Ok(None);
+    };
+    Ok(Some(String::from_utf8_lossy(trimmed).to_string()))
+}
+
+fn append_line(path: &Path, line: &str) -> Result<(), std::io::Error> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(format!("{}\n", line).as_bytes())?;
+    file.sync_all()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, path::PathBuf};
+    use crate::{config_directory::{follow::Follow, follow_list::FollowList}, public_key::PublicKey};
+    use super::{Journal, ListDiff};
+
+    #[test]
+    fn append_read_and_diff() {
+        let path = PathBuf::from("/tmp/pknames_append_read_and_diff.jsonl");
+        let _ = std::fs::remove_file(&path); // Delete so the test can work again even though it failed before.
+        let journal = Journal::new(path.clone());
+        assert!(journal.read().unwrap().is_empty());
+
+        let me = PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap();
+        let alice = PublicKey::parse("pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy").unwrap();
+        let dave = PublicKey::parse("pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy").unwrap();
+        let before = FollowList::new_with_follows(me.clone(), "me", vec![Follow::new(alice.clone(), 1.0, None), Follow::new(dave.clone(), 1.0, None)]);
+        let mut after = FollowList::new_with_follows(me.clone(), "me", vec![Follow::new(alice.clone(), 0.5, None), Follow::new(alice.clone(), 1.0, Some("example.com"))]);
+        after.blocks.push(dave.clone());
+
+        journal.append(10, "first", FollowList::new(me.clone(), "me"), before.clone()).unwrap();
+        let entry = journal.append(20, "second", before, after).unwrap();
+        assert_eq!(entry.id, 2);
+        let entries = journal.read().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].reason, "second");
+
+        let diff = entries[1].diff();
+        assert_eq!(diff.len(), 4);
+        assert!(matches!(&diff[0], ListDiff::Changed { after, .. } if *after.weight() == 0.5));
+        assert!(matches!(&diff[1], ListDiff::Added(follow) if follow.domain() == Some("example.com")));
+        assert!(matches!(&diff[2], ListDiff::Removed(follow) if *follow.pubkey() == dave));
+        assert_eq!(diff[3], ListDiff::Blocked(dave));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ids_continue_after_large_entries() {
+        let path = PathBuf::from("/tmp/pknames_ids_continue_after_large_entries.jsonl");
+        let _ = std::fs::remove_file(&path); // Delete so the test can work again even though it failed before.
+        let journal = Journal::new(path.clone());
+
+        // Entries are larger than the chunks the last line is read in.
+        let me = PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso").unwrap();
+        let follows: Vec<Follow> = (0..100).map(|_| Follow::new(PublicKey::from(&pkarr::Keypair::random().public_key()), 1.0, None)).collect();
+        let list = FollowList::new_with_follows(me.clone(), "me", follows);
+        for id in 1..=3 {
+            let entry = journal.append(id, "large", list.clone(), list.clone()).unwrap();
+            assert_eq!(entry.id, id);
+        };
+        assert!(std::fs::metadata(&path).unwrap().len() > 3 * 4096);
+
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"\n\n").unwrap();
+        assert_eq!(journal.append(4, "after blank lines", list.clone(), list).unwrap().id, 4);
+        assert_eq!(journal.read().unwrap().len(), 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
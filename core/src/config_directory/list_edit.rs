@@ -0,0 +1,103 @@
+use std::fmt;
+
+use crate::public_key::PublicKey;
+use super::follow::Follow;
+
+
+/**
+ * A single change to a follow list. See `FollowList::apply` and `MainDirectory::bulk_apply`.
+ */
+#[derive(Debug, Clone)]
+pub enum ListChange {
+    /// Adds the follow or replaces the follow with the same pubkey and domain.
+    Upsert(Follow),
+    Remove { pubkey: PublicKey, domain: Option<String> },
+    SetWeight { pubkey: PublicKey, domain: Option<String>, weight: f32 },
+    Block(PublicKey),
+    Unblock(PublicKey),
+}
+
+/**
+ * What a successfully applied change did to the list.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeOutcome {
+    Added,
+    /// The follow or weight that was replaced.
+    Updated { previous: Follow },
+    Removed(Follow),
+    Blocked,
+    Unblocked,
+    /// The list already was in the requested state.
+    Unchanged,
+}
+
+impl ChangeOutcome {
+    pub fn is_change(&self) -> bool {
+        *self != ChangeOutcome::Unchanged
+    }
+}
+
+/**
+ * Why a change was rejected. The list is left untouched.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListEditError {
+    /// Weights must be finite and within [-1, 1].
+    InvalidWeight(f32),
+    /// A list can't follow itself without a domain.
+    SelfFollow,
+    SelfBlock,
+    /// Scopes only apply to follows without domain.
+    ScopeWithDomain,
+    EmptyDomain,
+    FollowNotFound { pubkey: PublicKey, domain: Option<String> },
+    BlockNotFound(PublicKey),
+    /// The perspective has no list of its own.
+    ReadOnly(String),
+    /// Reading or writing the list failed.
+    Storage(String),
+    /// Change number `index` of a bulk apply failed. No change was applied.
+    Bulk { index: usize, error: Box<ListEditError> },
+}
+
+impl fmt::Display for ListEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListEditError::InvalidWeight(weight) => write!(f, "Invalid weight {}. Weights must be between -1 and 1.", weight),
+            ListEditError::SelfFollow => write!(f, "A list can't follow itself without a domain."),
+            ListEditError::SelfBlock => write!(f, "You can't block yourself."),
+            ListEditError::ScopeWithDomain => write!(f, "A scope only applies to list follows without domain."),
+            ListEditError::EmptyDomain => write!(f, "Domain must not be empty."),
+            ListEditError::FollowNotFound { pubkey, domain } => match domain {
+                Some(domain) => write!(f, "Follow {} {} not found in the list.", pubkey, domain),
+                None => write!(f, "Follow {} not found in the list.", pubkey),
+            },
+            ListEditError::BlockNotFound(pubkey) => write!(f, "Block {} not found in the list.", pubkey),
+            ListEditError::ReadOnly(reason) => write!(f, "{}", reason),
+            ListEditError::Storage(reason) => write!(f, "{}", reason),
+            ListEditError::Bulk { index, error } => write!(f, "Change {} failed, nothing was applied. {}", index + 1, error),
+        }
+    }
+}
+
+/**
+ * Checks a weight before it is written into a list. See `Follow` for the meaning of weights.
+ */
+pub fn validate_weight(weight: f32) -> Result<(), ListEditError> {
+    if !weight.is_finite() || !(-1.0..=1.0).contains(&weight) {
+        return Err(ListEditError::InvalidWeight(weight));
+    };
+    Ok(())
+}
+
+/**
+ * Weight as used in the graph. Lists of others are not validated, so out-of-range weights are clamped to [-1, 1]
+ * and NaN or infinite weights are ignored (None). A list can't outweigh others with enormous weights.
+ */
+pub fn clamp_weight(weight: f32) -> Option<f32> {
+    if !weight.is_finite() {
+        return None;
+    };
+    Some(weight.clamp(-1.0, 1.0))
+}
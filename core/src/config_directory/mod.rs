@@ -1,3 +1,15 @@
 pub mod follow_list;
 pub mod follow;
-pub mod dirs;
\ No newline at end of file
+pub mod dirs;
+pub mod config;
+pub mod pins;
+pub mod petnames;
+pub mod perspective;
+pub mod secret;
+pub mod succession;
+pub mod bundle;
+pub mod list_edit;
+pub mod storage;
+pub mod journal;
+pub mod provenance;
+pub mod doctor;
@@ -0,0 +1,58 @@
+use crate::public_key::PublicKey;
+
+
+/**
+ * Name of the identity whose secret is stored in `<dir>/secret`.
+ */
+pub const DEFAULT_IDENTITY: &str = "default";
+
+/**
+ * Point of view the web of trust is evaluated from. The me list of the perspective is the root of the graph.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Perspective {
+    /// Named identity with its own secret key in the directory.
+    Identity(String),
+    /// Only a public key, for example "what would Alice see?". Read-only because there is no secret key.
+    PublicKey(PublicKey),
+}
+
+impl Default for Perspective {
+    fn default() -> Self {
+        Perspective::Identity(DEFAULT_IDENTITY.to_string())
+    }
+}
+
+impl std::fmt::Display for Perspective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Perspective::Identity(name) => write!(f, "identity {}", name),
+            Perspective::PublicKey(pubkey) => write!(f, "public key {}", pubkey),
+        }
+    }
+}
+
+/**
+ * Identity names are used as file names. Only letters, digits, `-` and `_` are allowed.
+ */
+pub fn validate_identity_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid identity name \"{}\". Use letters, digits, - and _ only.", name));
+    };
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::validate_identity_name;
+
+    #[test]
+    fn identity_names() {
+        assert!(validate_identity_name("org").is_ok());
+        assert!(validate_identity_name("my-org_2").is_ok());
+        assert!(validate_identity_name("").is_err());
+        assert!(validate_identity_name("../secret").is_err());
+        assert!(validate_identity_name("my org").is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fs, path::Path};
+use serde::{Deserialize, Serialize};
+
+use crate::public_key::PublicKey;
+use super::follow_list::FollowList;
+
+/// Minimum number of characters of a key prefix. Shorter prefixes match too many keys to be useful.
+const MIN_PREFIX_LENGTH: usize = 4;
+
+/// Maximum number of characters of a printed alias. Longer aliases are cut.
+const MAX_ALIAS_LENGTH: usize = 32;
+
+
+/**
+ * Local names for public keys, for example `dave-laptop`. Stored in `<dir>/petnames.json`.
+ * Only you can set petnames, so unlike aliases advertised in lists they can be trusted.
+ */
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct PetnameBook {
+    /// Petname -> pubkey
+    #[serde(default)]
+    pub petnames: BTreeMap<String, PublicKey>,
+}
+
+impl PetnameBook {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let str_res = fs::read_to_string(path);
+        if let Err(e) = str_res {
+            return Err(format!("Failed to read petnames \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        let book = PetnameBook::from_json(&str_res.unwrap());
+        if let Err(e) = book {
+            return Err(format!("Failed to parse petnames \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        Ok(book.unwrap())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /**
+     * Names `pubkey`. A pubkey has at most one petname, an existing one is replaced.
+     */
+    pub fn set(&mut self, petname: &str, pubkey: &PublicKey) -> Result<(), String> {
+        if petname.is_empty() || petname.chars().any(|c| c.is_whitespace()) {
+            return Err(format!("Invalid petname \"{}\". Petnames can't be empty or contain whitespace.", petname));
+        };
+        if petname.starts_with("pk:") || PublicKey::parse(petname).is_ok() {
+            return Err(format!("Invalid petname \"{}\". Petnames can't look like a public key.", petname));
+        };
+        self.petnames.retain(|_, existing| existing != pubkey);
+        self.petnames.insert(petname.to_string(), pubkey.clone());
+        Ok(())
+    }
+
+    /**
+     * Removes the petname. Returns the pubkey it pointed to.
+     */
+    pub fn remove(&mut self, petname: &str) -> Option<PublicKey> {
+        self.petnames.remove(petname)
+    }
+
+    pub fn get_pubkey(&self, petname: &str) -> Option<&PublicKey> {
+        self.petnames.get(petname)
+    }
+
+    pub fn get_petname(&self, pubkey: &str) -> Option<&String> {
+        self.petnames.iter().find(|(_, existing)| *existing == pubkey).map(|(petname, _)| petname)
+    }
+}
+
+
+/**
+ * Everything known about the names of public keys.
+ * Used to turn user input into pubkeys and pubkeys into readable labels.
+ */
+pub struct KeyNames {
+    pub me_pubkey: PublicKey,
+    pub petnames: PetnameBook,
+    /// Aliases advertised by lists themselves. Anybody can claim any alias, so they are untrusted.
+    pub nicknames: HashMap<PublicKey, String>,
+    /// All pubkeys found in the petname book and the lists. Used for prefix matching.
+    pub known_pubkeys: BTreeSet<PublicKey>,
+    /// Pubkey -> name of the local identities. See `MainDirectory::list_identities`.
+    pub identities: BTreeMap<PublicKey, String>,
+}
+
+impl KeyNames {
+    pub fn new(me_pubkey: &PublicKey, petnames: PetnameBook, lists: &[FollowList]) -> Self {
+        let mut known_pubkeys: BTreeSet<PublicKey> = petnames.petnames.values().cloned().collect();
+        known_pubkeys.insert(me_pubkey.clone());
+        let mut nicknames = HashMap::new();
+        for list in lists.iter() {
+            known_pubkeys.extend(list.get_all_pubkeys());
+            known_pubkeys.extend(list.blocks.iter().cloned());
+            let nickname = sanitize_alias(&list.alias);
+            if list.pubkey != *me_pubkey && !nickname.is_empty() {
+                nicknames.insert(list.pubkey.clone(), nickname);
+            };
+        };
+        KeyNames { me_pubkey: me_pubkey.clone(), petnames, nicknames, known_pubkeys, identities: BTreeMap::new() }
+    }
+
+    /**
+     * Adds the local identities so they are labeled and can be used as input by name.
+     */
+    pub fn add_identity(&mut self, name: &str, pubkey: &PublicKey) {
+        self.known_pubkeys.insert(pubkey.clone());
+        self.identities.insert(pubkey.clone(), name.to_string());
+    }
+
+    /**
+     * Pubkey with its name, if any. For example `pk:1zpo3... (dave-laptop)` or `pk:kgoxg... (~alice)` for untrusted nicknames.
+     */
+    pub fn label(&self, pubkey: &str) -> String {
+        if self.me_pubkey == pubkey {
+            return format!("{} (me)", pubkey);
+        };
+        if let Some(identity) = self.identities.get(pubkey) {
+            return format!("{} (identity {})", pubkey, identity);
+        };
+        if let Some(petname) = self.petnames.get_petname(pubkey) {
+            return format!("{} ({})", pubkey, petname);
+        };
+        if let Some(nickname) = self.nicknames.get(pubkey) {
+            return format!("{} (~{})", pubkey, nickname);
+        };
+        pubkey.to_string()
+    }
+
+    /**
+     * Turns user input into a pubkey. Accepts full public keys, petnames, identity names and unambiguous prefixes of known public keys.
+     * Nicknames are not accepted because they are untrusted.
+     */
+    pub fn resolve_input(&self, input: &str) -> Result<PublicKey, String> {
+        if let Some(pubkey) = self.petnames.get_pubkey(input) {
+            return Ok(pubkey.clone());
+        };
+        if let Some((pubkey, _)) = self.identities.iter().find(|(_, name)| *name == input) {
+            return Ok(pubkey.clone());
+        };
+        if let Ok(pubkey) = PublicKey::parse(input) {
+            return Ok(pubkey);
+        };
+
+        let prefix = input.trim_start_matches("pk:");
+        if prefix.len() < MIN_PREFIX_LENGTH {
+            return Err(format!("\"{}\" is neither a petname nor a public key. Key prefixes need at least {} characters.", input, MIN_PREFIX_LENGTH));
+        };
+        let matches: Vec<&PublicKey> = self.known_pubkeys.iter().filter(|pubkey| {
+            pubkey.to_z32().starts_with(prefix)
+        }).collect();
+        match matches.len() {
+            0 => Err(format!("\"{}\" is neither a petname nor a known public key.", input)),
+            1 => Ok(matches[0].clone()),
+            _ => {
+                let candidates: Vec<String> = matches.iter().map(|pubkey| self.label(pubkey)).collect();
+                Err(format!("\"{}\" is ambiguous. Candidates: {}", input, candidates.join(", ")))
+            }
+        }
+    }
+}
+
+/**
+ * Makes an alias safe to print, lists come from untrusted third parties. Removes control characters like newlines and terminal escapes
+ * as well as bidi overrides so an alias can't fake output lines. Cuts it to `MAX_ALIAS_LENGTH` characters.
+ */
+pub fn sanitize_alias(alias: &str) -> String {
+    let is_bidi = |c: char| matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}');
+    let cleaned: String = alias.chars().filter(|c| !c.is_control() && !is_bidi(*c)).collect();
+    let cleaned = cleaned.trim();
+    if cleaned.chars().count() <= MAX_ALIAS_LENGTH {
+        return cleaned.to_string();
+    };
+    let cut: String = cleaned.chars().take(MAX_ALIAS_LENGTH - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{config_directory::{follow_list::FollowList, follow::Follow}, public_key::PublicKey};
+    use super::{PetnameBook, KeyNames};
+
+    const ME: &str = "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso";
+    const DAVE: &str = "pk:1zpo3gfh6657dh8f5rq7z4rzyo3u1tob14r3hcaa6bc9498nbjiy";
+    const ALICE: &str = "pk:kgoxg9i5czhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy";
+    const BOB: &str = "pk:kgoxhhhhczhqor1h3b35exfq7hfkpgnycush4n9pab9w3s4a3rjy";
+
+    fn pk(value: &str) -> PublicKey {
+        PublicKey::parse(value).unwrap()
+    }
+
+    fn get_names() -> KeyNames {
+        let mut book = PetnameBook::default();
+        book.set("dave-laptop", &pk(DAVE)).unwrap();
+        let lists = vec![
+            FollowList::new_with_follows(pk(ME), "me", vec![
+                Follow::new(pk(ALICE), 1.0, None),
+                Follow::new(PublicKey::new_unchecked(BOB), 1.0, None),
+            ]),
+            FollowList::new_with_follows(pk(ALICE), "alice", vec![]),
+        ];
+        KeyNames::new(&pk(ME), book, &lists)
+    }
+
+    #[test]
+    fn set_petname() {
+        let mut book = PetnameBook::default();
+        book.set("dave", &pk(DAVE)).unwrap();
+        book.set("dave-laptop", &pk(DAVE)).unwrap();
+        assert_eq!(book.petnames.len(), 1);
+        assert_eq!(book.get_petname(DAVE).unwrap(), "dave-laptop");
+        assert!(book.set("dave laptop", &pk(DAVE)).is_err());
+        assert!(book.set("pk:dave", &pk(DAVE)).is_err());
+        assert!(book.set(&DAVE[3..], &pk(DAVE)).is_err());
+        assert_eq!(book.remove("dave-laptop").unwrap(), DAVE);
+
+        let recovered = PetnameBook::from_json(&book.to_json()).unwrap();
+        assert!(recovered.petnames.is_empty());
+    }
+
+    #[test]
+    fn label() {
+        let names = get_names();
+        assert_eq!(names.label(DAVE), format!("{} (dave-laptop)", DAVE));
+        assert_eq!(names.label(ALICE), format!("{} (~alice)", ALICE));
+        assert_eq!(names.label(ME), format!("{} (me)", ME));
+        assert_eq!(names.label(BOB), BOB);
+    }
+
+    #[test]
+    fn untrusted_aliases_are_sanitized() {
+        let me = PublicKey::parse(ME).unwrap();
+        let alice = PublicKey::parse(ALICE).unwrap();
+        let carol = PublicKey::from(&pkarr::Keypair::random().public_key());
+        let dave = PublicKey::parse(DAVE).unwrap();
+        let forged = FollowList::new(alice, "alice\nDecision: \x1b[32mtrusted\x1b[0m");
+        let long = FollowList::new(carol.clone(), &"c".repeat(100));
+        let only_control = FollowList::new(dave, "\n\x1b\r");
+        let names = KeyNames::new(&me, PetnameBook::default(), &[forged, long, only_control]);
+
+        let label = names.label(ALICE);
+        assert!(!label.contains('\n') && !label.contains('\x1b'));
+        assert_eq!(label, format!("{} (~aliceDecision: [32mtrusted[0m)", ALICE));
+        assert_eq!(names.label(&carol), format!("{} (~{}…)", carol, "c".repeat(31)));
+        assert_eq!(names.label(DAVE), DAVE);
+    }
+
+    #[test]
+    fn resolve_input() {
+        let names = get_names();
+        assert_eq!(names.resolve_input("dave-laptop").unwrap(), DAVE);
+        assert_eq!(names.resolve_input(DAVE).unwrap(), DAVE);
+        assert_eq!(names.resolve_input(&DAVE[3..]).unwrap(), DAVE);
+        assert_eq!(names.resolve_input("1zpo").unwrap(), DAVE);
+        assert_eq!(names.resolve_input("pk:kgoxg").unwrap(), ALICE);
+        assert!(names.resolve_input("kgox").is_err_and(|e| e.contains("ambiguous")));
+        assert!(names.resolve_input("1zp").is_err());
+        assert!(names.resolve_input("alice").is_err());
+        assert!(names.resolve_input("zzzz").is_err());
+    }
+
+    #[test]
+    fn identities() {
+        let mut names = get_names();
+        names.add_identity("org", &PublicKey::new_unchecked(BOB));
+        assert_eq!(names.label(BOB), format!("{} (identity org)", BOB));
+        assert_eq!(names.resolve_input("org").unwrap(), BOB);
+    }
+}
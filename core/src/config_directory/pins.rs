@@ -0,0 +1,187 @@
+use std::{fs, path::Path};
+use serde::{Deserialize, Serialize};
+
+use crate::time::unix_now;
+
+
+/**
+ * Domain that is always resolved to `pubkey`, without consulting the web of trust.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Pin {
+    pub domain: String,
+    pub pubkey: String,
+    /// Unix timestamp in seconds after which the pin is ignored. None: Never expires.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+}
+
+impl Pin {
+    pub fn new(domain: &str, pubkey: &str, expires: Option<u64>) -> Self {
+        Pin {
+            domain: normalize_domain(domain),
+            pubkey: pubkey.to_string(),
+            expires,
+        }
+    }
+
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        match self.expires {
+            None => false,
+            Some(expires) => expires <= now,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(unix_now())
+    }
+
+    /**
+     * Like `Display` but with `label` instead of the pubkey. See `KeyNames::label`.
+     */
+    pub fn format_with_label(&self, label: &str) -> String {
+        let mut text = format!("📌 {} -> {} pinned", self.domain, label);
+        if let Some(expires) = self.expires {
+            let now = unix_now();
+            if expires <= now {
+                text = format!("{} (expired)", text);
+            } else {
+                text = format!("{} (expires in {}s)", text, expires - now);
+            }
+        };
+        text
+    }
+}
+
+impl std::fmt::Display for Pin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format_with_label(&self.pubkey))
+    }
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain.trim_end_matches('.').to_lowercase()
+}
+
+
+/**
+ * All pins of a pknames directory. Stored in `<dir>/pins.json`.
+ */
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct PinStore {
+    #[serde(default)]
+    pub pins: Vec<Pin>,
+}
+
+impl PinStore {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let str_res = fs::read_to_string(path);
+        if let Err(e) = str_res {
+            return Err(format!("Failed to read pins \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        let pins = PinStore::from_json(&str_res.unwrap());
+        if let Err(e) = pins {
+            return Err(format!("Failed to parse pins \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        Ok(pins.unwrap())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /**
+     * Adds a pin. Replaces an existing pin of the same domain.
+     */
+    pub fn pin(&mut self, pin: Pin) {
+        self.unpin(&pin.domain);
+        self.pins.push(pin);
+    }
+
+    /**
+     * Removes the pin of the domain. Returns the removed pin.
+     */
+    pub fn unpin(&mut self, domain: &str) -> Option<Pin> {
+        let domain = normalize_domain(domain);
+        let index = self.pins.iter().position(|pin| pin.domain == domain)?;
+        Some(self.pins.remove(index))
+    }
+
+    /**
+     * Pin of exactly this domain if it is not expired.
+     */
+    pub fn get(&self, domain: &str) -> Option<&Pin> {
+        let domain = normalize_domain(domain);
+        let now = unix_now();
+        self.pins.iter().find(|pin| pin.domain == domain && !pin.is_expired_at(now))
+    }
+
+    /**
+     * Finds the pin of the longest suffix of `name`.
+     * Returns the pin and the remaining subdomain, for example `www.example.com` -> (`example.com` pin, `www`).
+     */
+    pub fn find(&self, name: &str) -> Option<(&Pin, String)> {
+        let name = normalize_domain(name);
+        let labels: Vec<&str> = name.split('.').collect();
+        for i in 0..labels.len() {
+            let domain = labels[i..].join(".");
+            if let Some(pin) = self.get(&domain) {
+                let subdomain = labels[..i].join(".");
+                return Some((pin, subdomain));
+            }
+        }
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::time::unix_now;
+    use super::{Pin, PinStore};
+
+    #[test]
+    fn to_json_and_back() {
+        let mut store = PinStore::default();
+        store.pin(Pin::new("example.com", "pk:abc", None));
+        store.pin(Pin::new("example.org", "pk:def", Some(1000)));
+        let recovered = PinStore::from_json(&store.to_json()).unwrap();
+        assert_eq!(recovered.pins, store.pins);
+    }
+
+    #[test]
+    fn replace_and_unpin() {
+        let mut store = PinStore::default();
+        store.pin(Pin::new("Example.com.", "pk:abc", None));
+        store.pin(Pin::new("example.com", "pk:def", None));
+        assert_eq!(store.pins.len(), 1);
+        assert_eq!(store.get("example.com").unwrap().pubkey, "pk:def");
+        assert!(store.unpin("example.com").is_some());
+        assert!(store.get("example.com").is_none());
+        assert!(store.unpin("example.com").is_none());
+    }
+
+    #[test]
+    fn ignore_expired() {
+        let mut store = PinStore::default();
+        store.pin(Pin::new("example.com", "pk:abc", Some(unix_now() - 1)));
+        store.pin(Pin::new("example.org", "pk:def", Some(unix_now() + 60)));
+        assert!(store.get("example.com").is_none());
+        assert!(store.get("example.org").is_some());
+    }
+
+    #[test]
+    fn find_parent_domain() {
+        let mut store = PinStore::default();
+        store.pin(Pin::new("example.com", "pk:abc", None));
+        let (pin, subdomain) = store.find("api.www.example.com").unwrap();
+        assert_eq!(pin.pubkey, "pk:abc");
+        assert_eq!(subdomain, "api.www");
+        assert!(store.find("example.org").is_none());
+    }
+}
@@ -0,0 +1,141 @@
+use std::{fmt, fs, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+
+use super::follow_list::FollowList;
+
+
+/**
+ * Where a list comes from. Decides in which folder of `static_lists` the list is stored.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ListSource {
+    /// Written by one of the local identities. `static_lists/<z32>.json`.
+    Authored,
+    /// Added by hand, for example with `import-list` or by copying a file. `static_lists/imported/<z32>.json`.
+    Imported,
+    /// Downloaded and cached. `static_lists/fetched/<z32>.json`.
+    Fetched,
+}
+
+impl fmt::Display for ListSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListSource::Authored => write!(f, "authored"),
+            ListSource::Imported => write!(f, "imported"),
+            ListSource::Fetched => write!(f, "fetched"),
+        }
+    }
+}
+
+/**
+ * Whether the list was signed by its public key. Lists carry no signature, so every list is unsigned.
+ * Never read from `<z32>.meta`, anybody who can write a list can write its metadata too.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureStatus {
+    #[default]
+    Unsigned,
+}
+
+impl fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureStatus::Unsigned => write!(f, "unsigned"),
+        }
+    }
+}
+
+/**
+ * Metadata of a stored list. Imported and fetched lists keep it next to the list in `<z32>.meta`.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Provenance {
+    pub source: ListSource,
+    /// File path or url the list was taken from.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Unix timestamp in seconds of the import or fetch.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetched: Option<u64>,
+    #[serde(skip_deserializing)]
+    pub signature: SignatureStatus,
+}
+
+impl Provenance {
+    pub fn new(source: ListSource) -> Self {
+        Provenance { source, origin: None, fetched: None, signature: SignatureStatus::Unsigned }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read list metadata \"{}\". {}", path.to_str().unwrap(), e))?;
+        Provenance::from_json(&content).map_err(|e| format!("Failed to parse list metadata \"{}\". {}", path.to_str().unwrap(), e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if let Some(origin) = &self.origin {
+            write!(f, " from {}", origin)?;
+        };
+        if self.source != ListSource::Authored {
+            write!(f, ", {}", self.signature)?;
+        };
+        Ok(())
+    }
+}
+
+/**
+ * Metadata file of a list, `<z32>.meta` next to `<z32>.json`.
+ */
+pub fn metadata_path(list_path: &Path) -> PathBuf {
+    list_path.with_extension("meta")
+}
+
+/**
+ * A list read from the disk together with where it comes from.
+ */
+pub struct StoredList {
+    pub list: FollowList,
+    pub provenance: Provenance,
+    pub path: PathBuf,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{ListSource, Provenance, SignatureStatus};
+
+    #[test]
+    fn provenance_json() {
+        let provenance = Provenance {
+            source: ListSource::Fetched,
+            origin: Some("https://example.com/list.json".to_string()),
+            fetched: Some(1700000000),
+            signature: SignatureStatus::Unsigned,
+        };
+        assert_eq!(Provenance::from_json(&provenance.to_json()).unwrap(), provenance);
+        assert!(provenance.to_json().contains("\"fetched\""));
+
+        let minimal = Provenance::from_json(r#"{"source": "imported"}"#).unwrap();
+        assert_eq!(minimal, Provenance::new(ListSource::Imported));
+        assert_eq!(minimal.to_string(), "imported, unsigned");
+
+        // The metadata can't claim a signature.
+        let claimed = Provenance::from_json(r#"{"source": "fetched", "signature": "verified"}"#).unwrap();
+        assert_eq!(claimed.signature, SignatureStatus::Unsigned);
+        assert!(Provenance::new(ListSource::Fetched).to_json().contains("\"signature\": \"unsigned\""));
+    }
+}
@@ -0,0 +1,255 @@
+use std::{fs, path::Path};
+use argon2::{Argon2, Algorithm, Params, Version};
+use chacha20poly1305::{aead::{Aead, KeyInit}, XChaCha20Poly1305, XNonce};
+use pkarr::Keypair;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::public_key::PublicKey;
+use super::storage::write_atomic_private;
+
+
+pub const SECRET_KEY_LENGTH: usize = 32;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+
+/**
+ * Env variable the passphrase of encrypted secrets is read from.
+ */
+pub const PASSPHRASE_ENV: &str = "PKNAMES_PASSPHRASE";
+
+
+/**
+ * Secret key encrypted with a key derived from a passphrase with argon2id and XChaCha20-Poly1305.
+ * The public key is stored in plain text so it can be read without the passphrase.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EncryptedSecret {
+    pub public_key: PublicKey,
+    pub kdf: KdfParams,
+    /// z-base32
+    pub salt: String,
+    /// z-base32
+    pub nonce: String,
+    /// z-base32
+    pub ciphertext: String,
+}
+
+/**
+ * Argon2id parameters. Stored with the secret so they can be raised later without breaking existing files.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    fn derive_key(&self, passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32)).map_err(|e| e.to_string())?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let mut key = [0u8; 32];
+        argon2.hash_password_into(passphrase.as_bytes(), salt, &mut key).map_err(|e| e.to_string())?;
+        Ok(key)
+    }
+}
+
+impl EncryptedSecret {
+    pub fn encrypt(keypair: &Keypair, passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; SALT_LENGTH];
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let kdf = KdfParams::default();
+        let key = kdf.derive_key(passphrase, &salt)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let ciphertext = cipher.encrypt(XNonce::from_slice(&nonce), keypair.secret_key().as_slice()).map_err(|e| e.to_string())?;
+
+        Ok(EncryptedSecret {
+            public_key: PublicKey::from(&keypair.public_key()),
+            kdf,
+            salt: zbase32::encode_full_bytes(&salt),
+            nonce: zbase32::encode_full_bytes(&nonce),
+            ciphertext: zbase32::encode_full_bytes(&ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair, String> {
+        let salt = zbase32::decode_full_bytes_str(&self.salt).map_err(|e| e.to_string())?;
+        let nonce = zbase32::decode_full_bytes_str(&self.nonce).map_err(|e| e.to_string())?;
+        let ciphertext = zbase32::decode_full_bytes_str(&self.ciphertext).map_err(|e| e.to_string())?;
+        if nonce.len() != NONCE_LENGTH {
+            return Err(format!("Nonce not {} bytes long.", NONCE_LENGTH));
+        };
+
+        let key = self.kdf.derive_key(passphrase, &salt)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let plain_secret = cipher.decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice()).map_err(|_| "Wrong passphrase.".to_string())?;
+        let keypair = keypair_from_bytes(&plain_secret)?;
+        if PublicKey::from(&keypair.public_key()) != self.public_key {
+            return Err("Decrypted secret does not match the stored public key.".to_string());
+        };
+        Ok(keypair)
+    }
+}
+
+
+/**
+ * Content of a secret file. Plain secrets are the legacy z-base32 format, encrypted secrets are json.
+ */
+#[derive(Debug)]
+pub enum SecretFile {
+    Plain(Keypair),
+    Encrypted(EncryptedSecret),
+}
+
+impl SecretFile {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let read_result = fs::read_to_string(path);
+        if let Err(e) = read_result {
+            return Err(format!("Failed to read secret \"{}\". {}", path.to_str().unwrap(), e));
+        };
+        SecretFile::parse(&read_result.unwrap())
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let content = content.trim();
+        if content.starts_with('{') {
+            let encrypted: EncryptedSecret = serde_json::from_str(content).map_err(|e| e.to_string())?;
+            return Ok(SecretFile::Encrypted(encrypted));
+        };
+        let decode_result = zbase32::decode_full_bytes_str(content);
+        if let Err(e) = decode_result {
+            return Err(e.to_string());
+        };
+        Ok(SecretFile::Plain(keypair_from_bytes(&decode_result.unwrap())?))
+    }
+
+    pub fn to_file_content(&self) -> String {
+        match self {
+            SecretFile::Plain(keypair) => zbase32::encode_full_bytes(&keypair.secret_key()),
+            SecretFile::Encrypted(encrypted) => serde_json::to_string_pretty(encrypted).unwrap(),
+        }
+    }
+
+    /**
+     * Writes the secret readable by the owner only.
+     */
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        write_atomic_private(path, self.to_file_content().as_bytes()).map_err(|e| format!("Failed to write secret \"{}\". {}", path.to_str().unwrap(), e))
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self, SecretFile::Encrypted(_))
+    }
+
+    /**
+     * Public key without decrypting anything.
+     */
+    pub fn public_key(&self) -> PublicKey {
+        match self {
+            SecretFile::Plain(keypair) => PublicKey::from(&keypair.public_key()),
+            SecretFile::Encrypted(encrypted) => encrypted.public_key.clone(),
+        }
+    }
+
+    /**
+     * Keypair of the secret. Encrypted secrets need the passphrase.
+     */
+    pub fn unlock(&self, passphrase: Option<&str>) -> Result<Keypair, String> {
+        match self {
+            SecretFile::Plain(keypair) => Ok(Keypair::from_secret_key(&keypair.secret_key())),
+            SecretFile::Encrypted(encrypted) => match passphrase {
+                Some(passphrase) => encrypted.decrypt(passphrase),
+                None => Err(format!("The secret key is encrypted. Enter the passphrase or set {}.", PASSPHRASE_ENV)),
+            },
+        }
+    }
+}
+
+fn keypair_from_bytes(plain_secret: &[u8]) -> Result<Keypair, String> {
+    if plain_secret.len() != SECRET_KEY_LENGTH {
+        return Err(format!("Secret not {} bytes long.", SECRET_KEY_LENGTH));
+    }
+    let slice: &[u8; SECRET_KEY_LENGTH] = &plain_secret[0..SECRET_KEY_LENGTH].try_into().unwrap();
+    Ok(Keypair::from_secret_key(slice))
+}
+
+/**
+ * Refuses secrets that other users can read, like ssh does.
+ */
+#[cfg(unix)]
+pub fn check_permissions(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read secret \"{}\". {}", path.to_str().unwrap(), e))?;
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Err(format!("Secret \"{}\" is accessible by other users (mode {:o}). Run `chmod 600 {}`.", path.to_str().unwrap(), mode, path.to_str().unwrap()));
+    };
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn check_permissions(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use pkarr::Keypair;
+    use super::{EncryptedSecret, SecretFile, check_permissions};
+
+    #[test]
+    fn encrypt_and_decrypt() {
+        let keypair = Keypair::random();
+        let encrypted = EncryptedSecret::encrypt(&keypair, "correct horse").unwrap();
+        assert_eq!(encrypted.public_key, keypair.to_uri_string());
+        assert!(encrypted.decrypt("wrong horse").is_err());
+        assert_eq!(encrypted.decrypt("correct horse").unwrap().secret_key(), keypair.secret_key());
+    }
+
+    #[test]
+    fn parse_plain_and_encrypted() {
+        let keypair = Keypair::random();
+        let plain = SecretFile::Plain(Keypair::from_secret_key(&keypair.secret_key()));
+        let parsed = SecretFile::parse(&plain.to_file_content()).unwrap();
+        assert!(!parsed.is_encrypted());
+        assert_eq!(parsed.unlock(None).unwrap().secret_key(), keypair.secret_key());
+
+        let encrypted = SecretFile::Encrypted(EncryptedSecret::encrypt(&keypair, "pass").unwrap());
+        let parsed = SecretFile::parse(&encrypted.to_file_content()).unwrap();
+        assert!(parsed.is_encrypted());
+        assert_eq!(parsed.public_key(), keypair.to_uri_string());
+        assert!(parsed.unlock(None).is_err());
+        assert_eq!(parsed.unlock(Some("pass")).unwrap().secret_key(), keypair.secret_key());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn owner_only_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = PathBuf::from("/tmp/pknames_owner_only_permissions.secret");
+        let _ = std::fs::remove_file(&path); // Delete so the test can work again even though it failed before.
+        SecretFile::Plain(Keypair::random()).write(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(check_permissions(&path).is_ok());
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(check_permissions(&path).is_err_and(|e| e.contains("chmod 600")));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
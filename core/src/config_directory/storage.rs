@@ -0,0 +1,152 @@
+use std::{fs::{self, File, OpenOptions}, io::Write, path::{Path, PathBuf}};
+
+
+/**
+ * Writes to a temporary file next to `path` and renames it over `path`.
+ * Readers see either the old or the new content, never a half written file.
+ */
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    let temp_path = temp_path(path);
+    let result = write_and_sync(&mut OpenOptions::new().write(true).create(true).truncate(true).open(&temp_path)?, content);
+    finish(path, &temp_path, result)
+}
+
+/**
+ * Like `write_atomic` but the file is only readable and writable by the owner.
+ */
+#[cfg(unix)]
+pub fn write_atomic_private(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let temp_path = temp_path(path);
+    let mut file = OpenOptions::new().write(true).create_new(true).mode(0o600).open(&temp_path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    let result = write_and_sync(&mut file, content);
+    finish(path, &temp_path, result)
+}
+
+#[cfg(not(unix))]
+pub fn write_atomic_private(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    write_atomic(path, content)
+}
+
+/**
+ * Unique per write, so concurrent writers of the same file don't truncate or rename each others temporary file.
+ */
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.{:08x}.tmp", name, std::process::id(), rand::random::<u32>()))
+}
+
+fn write_and_sync(file: &mut File, content: &[u8]) -> Result<(), std::io::Error> {
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+fn finish(path: &Path, temp_path: &Path, result: Result<(), std::io::Error>) -> Result<(), std::io::Error> {
+    if let Err(e) = result {
+        let _ = fs::remove_file(temp_path);
+        return Err(e);
+    };
+    if let Err(e) = fs::rename(temp_path, path) {
+        let _ = fs::remove_file(temp_path);
+        return Err(e);
+    };
+    // Persist the rename. Not supported everywhere, so errors are ignored.
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        };
+    };
+    Ok(())
+}
+
+
+/**
+ * Exclusive advisory lock on a pknames directory. Held for read-modify-write operations so concurrent
+ * processes, for example two `pknamescli add`, don't lose each others updates. Released on drop.
+ */
+pub struct DirectoryLock {
+    _file: File,
+}
+
+impl DirectoryLock {
+    /**
+     * Blocks until the lock on `<dir>/.lock` is acquired.
+     */
+    pub fn acquire(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(".lock");
+        let file = OpenOptions::new().write(true).create(true).truncate(false).open(&path)
+            .map_err(|e| format!("Failed to open lock file \"{}\". {}", path.to_str().unwrap(), e))?;
+        file.lock().map_err(|e| format!("Failed to lock \"{}\". {}", path.to_str().unwrap(), e))?;
+        Ok(DirectoryLock { _file: file })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::{write_atomic, write_atomic_private, DirectoryLock};
+
+    #[test]
+    fn atomic_write_replaces_content() {
+        let dir = PathBuf::from("/tmp/pknames_atomic_write_replaces_content");
+        let _ = std::fs::remove_dir_all(&dir); // Delete so the test can work again even though it failed before.
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("list.json");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1); // No temporary file left.
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_atomic_writes() {
+        let dir = PathBuf::from("/tmp/pknames_concurrent_atomic_writes");
+        let _ = std::fs::remove_dir_all(&dir); // Delete so the test can work again even though it failed before.
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("packet");
+        let threads: Vec<_> = (0..8).map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    write_atomic(&path, format!("writer {}", i).as_bytes()).unwrap();
+                };
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        };
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("writer "));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1); // No temporary file left.
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn atomic_private_write() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = PathBuf::from("/tmp/pknames_atomic_private_write");
+        let _ = std::fs::remove_dir_all(&dir); // Delete so the test can work again even though it failed before.
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("secret");
+        std::fs::write(&path, "old").unwrap();
+        write_atomic_private(&path, b"new").unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = PathBuf::from("/tmp/pknames_lock_is_exclusive");
+        let _ = std::fs::remove_dir_all(&dir); // Delete so the test can work again even though it failed before.
+        std::fs::create_dir(&dir).unwrap();
+        let lock = DirectoryLock::acquire(&dir).unwrap();
+        let file = std::fs::File::open(dir.join(".lock")).unwrap();
+        assert!(file.try_lock().is_err());
+        drop(lock);
+        assert!(file.try_lock().is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
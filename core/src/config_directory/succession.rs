@@ -0,0 +1,169 @@
+use ed25519_dalek::Signature;
+use pkarr::{dns::{rdata::{RData, TXT}, Name, Packet, ResourceRecord, CLASS}, Keypair, SignedPacket};
+use serde::{Deserialize, Serialize};
+
+use crate::{public_key::PublicKey, transport::pkarr_transport::PkarrTransport};
+
+
+/**
+ * Name of the TXT record in the signed packet of the retired key that carries the succession as json.
+ */
+pub const SUCCESSION_RECORD: &str = "_pknames-succession";
+
+/**
+ * Time to live of the succession record in seconds.
+ */
+const SUCCESSION_TTL: u32 = 86400;
+
+
+/**
+ * Statement of a retired key that it moved to a successor key. Signed by the retired key.
+ * Stored in the list of the retired key. Graph building treats a verified succession as a full follow of the successor.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Succession {
+    pub successor: PublicKey,
+    /// Unix timestamp in seconds.
+    pub created: u64,
+    /// z-base32 ed25519 signature of the retired key over `Succession::message`.
+    pub signature: String,
+}
+
+impl Succession {
+    /**
+     * Signs that `retired` moved to `successor`.
+     */
+    pub fn sign(retired: &Keypair, successor: &PublicKey, created: u64) -> Self {
+        let message = Succession::message(&PublicKey::from(&retired.public_key()), successor, created);
+        let signature = retired.sign(&message);
+        Succession {
+            successor: successor.clone(),
+            created,
+            signature: zbase32::encode_full_bytes(&signature.to_bytes()),
+        }
+    }
+
+    /**
+     * Signed message. Contains the retired key so a statement can't be copied into another list.
+     */
+    fn message(retired: &PublicKey, successor: &PublicKey, created: u64) -> Vec<u8> {
+        format!("pknames succession {} {} {}", retired, successor, created).into_bytes()
+    }
+
+    /**
+     * Checks that `retired` signed this statement.
+     */
+    pub fn verify(&self, retired: &PublicKey) -> Result<(), String> {
+        if &self.successor == retired {
+            return Err("A key can't succeed itself.".to_string());
+        };
+        let retired_key = retired.to_pkarr()?;
+        let bytes = zbase32::decode_full_bytes_str(&self.signature).map_err(|e| e.to_string())?;
+        let bytes: [u8; 64] = bytes.try_into().map_err(|_| "Signature not 64 bytes long.".to_string())?;
+        let signature = Signature::from_bytes(&bytes);
+        let message = Succession::message(retired, &self.successor, self.created);
+        retired_key.verify(&message, &signature).map_err(|_| format!("Invalid succession signature of {}.", retired))
+    }
+
+    /**
+     * Signed packet of the retired key with the succession as `SUCCESSION_RECORD`.
+     * Keeps all other records of `current`, the packet the retired key published before.
+     */
+    pub fn to_packet(&self, retired: &Keypair, current: Option<&SignedPacket>) -> Result<SignedPacket, String> {
+        let json = serde_json::to_string(self).unwrap();
+        let mut packet = Packet::new_reply(0);
+        if let Some(current) = current {
+            let previous: Vec<&ResourceRecord> = current.resource_records(SUCCESSION_RECORD).collect();
+            for answer in current.packet().answers.iter() {
+                if !previous.contains(&answer) {
+                    packet.answers.push(answer.clone());
+                };
+            };
+        };
+        let txt = TXT::try_from(json.as_str()).map_err(|e| e.to_string())?;
+        let name = Name::new(SUCCESSION_RECORD).map_err(|e| e.to_string())?;
+        packet.answers.push(ResourceRecord::new(name, CLASS::IN, SUCCESSION_TTL, RData::TXT(txt)));
+        SignedPacket::from_packet(retired, &packet).map_err(|e| format!("Failed to sign the succession packet. {}", e))
+    }
+
+    /**
+     * Succession published in a signed packet. None if the packet has no `SUCCESSION_RECORD`.
+     * Fails if the record can't be parsed or isn't signed by the key of the packet.
+     */
+    pub fn from_packet(packet: &SignedPacket) -> Option<Result<Self, String>> {
+        let record = packet.resource_records(SUCCESSION_RECORD).next()?;
+        let retired = PublicKey::from(packet.public_key());
+        let result = match &record.rdata {
+            RData::TXT(txt) => String::try_from(txt.clone()).map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str::<Succession>(&json).map_err(|e| format!("Failed to parse the succession of {}. {}", retired, e)))
+                .and_then(|succession| succession.verify(&retired).map(|_| succession)),
+            _ => Err(format!("The succession record of {} is not a TXT record.", retired)),
+        };
+        Some(result)
+    }
+}
+
+/**
+ * Publishes the succession in the signed packet of the retired key so everybody who resolves the old key sees it.
+ * Resolves the current packet first to keep the records the retired key published before.
+ */
+pub fn publish_succession(transport: &dyn PkarrTransport, retired: &Keypair, succession: &Succession) -> Result<(), String> {
+    let current = transport.resolve_most_recent(&retired.public_key())?;
+    let packet = succession.to_packet(retired, current.as_ref())?;
+    transport.publish(&packet)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use pkarr::{dns::{rdata::{RData, A}, Name, Packet, ResourceRecord, CLASS}, Keypair, SignedPacket};
+    use crate::{public_key::PublicKey, transport::{memory_transport::MemoryTransport, pkarr_transport::PkarrTransport}};
+    use super::{publish_succession, Succession};
+
+    #[test]
+    fn sign_and_verify() {
+        let retired = Keypair::random();
+        let retired_pubkey = PublicKey::from(&retired.public_key());
+        let successor = PublicKey::from(&Keypair::random().public_key());
+        let succession = Succession::sign(&retired, &successor, 1700000000);
+        assert!(succession.verify(&retired_pubkey).is_ok());
+
+        // Copied into another list.
+        let other = PublicKey::from(&Keypair::random().public_key());
+        assert!(succession.verify(&other).is_err());
+
+        // Tampered successor or timestamp.
+        let tampered = Succession { successor: other, ..succession.clone() };
+        assert!(tampered.verify(&retired_pubkey).is_err());
+        let tampered = Succession { created: 1800000000, ..succession };
+        assert!(tampered.verify(&retired_pubkey).is_err());
+    }
+
+    #[test]
+    fn publish_and_resolve() {
+        let transport = MemoryTransport::new();
+        let retired = Keypair::random();
+
+        // The retired key published an A record before.
+        let mut packet = Packet::new_reply(0);
+        packet.answers.push(ResourceRecord::new(Name::new("@").unwrap(), CLASS::IN, 300, RData::A(A { address: 0x7f000001 })));
+        transport.publish(&SignedPacket::from_packet(&retired, &packet).unwrap()).unwrap();
+
+        let first = Succession::sign(&retired, &PublicKey::from(&Keypair::random().public_key()), 1700000000);
+        publish_succession(&transport, &retired, &first).unwrap();
+        let successor = PublicKey::from(&Keypair::random().public_key());
+        let second = Succession::sign(&retired, &successor, 1700000001);
+        publish_succession(&transport, &retired, &second).unwrap();
+
+        let resolved = transport.resolve_most_recent(&retired.public_key()).unwrap().unwrap();
+        assert_eq!(Succession::from_packet(&resolved), Some(Ok(second)));
+        assert_eq!(resolved.resource_records("@").count(), 1);
+        assert_eq!(resolved.packet().answers.len(), 2);
+
+        // Signed by another key.
+        let other = Keypair::random();
+        let forged = Succession::sign(&other, &successor, 1700000002).to_packet(&retired, None).unwrap();
+        assert!(matches!(Succession::from_packet(&forged), Some(Err(_))));
+        assert_eq!(Succession::from_packet(&SignedPacket::from_packet(&retired, &packet).unwrap()), None);
+    }
+}
@@ -1,4 +1,8 @@
 pub mod prediction;
 pub mod pruning;
 pub mod config_directory;
-pub mod resolve;
\ No newline at end of file
+pub mod resolve;
+pub mod transport;
+pub mod public_key;
+pub mod time;
+pub mod simulation;
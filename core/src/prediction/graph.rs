@@ -1,9 +1,13 @@
+use crate::public_key::PublicKey;
+
 use super::{node::{WotNode, WotFollow}, predictor::WotPredictor};
-use std::{collections::HashSet, fmt};
+use std::{collections::{HashMap, HashSet}, fmt};
 
 #[derive(Debug, Clone)]
 pub struct WotGraph {
-    pub nodes: Vec<WotNode>
+    pub nodes: Vec<WotNode>,
+    /// Blocked pubkeys by list pubkey. Only the blocks of the me list are applied, see `prune_blocked`.
+    pub blocks: HashMap<PublicKey, HashSet<PublicKey>>
 }
 
 impl fmt::Display for WotGraph {
@@ -25,7 +29,14 @@ impl fmt::Display for WotGraph {
 impl WotGraph {
     pub fn new(mut nodes: Vec<WotNode>) -> WotGraph {
         nodes.sort_unstable_by_key(|node| node.pubkey.clone());
-        WotGraph { nodes }
+        WotGraph { nodes, blocks: HashMap::new() }
+    }
+
+    /**
+     * Pubkeys blocked by the list `list_pubkey`.
+     */
+    pub fn get_blocks(&self, list_pubkey: &str) -> HashSet<PublicKey> {
+        self.blocks.get(list_pubkey).cloned().unwrap_or_default()
     }
 
     pub fn validate(&self) -> Result<(), &str> {
@@ -65,7 +76,7 @@ impl WotGraph {
      */
     pub fn is_unique(&self) -> bool {
         let pubkeys = self.nodes.iter().map(|node| node.pubkey.clone());
-        let set: HashSet<String> = HashSet::from_iter(pubkeys);
+        let set: HashSet<PublicKey> = HashSet::from_iter(pubkeys);
         set.len() == self.nodes.len()
     }
 
@@ -163,19 +174,14 @@ impl WotGraph {
     }
 
     pub fn get_classes(&self) -> Vec<&WotNode> {
-        let classes_pubkeys: HashSet<String> = self.nodes.iter().map(|node| {
-            let class_pubkeys: Vec<String> = node.follows.iter().filter(|follow| {
+        let classes_pubkeys: HashSet<&PublicKey> = self.nodes.iter().flat_map(|node| {
+            node.follows.iter().filter(|follow| {
                 follow.attribution.is_some()
-            }).map(|follow| {
-                follow.target_pubkey.clone()
-            }).collect();
-            class_pubkeys
-        }).flatten().collect();
-
-        let nodes: Vec<&WotNode> = classes_pubkeys.into_iter().filter_map(|pubkey| {
-            self.get_node(pubkey.as_str())
+            }).map(|follow| &follow.target_pubkey)
         }).collect();
-        nodes
+
+        // Keep the sorted order of the nodes so the result is deterministic.
+        self.nodes.iter().filter(|node| classes_pubkeys.contains(&node.pubkey)).collect()
     }
 
     pub fn get_follow_nodes(&self) -> Vec<&WotNode> {
@@ -242,6 +248,22 @@ impl WotGraph {
     pub fn depth(&self) -> usize {
         self.get_layers().len()
     }
+
+    /**
+     * Scales the list follows (follows without attribution) of every node so their absolute weights sum up to at most 1.
+     * A list then has the same total influence no matter how many lists it follows. Attributions are not changed.
+     */
+    pub fn normalize_follow_weights(&mut self) {
+        for node in self.nodes.iter_mut() {
+            let total: f32 = node.follows.iter().filter(|follow| follow.attribution.is_none()).map(|follow| follow.weight.abs()).sum();
+            if total <= 1.0 {
+                continue;
+            };
+            for follow in node.follows.iter_mut().filter(|follow| follow.attribution.is_none()) {
+                follow.weight /= total;
+            };
+        };
+    }
 }
 
 impl From<WotPredictor> for WotGraph {
@@ -254,6 +276,7 @@ impl From<WotPredictor> for WotGraph {
 
 #[cfg(test)]
 mod tests {
+    use crate::public_key::PublicKey;
     use super::super::node::{WotNode, WotFollow};
     use super::WotGraph;
 
@@ -264,20 +287,20 @@ mod tests {
         let mut nodes: Vec<WotNode> = Vec::new();
 
         // Classes
-        nodes.push(WotNode::new_class("d1", ""));
-        nodes.push(WotNode::new_class("d2", ""));
+        nodes.push(WotNode::new_class_unchecked("d1", ""));
+        nodes.push(WotNode::new_class_unchecked("d2", ""));
 
-        nodes.push(WotNode::new_list("n2", "", vec![
-            WotFollow::new("n2", "d1", 1.0, Some("example.com")),
-            WotFollow::new("n2", "d2", -1.0, Some("example.com")),
+        nodes.push(WotNode::new_list_unchecked("n2", "", vec![
+            WotFollow::new_unchecked("n2", "d1", 1.0, Some("example.com")),
+            WotFollow::new_unchecked("n2", "d2", -1.0, Some("example.com")),
         ]));
-        nodes.push(WotNode::new_list("n1", "", vec![
-            WotFollow::new("n1", "d1", -0.5, Some("example.com")),
-            WotFollow::new("n1", "d2", 0.0, Some("example.com"))
+        nodes.push(WotNode::new_list_unchecked("n1", "", vec![
+            WotFollow::new_unchecked("n1", "d1", -0.5, Some("example.com")),
+            WotFollow::new_unchecked("n1", "d2", 0.0, Some("example.com"))
         ]));
-        nodes.push(WotNode::new_list("me", "", vec![
-            WotFollow::new("me", "n1", 1.0, None),
-            WotFollow::new("me", "n2", 0.5, None)
+        nodes.push(WotNode::new_list_unchecked("me", "", vec![
+            WotFollow::new_unchecked("me", "n1", 1.0, None),
+            WotFollow::new_unchecked("me", "n2", 0.5, None)
         ]));
 
         WotGraph::new(nodes)
@@ -297,12 +320,12 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com1"),
             follows: vec![],
         });
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com2"),
             follows: vec![],
         });
@@ -325,8 +348,8 @@ mod tests {
         let graph = get_simple_graph();
         let classes = graph.get_classes();
         assert_eq!(classes.len(), 2);
-        assert_eq!(classes[0].pubkey, "d2");
-        assert_eq!(classes[1].pubkey, "d1");
+        assert_eq!(classes[0].pubkey, "d1");
+        assert_eq!(classes[1].pubkey, "d2");
     }
 
     #[test]
@@ -380,4 +403,23 @@ mod tests {
         let graph = get_simple_graph();
         println!("{}", graph)
     }
+
+    #[test]
+    fn normalize_follow_weights() {
+        let mut graph = WotGraph::new(vec![
+            WotNode::new_list_unchecked("me", "", vec![
+                WotFollow::new_unchecked("me", "l1", 1.0, None),
+                WotFollow::new_unchecked("me", "l2", -1.0, None),
+                WotFollow::new_unchecked("me", "l3", 0.5, Some("example.com")),
+            ]),
+            WotNode::new_list_unchecked("l1", "", vec![WotFollow::new_unchecked("l1", "l2", 0.5, None)]),
+            WotNode::new_list_unchecked("l2", "", vec![]),
+            WotNode::new_class_unchecked("l3", ""),
+        ]);
+        graph.normalize_follow_weights();
+        assert_eq!(graph.get_follow("me", "l1").unwrap().weight, 0.5);
+        assert_eq!(graph.get_follow("me", "l2").unwrap().weight, -0.5);
+        assert_eq!(graph.get_follow("me", "l3").unwrap().weight, 0.5);
+        assert_eq!(graph.get_follow("l1", "l2").unwrap().weight, 0.5);
+    }
 }
@@ -1,20 +1,22 @@
 use std::fmt;
 use std::hash::Hash;
 
+use crate::{config_directory::petnames::sanitize_alias, public_key::PublicKey};
+
 
 
 #[derive(Debug, Clone)]
 pub struct WotNode {
-    pub pubkey: String,
+    pub pubkey: PublicKey,
     pub alias: String,
     pub follows: Vec<WotFollow>
 }
 
 impl fmt::Display for WotNode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut name = self.pubkey.clone();
+        let mut name = self.pubkey.to_string();
         if self.alias.len() > 0 {
-            name = format!("{} ({})", name, self.alias);
+            name = format!("{} ({})", name, sanitize_alias(&self.alias));
         }
         write!(f, "{}", name)
     }
@@ -23,22 +25,36 @@ impl fmt::Display for WotNode {
 
 impl WotNode {
 
-    pub fn new_class(pubkey: &str, alias: &str) -> WotNode {
+    pub fn new_class(pubkey: PublicKey, alias: &str) -> WotNode {
         WotNode {
-            pubkey: pubkey.to_string(),
+            pubkey,
             alias: alias.to_string(),
             follows: vec![]
         }
     }
 
-    pub fn new_list(pubkey: &str, alias: &str, follows: Vec<WotFollow>) -> WotNode {
+    pub fn new_list(pubkey: PublicKey, alias: &str, follows: Vec<WotFollow>) -> WotNode {
         WotNode {
-            pubkey: pubkey.to_string(),
+            pubkey,
             alias: alias.to_string(),
             follows: follows 
         }
     }
 
+    /**
+     * Like `new_class` for synthetic graphs. Pubkeys are not validated. See `PublicKey::new_unchecked`.
+     */
+    pub fn new_class_unchecked(pubkey: &str, alias: &str) -> WotNode {
+        WotNode::new_class(PublicKey::new_unchecked(pubkey), alias)
+    }
+
+    /**
+     * Like `new_list` for synthetic graphs. Pubkeys are not validated. See `PublicKey::new_unchecked`.
+     */
+    pub fn new_list_unchecked(pubkey: &str, alias: &str, follows: Vec<WotFollow>) -> WotNode {
+        WotNode::new_list(PublicKey::new_unchecked(pubkey), alias, follows)
+    }
+
     /**
      * Extend follows
      */
@@ -63,7 +79,7 @@ impl WotNode {
      * Finds a WotNode in a Vec<&WotNode>
      */
     pub fn binary_search_ref<'a>(pubkey: &str, list: &'a Vec<&WotNode>) -> Option<&'a WotNode> {
-        let result = list.binary_search_by_key(&pubkey, |node| &node.pubkey);
+        let result = list.binary_search_by_key(&pubkey, |node| node.pubkey.as_str());
         if let Ok(index) = result  {
             let node = &list[index];
             Some(node)
@@ -76,7 +92,7 @@ impl WotNode {
      * Finds a WotNode in a Vec<WotNode>
      */
     pub fn binary_search<'a>(pubkey: &str, list: &'a Vec<WotNode>) -> Option<&'a WotNode> {
-        let result = list.binary_search_by_key(&pubkey, |node| &node.pubkey);
+        let result = list.binary_search_by_key(&pubkey, |node| node.pubkey.as_str());
         if let Ok(index) = result  {
             let node = &list[index];
             Some(node)
@@ -89,7 +105,7 @@ impl WotNode {
      * Finds a WotNode in a Vec<WotNode>
      */
     pub fn binary_search_mut<'a>(pubkey: &str, list: &'a mut Vec<WotNode>) -> Option<&'a mut WotNode> {
-        let result = list.binary_search_by_key(&pubkey, |node| &node.pubkey);
+        let result = list.binary_search_by_key(&pubkey, |node| node.pubkey.as_str());
         if let Ok(index) = result  {
             list.get_mut(index)
         } else {
@@ -118,15 +134,43 @@ impl Hash for WotNode {
 // Follow
 #[derive(Debug, Clone)]
 pub struct WotFollow {
-    pub target_pubkey: String,
-    pub source_pubkey: String,
+    pub target_pubkey: PublicKey,
+    pub source_pubkey: PublicKey,
     pub weight: f32,
-    pub attribution: Option<String>
+    pub attribution: Option<String>,
+    /// Domains this follow is trusted about. Empty: All domains. See `scope_matches`.
+    pub scope: Vec<String>,
 }
 
 impl WotFollow {
-    pub fn new(source_pubkey: &str, target_pubkey: &str, weight: f32, attribution: Option<&str>) -> Self {
-        WotFollow { target_pubkey: target_pubkey.to_string(), source_pubkey: source_pubkey.to_string(), weight , attribution: attribution.map(|s| s.to_string())}
+    pub fn new(source_pubkey: PublicKey, target_pubkey: PublicKey, weight: f32, attribution: Option<&str>) -> Self {
+        WotFollow { target_pubkey, source_pubkey, weight , attribution: attribution.map(|s| s.to_string()), scope: vec![]}
+    }
+
+    /**
+     * Like `new` for synthetic graphs. Pubkeys are not validated. See `PublicKey::new_unchecked`.
+     */
+    pub fn new_unchecked(source_pubkey: &str, target_pubkey: &str, weight: f32, attribution: Option<&str>) -> Self {
+        WotFollow::new(PublicKey::new_unchecked(source_pubkey), PublicKey::new_unchecked(target_pubkey), weight, attribution)
+    }
+
+    /**
+     * If the follow is trusted about `domain`.
+     */
+    pub fn is_in_scope(&self, domain: &str) -> bool {
+        self.scope.is_empty() || self.scope.iter().any(|pattern| scope_matches(pattern, domain))
+    }
+}
+
+/**
+ * Matches a domain against a scope pattern. `*.bitcoin` matches all subdomains of `bitcoin`, everything else must match exactly.
+ */
+pub fn scope_matches(pattern: &str, domain: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.').to_lowercase();
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => domain.ends_with(&format!(".{}", suffix)),
+        None => pattern == domain,
     }
 }
 
@@ -154,28 +198,29 @@ impl Hash for WotFollow {
 
 #[cfg(test)]
 mod tests {
+    use crate::public_key::PublicKey;
     use crate::prediction::node::WotFollow;
-    use super::WotNode;
+    use super::{WotNode, scope_matches};
 
     #[test]
     fn sort_node_vec() {
         let mut list: Vec<WotNode> = vec![
-            WotNode::new_list(
+            WotNode::new_list_unchecked(
                 "c", 
                 "", 
                 vec![]
             ),
-            WotNode::new_list(
+            WotNode::new_list_unchecked(
                 "b", 
                 "", 
                 vec![]
             ),
-            WotNode::new_list(
+            WotNode::new_list_unchecked(
                 "a", 
                 "", 
                 vec![]
             ),
-            WotNode::new_list(
+            WotNode::new_list_unchecked(
                 "d", 
                 "", 
                 vec![]
@@ -194,25 +239,43 @@ mod tests {
     #[test]
     fn display_node() {
         let pubkey = String::from("923jladsf");
-        let node: WotNode = WotNode::new_list(
+        let node: WotNode = WotNode::new_list_unchecked(
             &pubkey, 
             "me", 
             vec![
                 WotFollow {
-                    source_pubkey: String::from("hello"),
-                    target_pubkey: String::from("n1"),
+                    source_pubkey: PublicKey::new_unchecked("hello"),
+                    target_pubkey: PublicKey::new_unchecked("n1"),
                     weight: 1.0,
-                    attribution: Some("example.com".to_string())
+                    attribution: Some("example.com".to_string()),
+                    scope: vec![],
                 },
                 WotFollow {
-                    source_pubkey: String::from("hello"),
-                    target_pubkey: String::from("n2"),
+                    source_pubkey: PublicKey::new_unchecked("hello"),
+                    target_pubkey: PublicKey::new_unchecked("n2"),
                     weight: -1.0,
-                    attribution: Some("example.com".to_string())
+                    attribution: Some("example.com".to_string()),
+                    scope: vec![],
                 }
             ]
         );
         println!("{}", node);
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scope() {
+        assert!(scope_matches("*.bitcoin", "satoshi.bitcoin"));
+        assert!(scope_matches("*.bitcoin", "www.satoshi.Bitcoin."));
+        assert!(!scope_matches("*.bitcoin", "bitcoin"));
+        assert!(!scope_matches("*.bitcoin", "notbitcoin"));
+        assert!(scope_matches("example.com", "example.com"));
+        assert!(!scope_matches("example.com", "www.example.com"));
+
+        let mut follow = WotFollow::new_unchecked("me", "alice", 1.0, None);
+        assert!(follow.is_in_scope("example.com"));
+        follow.scope = vec!["*.bitcoin".to_string()];
+        assert!(!follow.is_in_scope("example.com"));
+        assert!(follow.is_in_scope("satoshi.bitcoin"));
+    }
+}
@@ -4,24 +4,53 @@ use burn::tensor::{Data, Shape};
 
 use super::{graph::WotGraph, feed_forward::FeedForward, node::{WotNode, WotFollow}};
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct WotClassPrediction {
     pub pubkey: String,
-    pub probability: f32
+    pub probability: f32,
+    /// Sum of power * weight of all attributions pointing to this class.
+    /// Negative attributions ("this key is NOT example.com") lower it, possibly below zero.
+    pub trust: f32
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct WotNodePrediction {
     pub pubkey: String,
     pub power: f32
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct WotPrediction {
     pub classes: Vec<WotClassPrediction>,
     pub nodes: Vec<WotNodePrediction>,
 }
 
+/**
+ * Requirements a prediction must meet before a class is accepted as the answer.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecisionThresholds {
+    /// Minimum probability of the best class. 0 to 1.
+    pub min_probability: f32,
+    /// Minimum probability lead of the best class over the runner-up. 0 to 1.
+    pub min_margin: f32,
+    /// Minimum total incoming trust of the best class. See `WotClassPrediction.trust`.
+    pub min_trust: f32,
+}
+
+/**
+ * Outcome of `WotPrediction::decide`.
+ */
+#[derive(Clone, Debug)]
+pub enum WotDecision {
+    /// The best class is trustworthy and clearly ahead. Contains its pubkey.
+    Resolved(String),
+    /// Not confident enough which class is right. Contains all classes sorted by probability, best first.
+    Ambiguous(Vec<WotClassPrediction>),
+    /// The best class is not backed by enough trust, or there are no classes at all.
+    Untrusted,
+}
+
 impl fmt::Display for WotPrediction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut out = "".to_string();
@@ -48,6 +77,40 @@ impl WotPrediction {
         })
     }
 
+    /**
+     * Classes sorted by probability, highest first.
+     */
+    pub fn get_sorted_classes(&self) -> Vec<&WotClassPrediction> {
+        let mut classes: Vec<&WotClassPrediction> = self.classes.iter().collect();
+        classes.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+        classes
+    }
+
+    /**
+     * Decides if the best class can be trusted as the answer.
+     * Checks the incoming trust first, then the probability and the margin over the runner-up.
+     */
+    pub fn decide(&self, thresholds: &DecisionThresholds) -> WotDecision {
+        let sorted = self.get_sorted_classes();
+        let best = match sorted.first() {
+            None => return WotDecision::Untrusted,
+            Some(best) => *best,
+        };
+        if best.trust < thresholds.min_trust {
+            return WotDecision::Untrusted;
+        };
+
+        let margin = match sorted.get(1) {
+            None => best.probability,
+            Some(runner_up) => best.probability - runner_up.probability,
+        };
+        if best.probability < thresholds.min_probability || margin < thresholds.min_margin {
+            let candidates = sorted.into_iter().cloned().collect();
+            return WotDecision::Ambiguous(candidates);
+        };
+        WotDecision::Resolved(best.pubkey.clone())
+    }
+
     /**
      * Get value of pubkey
      */
@@ -90,16 +153,32 @@ impl WotPredictor {
                 let power = _prediction[i][j];
 
                 if is_last_layer {
-                    class_predictions.push(WotClassPrediction{pubkey: node.pubkey.clone(), probability: power})
+                    class_predictions.push(WotClassPrediction{pubkey: node.pubkey.to_string(), probability: power, trust: 0.0})
                 } else {
-                    node_predictions.push(WotNodePrediction{pubkey: node.pubkey.clone(), power: power})
+                    node_predictions.push(WotNodePrediction{pubkey: node.pubkey.to_string(), power: power})
                 }
             });
         });
 
+        for class in class_predictions.iter_mut() {
+            class.trust = self.get_incoming_trust(&class.pubkey, &node_predictions);
+        };
+
         WotPrediction { nodes: node_predictions, classes: class_predictions }
     }
 
+    /**
+     * Sum of power * weight of all follows pointing to `pubkey`, negative ones included.
+     * Softmax only compares classes with each other, so a class that is only distrusted can still get a high probability.
+     * The trust makes this visible.
+     */
+    fn get_incoming_trust(&self, pubkey: &str, node_predictions: &[WotNodePrediction]) -> f32 {
+        self.graph.get_follows_by_target_pubkey(pubkey).iter().map(|follow| {
+            let power = node_predictions.iter().find(|node| follow.source_pubkey == node.pubkey).map(|node| node.power).unwrap_or(0.0);
+            power * follow.weight
+        }).sum()
+    }
+
     pub fn train(&mut self, correct_pubkey: &str, learning_rates: Vec<f64>) {
         let weights = self.get_ff_weights();
         let feed_forward = FeedForward::new(weights);
@@ -160,8 +239,10 @@ impl WotPredictor {
                         let temp = WotNode{
                             pubkey: follow.target_pubkey.clone(),
                             alias: "".to_string(),
-                            follows:  vec![WotFollow::new(&follow.target_pubkey, &follow.target_pubkey, 1.0, None)]
+                            follows:  vec![WotFollow::new(follow.target_pubkey.clone(), follow.target_pubkey.clone(), 1.0, None)]
                         };
+                        // Remember the temp node. Duplicates would multiply the power passed through.
+                        current_layer_map.insert(temp.pubkey.clone(), temp.clone());
                         current_layer.push(temp);
                     }
                 };
@@ -259,9 +340,11 @@ impl From<WotGraph> for WotPredictor {
 
 #[cfg(test)]
 mod tests {
+    use crate::public_key::PublicKey;
 
 
-    use crate::prediction::predictor::WotPredictor;
+    use crate::{config_directory::{follow::Follow, follow_list::FollowList}, pruning::prune::prune_graph};
+    use crate::prediction::predictor::{WotPredictor, WotPrediction, WotClassPrediction, WotDecision, DecisionThresholds};
 
     use super::super::node::{WotNode, WotFollow};
     use super::WotGraph;
@@ -275,40 +358,40 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com1"),
             follows: vec![],
         });
         nodes.push(WotNode {
-            pubkey: "d2".to_string(),
+            pubkey: PublicKey::new_unchecked("d2"),
             alias: String::from("example.com2"),
             follows: vec![],
         });
 
         nodes.push(WotNode {
-            pubkey: "n2".to_string(),
+            pubkey: PublicKey::new_unchecked("n2"),
             alias: "".to_string(),
             follows: vec![
-                    WotFollow::new("n2", "d1", 1.0, Some("example.com")),
-                    WotFollow::new("n2", "d2", -1.0, Some("example.com"))
+                    WotFollow::new_unchecked("n2", "d1", 1.0, Some("example.com")),
+                    WotFollow::new_unchecked("n2", "d2", -1.0, Some("example.com"))
                 ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n1".to_string(),
+            pubkey: PublicKey::new_unchecked("n1"),
             alias: "".to_string(),
             follows: vec![
-                    WotFollow::new("n1", "d1", -0.5, Some("example.com")),
-                    WotFollow::new("n1", "d2", 0.0, Some("example.com"))
+                    WotFollow::new_unchecked("n1", "d1", -0.5, Some("example.com")),
+                    WotFollow::new_unchecked("n1", "d2", 0.0, Some("example.com"))
                 ]
         });
 
         nodes.push(WotNode {
-            pubkey: "me".to_string(),
+            pubkey: PublicKey::new_unchecked("me"),
             alias: "".to_string(),
             follows: vec![
-                    WotFollow::new("me", "n1", 1.0, None),
-                    WotFollow::new("me", "n2", 0.5, None)
+                    WotFollow::new_unchecked("me", "n1", 1.0, None),
+                    WotFollow::new_unchecked("me", "n2", 0.5, None)
                 ]
         });
 
@@ -320,47 +403,47 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com1"),
             follows: vec![],
         });
         nodes.push(WotNode {
-            pubkey: "d2".to_string(),
+            pubkey: PublicKey::new_unchecked("d2"),
             alias: String::from("example.com2"),
             follows: vec![],
         });
 
         nodes.push(WotNode {
-            pubkey: "n3".to_string(),
+            pubkey: PublicKey::new_unchecked("n3"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n1", "d1", 1.0, Some("example.com")),
-                WotFollow::new("n1", "d2", 1.0, Some("example.com"))
+                WotFollow::new_unchecked("n1", "d1", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("n1", "d2", 1.0, Some("example.com"))
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n2".to_string(),
+            pubkey: PublicKey::new_unchecked("n2"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n2", "d2", -1.0, Some("example.com"))
+                WotFollow::new_unchecked("n2", "d2", -1.0, Some("example.com"))
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n1".to_string(),
+            pubkey: PublicKey::new_unchecked("n1"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n1", "n3", 1.0, None),
+                WotFollow::new_unchecked("n1", "n3", 1.0, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "me".to_string(),
+            pubkey: PublicKey::new_unchecked("me"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("me", "n1", 1.0, None),
-                WotFollow::new("me", "n2", 0.5, None)
+                WotFollow::new_unchecked("me", "n1", 1.0, None),
+                WotFollow::new_unchecked("me", "n2", 0.5, None)
             ]
         });
 
@@ -390,6 +473,26 @@ mod tests {
         assert_eq!(layers[3].len(), 2);
     }
 
+    #[test]
+    fn temp_nodes_are_not_duplicated() {
+        // a and b both skip the layer of x.
+        let graph = WotGraph::new(vec![
+            WotNode::new_class_unchecked("d1", "example.com"),
+            WotNode::new_list_unchecked("x", "", vec![WotFollow::new_unchecked("x", "d1", 1.0, Some("example.com"))]),
+            WotNode::new_list_unchecked("z", "", vec![WotFollow::new_unchecked("z", "d1", 1.0, Some("example.com"))]),
+            WotNode::new_list_unchecked("y", "", vec![WotFollow::new_unchecked("y", "z", 1.0, None)]),
+            WotNode::new_list_unchecked("a", "", vec![WotFollow::new_unchecked("a", "x", 1.0, None), WotFollow::new_unchecked("a", "y", 1.0, None)]),
+            WotNode::new_list_unchecked("b", "", vec![WotFollow::new_unchecked("b", "x", 1.0, None), WotFollow::new_unchecked("b", "y", 1.0, None)]),
+            WotNode::new_list_unchecked("me", "", vec![WotFollow::new_unchecked("me", "a", 1.0, None), WotFollow::new_unchecked("me", "b", 1.0, None)]),
+        ]);
+        let predictor: WotPredictor = graph.into();
+
+        let layers = predictor.layers_with_temp_nodes();
+        assert_eq!(layers[2].len(), 2);
+        let power_x: Vec<f32> = predictor.predict().nodes.iter().filter(|node| node.pubkey == "x").map(|node| node.power).collect();
+        assert_eq!(power_x, vec![2.0, 2.0]);
+    }
+
     #[test]
     fn predict_simple() {
         let graph = get_simple_graph();
@@ -413,6 +516,125 @@ mod tests {
         assert_eq!(result.get_value("d2").unwrap(), 0.18242551);
     }
 
+    #[test]
+    fn incoming_trust() {
+        let graph = get_simple_graph();
+        let predictor: WotPredictor = graph.into();
+        let result = predictor.predict();
+        let d1 = result.classes.iter().find(|class| class.pubkey == "d1").unwrap();
+        assert_eq!(d1.trust, 0.0);
+        let d2 = result.classes.iter().find(|class| class.pubkey == "d2").unwrap();
+        assert_eq!(d2.trust, -0.5);
+    }
+
+    #[test]
+    fn only_distrusted_class_is_untrusted() {
+        let graph = WotGraph::new(vec![
+            WotNode::new_class_unchecked("d1", ""),
+            WotNode::new_list_unchecked("me", "", vec![
+                WotFollow::new_unchecked("me", "d1", -1.0, Some("example.com")),
+            ]),
+        ]);
+        let predictor: WotPredictor = graph.into();
+        let result = predictor.predict();
+        assert_eq!(result.get_best_class().unwrap().pubkey, "d1");
+        assert!(matches!(result.decide(&get_thresholds()), WotDecision::Untrusted));
+    }
+
+    /**
+     * `evil` is vouched for by all friends but blocked by me.
+     */
+    fn get_blocked_lists() -> Vec<FollowList> {
+        let mut me = FollowList::new_with_follows(PublicKey::new_unchecked("me"), "", vec![
+            Follow::new(PublicKey::new_unchecked("n1"), 1.0, None),
+            Follow::new(PublicKey::new_unchecked("n2"), 1.0, None),
+            Follow::new(PublicKey::new_unchecked("evil"), 1.0, None),
+            Follow::new(PublicKey::new_unchecked("good"), 0.5, Some("example.com")),
+            Follow::new(PublicKey::new_unchecked("good2"), 0.5, Some("example.org")),
+        ]);
+        me.blocks.push(PublicKey::new_unchecked("evil"));
+        let n1 = FollowList::new_with_follows(PublicKey::new_unchecked("n1"), "", vec![
+            Follow::new(PublicKey::new_unchecked("evil"), 1.0, Some("example.com")),
+            Follow::new(PublicKey::new_unchecked("evil"), 1.0, Some("example.org")),
+            Follow::new(PublicKey::new_unchecked("evil"), 1.0, None),
+        ]);
+        let n2 = FollowList::new_with_follows(PublicKey::new_unchecked("n2"), "", vec![
+            Follow::new(PublicKey::new_unchecked("evil"), 1.0, Some("example.com")),
+            Follow::new(PublicKey::new_unchecked("evil"), 1.0, Some("example.org")),
+        ]);
+        let evil = FollowList::new_with_follows(PublicKey::new_unchecked("evil"), "", vec![
+            Follow::new(PublicKey::new_unchecked("evil2"), 1.0, Some("example.com")),
+            Follow::new(PublicKey::new_unchecked("evil2"), 1.0, Some("example.org")),
+        ]);
+        vec![me, n1, n2, evil]
+    }
+
+    #[test]
+    fn blocked_key_can_not_win() {
+        for domain in ["example.com", "example.org"] {
+            let graph: WotGraph = get_blocked_lists().into();
+            let graph = prune_graph(graph, "me", domain);
+            let predictor: WotPredictor = graph.into();
+            let result = predictor.predict();
+
+            assert!(result.classes.iter().all(|class| class.pubkey != "evil"), "{}", domain);
+            // Attributions made by evil don't count either.
+            assert!(result.classes.iter().all(|class| class.pubkey != "evil2"), "{}", domain);
+            assert!(result.nodes.iter().all(|node| node.pubkey != "evil"), "{}", domain);
+            assert!(matches!(result.decide(&get_thresholds()), WotDecision::Resolved(pubkey) if pubkey.starts_with("good")));
+        }
+    }
+
+    fn get_prediction(classes: Vec<(f32, f32)>) -> WotPrediction {
+        let classes = classes.into_iter().enumerate().map(|(i, (probability, trust))| {
+            WotClassPrediction { pubkey: format!("d{}", i + 1), probability, trust }
+        }).collect();
+        WotPrediction { classes, nodes: vec![] }
+    }
+
+    fn get_thresholds() -> DecisionThresholds {
+        DecisionThresholds { min_probability: 0.6, min_margin: 0.2, min_trust: 0.25 }
+    }
+
+    #[test]
+    fn decide_resolved() {
+        let prediction = get_prediction(vec![(0.2, 1.0), (0.8, 1.0)]);
+        let decision = prediction.decide(&get_thresholds());
+        assert!(matches!(decision, WotDecision::Resolved(pubkey) if pubkey == "d2"));
+    }
+
+    #[test]
+    fn decide_too_close() {
+        let prediction = get_prediction(vec![(0.51, 1.0), (0.49, 1.0)]);
+        let thresholds = DecisionThresholds { min_probability: 0.5, ..get_thresholds() };
+        match prediction.decide(&thresholds) {
+            WotDecision::Ambiguous(candidates) => {
+                assert_eq!(candidates.len(), 2);
+                assert_eq!(candidates[0].pubkey, "d1");
+            },
+            other => panic!("Expected ambiguous, got {:?}", other)
+        };
+    }
+
+    #[test]
+    fn decide_below_min_probability() {
+        let prediction = get_prediction(vec![(0.4, 1.0), (0.3, 1.0), (0.3, 1.0)]);
+        let thresholds = DecisionThresholds { min_margin: 0.05, ..get_thresholds() };
+        assert!(matches!(prediction.decide(&thresholds), WotDecision::Ambiguous(_)));
+    }
+
+    #[test]
+    fn decide_weak_single_attestation() {
+        let prediction = get_prediction(vec![(1.0, 0.1)]);
+        assert!(matches!(prediction.decide(&get_thresholds()), WotDecision::Untrusted));
+    }
+
+    #[test]
+    fn decide_no_classes() {
+        let prediction = get_prediction(vec![]);
+        assert!(matches!(prediction.decide(&get_thresholds()), WotDecision::Untrusted));
+    }
+
     #[ignore] // Todo: Test fails but can't be bother yet to fix it. Very advanced feature.
     #[test]
     fn train_simple() {
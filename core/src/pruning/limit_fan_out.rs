@@ -0,0 +1,119 @@
+use crate::prediction::graph::WotGraph;
+
+/**
+ * Scales the follows of every list except me so their absolute weights, attributions included, sum up to at most 1.
+ * A list then can't hand out more trust than it receives itself, no matter how many keys it follows.
+ * In the acyclic graph this bounds everything reachable only through a list by the trust in that list.
+ * A cluster of freshly created keys that all follow each other is therefore worth at most one follow of the list that introduced it.
+ * The me list is not limited. Its weights are the user's own choices.
+ */
+pub fn limit_fan_out(mut graph: WotGraph, me_pubkey: &str) -> WotGraph {
+    for node in graph.nodes.iter_mut() {
+        if node.pubkey == me_pubkey {
+            continue;
+        };
+        let total: f32 = node.follows.iter().map(|follow| follow.weight.abs()).sum();
+        if total <= 1.0 {
+            continue;
+        };
+        for follow in node.follows.iter_mut() {
+            follow.weight /= total;
+        };
+    };
+    graph
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::prediction::{graph::WotGraph, node::{WotNode, WotFollow}, predictor::WotPredictor};
+    use crate::pruning::prune::prune_graph;
+    use super::limit_fan_out;
+
+    const SYBILS: usize = 100;
+
+    /**
+     * Me follows `honest` which attributes the right key.
+     * `attacker` creates 100 keys that follow each other in a ring and attribute the wrong key.
+     * Without the limit every key of the ring would pass on the full trust of the attacker.
+     */
+    fn get_sybil_graph(me_follows: Vec<WotFollow>, extra_lists: Vec<WotNode>) -> WotGraph {
+        let sybils: Vec<String> = (0..SYBILS).map(|i| format!("sybil{:03}", i)).collect();
+        let mut nodes = vec![
+            WotNode::new_class_unchecked("right", "example.com"),
+            WotNode::new_class_unchecked("wrong", "example.com"),
+            WotNode::new_list_unchecked("me", "", me_follows),
+            WotNode::new_list_unchecked("honest", "", vec![WotFollow::new_unchecked("honest", "right", 1.0, Some("example.com"))]),
+            WotNode::new_list_unchecked("attacker", "", sybils.iter().map(|sybil| WotFollow::new_unchecked("attacker", sybil, 1.0, None)).collect()),
+        ];
+        for (i, sybil) in sybils.iter().enumerate() {
+            let next = &sybils[(i + 1) % SYBILS];
+            let mut follows = vec![WotFollow::new_unchecked(sybil, next, 1.0, None)];
+            follows.push(WotFollow::new_unchecked(sybil, "wrong", 1.0, Some("example.com")));
+            follows.push(WotFollow::new_unchecked(sybil, "right", -1.0, Some("example.com")));
+            nodes.push(WotNode::new_list_unchecked(sybil, "", follows));
+        };
+        nodes.extend(extra_lists);
+        WotGraph::new(nodes)
+    }
+
+    #[test]
+    fn limit_fan_out_except_me() {
+        let graph = WotGraph::new(vec![
+            WotNode::new_list_unchecked("me", "", vec![
+                WotFollow::new_unchecked("me", "l1", 1.0, None),
+                WotFollow::new_unchecked("me", "l2", 1.0, None),
+            ]),
+            WotNode::new_list_unchecked("l1", "", vec![
+                WotFollow::new_unchecked("l1", "l2", 1.0, None),
+                WotFollow::new_unchecked("l1", "d1", -1.0, Some("example.com")),
+            ]),
+            WotNode::new_list_unchecked("l2", "", vec![WotFollow::new_unchecked("l2", "d1", 0.5, Some("example.com"))]),
+            WotNode::new_class_unchecked("d1", "example.com"),
+        ]);
+        let graph = limit_fan_out(graph, "me");
+        assert_eq!(graph.get_follow("me", "l1").unwrap().weight, 1.0);
+        assert_eq!(graph.get_follow("me", "l2").unwrap().weight, 1.0);
+        assert_eq!(graph.get_follow("l1", "l2").unwrap().weight, 0.5);
+        assert_eq!(graph.get_follow("l1", "d1").unwrap().weight, -0.5);
+        assert_eq!(graph.get_follow("l2", "d1").unwrap().weight, 0.5);
+    }
+
+    #[test]
+    fn sybil_cluster_cant_outweigh_equal_direct_follow() {
+        let graph = get_sybil_graph(vec![
+            WotFollow::new_unchecked("me", "honest", 1.0, None),
+            WotFollow::new_unchecked("me", "attacker", 1.0, None),
+        ], vec![]);
+        let graph = prune_graph(graph, "me", "example.com");
+        let predictor: WotPredictor = graph.into();
+        let prediction = predictor.predict();
+
+        let right = prediction.classes.iter().find(|class| class.pubkey == "right").unwrap();
+        let wrong = prediction.classes.iter().find(|class| class.pubkey == "wrong").unwrap();
+        assert!(wrong.trust <= 1.0 + 1e-4, "Sybil trust {} exceeds the trust in the attacker.", wrong.trust);
+        assert!(wrong.probability <= 0.5 + 1e-4, "Sybils outweigh a direct follow: {} vs {}", wrong.probability, right.probability);
+    }
+
+    #[test]
+    fn sybil_cluster_cant_outweigh_direct_follow() {
+        // The attacker is reached through a friend who follows another list too.
+        let graph = get_sybil_graph(vec![
+            WotFollow::new_unchecked("me", "honest", 1.0, None),
+            WotFollow::new_unchecked("me", "friend", 1.0, None),
+        ], vec![
+            WotNode::new_list_unchecked("friend", "", vec![
+                WotFollow::new_unchecked("friend", "attacker", 1.0, None),
+                WotFollow::new_unchecked("friend", "other", 1.0, None),
+            ]),
+            WotNode::new_list_unchecked("other", "", vec![WotFollow::new_unchecked("other", "right", 1.0, Some("example.com"))]),
+        ]);
+        let graph = prune_graph(graph, "me", "example.com");
+        let predictor: WotPredictor = graph.into();
+        let prediction = predictor.predict();
+
+        assert_eq!(prediction.get_best_class().unwrap().pubkey, "right");
+        let wrong = prediction.classes.iter().find(|class| class.pubkey == "wrong").unwrap();
+        assert!(wrong.trust <= 0.5 + 1e-4, "Sybil trust {} exceeds the trust in the attacker.", wrong.trust);
+    }
+}
@@ -2,4 +2,6 @@ pub mod prune;
 mod prune_undesired_attributions;
 mod prune_cycles;
 mod prune_useless_nodes;
-mod prune_class_follows;
\ No newline at end of file
+mod prune_class_follows;
+mod prune_blocked;
+mod limit_fan_out;
\ No newline at end of file
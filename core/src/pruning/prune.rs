@@ -1,26 +1,39 @@
 use crate::prediction::graph::WotGraph;
 
-use super::{prune_undesired_attributions::prune_undesired_attributions, prune_useless_nodes::UselessNodePruner, prune_cycles::CyclePruner, prune_class_follows::{prune_class_follows, prune_attribution_chains}};
+use super::{prune_undesired_attributions::prune_undesired_attributions, prune_useless_nodes::UselessNodePruner, prune_cycles::CyclePruner, prune_class_follows::{prune_class_follows, prune_attribution_chains}, prune_blocked::prune_blocked, limit_fan_out::limit_fan_out};
 
 
 /**
  * Turns the possibly cyclical Web of Trust graph into an acyclical graph and prunes unnecesarry nodes.
  * This is needed to do any calculation.
+ * Finally limits the fan-out of every list so a cluster of Sybil keys can't multiply the trust it gets.
+ * Returns an empty graph if no class can be reached from me.
  */
 pub fn prune_graph(graph: WotGraph, me_pubkey: &str, desired_attribution: &str) -> WotGraph {
+    let graph = prune_blocked(graph, me_pubkey);
     let graph = prune_undesired_attributions(graph, desired_attribution);
     let graph = UselessNodePruner::prune(graph, me_pubkey);
-    let graph = CyclePruner::prune(graph, me_pubkey);
+    if graph.get_node(me_pubkey).is_none() {
+        // No class can be reached from me. Nothing is left.
+        return graph;
+    };
+    // Classes lose their regular follows before the cycles are broken. Otherwise the cycle pruner may cut
+    // the only follow into a node that is needed once the follows of a class are gone.
     let graph = prune_attribution_chains(graph);
     let graph = prune_class_follows(graph);
+    let graph = CyclePruner::prune(graph, me_pubkey);
     let graph = UselessNodePruner::prune(graph, me_pubkey);
+    let graph = limit_fan_out(graph, me_pubkey);
     graph
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::{prediction::node::{WotNode, WotFollow}, pruning::prune::prune_graph};
+    use std::collections::HashSet;
+    use proptest::{prelude::*, collection::vec};
+    use crate::public_key::PublicKey;
+    use crate::{prediction::{node::{WotNode, WotFollow}, predictor::WotPredictor}, pruning::prune::prune_graph};
     use super::WotGraph;
 
     /**
@@ -31,50 +44,50 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com1"),
             follows: vec![]
         });
         nodes.push(WotNode {
-            pubkey: "d2".to_string(),
+            pubkey: PublicKey::new_unchecked("d2"),
             alias: String::from("example.com2"),
             follows: vec![]
         });
 
         nodes.push(WotNode {
-            pubkey: "n2".to_string(),
+            pubkey: PublicKey::new_unchecked("n2"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n2", "d1", 1.0, Some("example.com")),
-                WotFollow::new("n2", "d2", -1.0, Some("example.com")),
-                WotFollow::new("n2", "n3", -1.0, None),
+                WotFollow::new_unchecked("n2", "d1", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("n2", "d2", -1.0, Some("example.com")),
+                WotFollow::new_unchecked("n2", "n3", -1.0, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n1".to_string(),
+            pubkey: PublicKey::new_unchecked("n1"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n1", "d1", -0.5, Some("example.com")),
-                WotFollow::new("n1", "d2", 0.0, Some("example.com")),
-                WotFollow::new("n1", "me", 0.0, None)
+                WotFollow::new_unchecked("n1", "d1", -0.5, Some("example.com")),
+                WotFollow::new_unchecked("n1", "d2", 0.0, Some("example.com")),
+                WotFollow::new_unchecked("n1", "me", 0.0, None)
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n3".to_string(),
+            pubkey: PublicKey::new_unchecked("n3"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n3", "me", -0.5, None),
+                WotFollow::new_unchecked("n3", "me", -0.5, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "me".to_string(),
+            pubkey: PublicKey::new_unchecked("me"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("me", "n1", 1.0, None),
-                WotFollow::new("me", "n2", 0.5, None)
+                WotFollow::new_unchecked("me", "n1", 1.0, None),
+                WotFollow::new_unchecked("me", "n2", 0.5, None)
             ]
         });
 
@@ -89,5 +102,174 @@ mod tests {
         assert_eq!(pruned.nodes.len(), 5);
     }
 
+    #[test]
+    fn prune_without_reachable_class() {
+        // Me doesn't follow anybody. The cycle pruning used to panic because me was already pruned.
+        let nodes = vec![
+            WotNode::new_list_unchecked("me", "", vec![]),
+            WotNode::new_list_unchecked("n1", "", vec![WotFollow::new_unchecked("n1", "d1", 1.0, Some("example.com"))]),
+            WotNode::new_class_unchecked("d1", "example.com"),
+        ];
+        let pruned = prune_graph(WotGraph::new(nodes), "me", "example.com");
+        assert!(pruned.nodes.is_empty());
+    }
+
+    #[test]
+    fn prune_cycle_next_to_class() {
+        // n2 is a class and an attributor, so it loses all its follows. The cycle n3 -> n4 -> n3 must not be cut at n3 -> n4,
+        // that is the only way left to reach n4 and its attribution of n5.
+        let nodes = vec![
+            WotNode::new_list_unchecked("me", "", vec![
+                WotFollow::new_unchecked("me", "n2", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("me", "n3", 1.0, None),
+            ]),
+            WotNode::new_list_unchecked("n2", "", vec![
+                WotFollow::new_unchecked("n2", "n4", 1.0, None),
+                WotFollow::new_unchecked("n2", "d1", 1.0, Some("example.com")),
+            ]),
+            WotNode::new_list_unchecked("n3", "", vec![WotFollow::new_unchecked("n3", "n4", 1.0, None)]),
+            WotNode::new_list_unchecked("n4", "", vec![
+                WotFollow::new_unchecked("n4", "n5", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("n4", "n3", 1.0, None),
+            ]),
+            WotNode::new_class_unchecked("n5", "example.com"),
+            WotNode::new_class_unchecked("d1", "example.com"),
+        ];
+        let pruned = prune_graph(WotGraph::new(nodes), "me", "example.com");
+        assert!(pruned.get_follow("n3", "n4").is_some());
+        assert!(pruned.get_node("n5").is_some());
+    }
+
+    const LISTS: [&str; 6] = ["me", "l1", "l2", "l3", "l4", "l5"];
+    const CLASSES: [&str; 3] = ["d1", "d2", "d3"];
 
+    /// Source index into `LISTS`, target index into `LISTS` followed by `CLASSES`, weight, attribution.
+    type FollowSpec = (usize, usize, f32, Option<&'static str>);
+
+    /**
+     * Random follows between a fixed set of nodes, self follows, cycles and foreign domains included.
+     * Shrinks towards fewer follows, lower indices and no attribution.
+     */
+    fn arb_follows() -> impl Strategy<Value = Vec<FollowSpec>> {
+        let attribution = prop_oneof![2 => Just(None), 2 => Just(Some("example.com")), 1 => Just(Some("other.com"))];
+        vec((0..LISTS.len(), 0..LISTS.len() + CLASSES.len(), -1.0f32..=1.0, attribution), 0..30)
+    }
+
+    /**
+     * Builds the graph. Only the first follow from a source to a target is kept, like in a list.
+     */
+    fn build_graph(follows: &[FollowSpec], blocked: &[usize]) -> WotGraph {
+        let targets: Vec<&str> = LISTS.iter().chain(CLASSES.iter()).copied().collect();
+        let mut nodes: Vec<WotNode> = LISTS.iter().map(|pubkey| WotNode::new_list_unchecked(pubkey, "", vec![])).collect();
+        nodes.extend(CLASSES.iter().map(|pubkey| WotNode::new_class_unchecked(pubkey, "")));
+        for (source, target, weight, attribution) in follows.iter() {
+            let node = &mut nodes[*source];
+            if node.get_follow(targets[*target]).is_none() {
+                node.follows.push(WotFollow::new_unchecked(LISTS[*source], targets[*target], *weight, *attribution));
+            };
+        };
+        let mut graph = WotGraph::new(nodes);
+        let blocked: HashSet<PublicKey> = blocked.iter().map(|i| PublicKey::new_unchecked(LISTS[*i])).collect();
+        graph.blocks.insert(PublicKey::new_unchecked("me"), blocked);
+        graph
+    }
+
+    fn reachable_from(graph: &WotGraph, pubkey: &str) -> HashSet<String> {
+        let mut reachable = HashSet::from([pubkey.to_string()]);
+        let mut stack = vec![pubkey.to_string()];
+        while let Some(current) = stack.pop() {
+            for follow in graph.get_node(&current).map(|node| node.follows.clone()).unwrap_or_default() {
+                if reachable.insert(follow.target_pubkey.to_string()) {
+                    stack.push(follow.target_pubkey.to_string());
+                };
+            };
+        };
+        reachable
+    }
+
+    /**
+     * Whether me reaches a class without any pruner, so it can check them. Classes are nodes attributed example.com.
+     * Paths only use regular follows in scope and never pass a class or a blocked node. Nodes that attribute themselves can't be classes.
+     */
+    fn me_reaches_class(graph: &WotGraph) -> bool {
+        let blocked = graph.get_blocks("me");
+        let follows: Vec<WotFollow> = graph.get_follows().into_iter().filter(|follow| {
+            let in_scope = match &follow.attribution {
+                None => follow.is_in_scope("example.com"),
+                Some(attribution) => attribution == "example.com",
+            };
+            in_scope && !blocked.contains(&follow.target_pubkey) && graph.get_node(&follow.target_pubkey).is_some()
+        }).cloned().collect();
+        let classes: HashSet<&str> = follows.iter().filter(|follow| follow.attribution.is_some()).map(|follow| follow.target_pubkey.as_str()).collect();
+        let attributors: HashSet<&str> = follows.iter().filter(|follow| follow.attribution.is_some()).map(|follow| follow.source_pubkey.as_str()).collect();
+        if classes.contains("me") {
+            return false;
+        };
+
+        let mut reachable = HashSet::from(["me"]);
+        let mut stack = vec!["me"];
+        while let Some(current) = stack.pop() {
+            for follow in follows.iter().filter(|follow| follow.source_pubkey.as_str() == current) {
+                let target = follow.target_pubkey.as_str();
+                if follow.attribution.is_some() {
+                    if !attributors.contains(target) {
+                        return true;
+                    };
+                    continue;
+                };
+                if !classes.contains(target) && reachable.insert(target) {
+                    stack.push(target);
+                };
+            };
+        };
+        false
+    }
+
+    proptest! {
+        #[test]
+        fn pruned_graph_invariants(follows in arb_follows(), blocked in vec(1..LISTS.len(), 0..2)) {
+            let graph = build_graph(&follows, &blocked);
+            let reaches_class = me_reaches_class(&graph);
+            let pruned = prune_graph(graph, "me", "example.com");
+
+            // Panics if there is a cycle left.
+            pruned.get_layers();
+            prop_assert!(pruned.validate().is_ok());
+            if pruned.nodes.is_empty() {
+                // Only allowed if there was nothing to keep.
+                prop_assert!(!reaches_class, "Everything got pruned but me reaches a class.");
+                return Ok(());
+            };
+
+            prop_assert!(pruned.get_node("me").is_some(), "Me got pruned but {} nodes are left.", pruned.nodes.len());
+            let classes: HashSet<String> = pruned.get_classes().iter().map(|class| class.pubkey.to_string()).collect();
+            prop_assert!(!classes.is_empty());
+            let from_me = reachable_from(&pruned, "me");
+            for node in pruned.nodes.iter() {
+                prop_assert!(from_me.contains(node.pubkey.as_str()), "{} can't be reached from me.", node.pubkey);
+                let leads_to_class = reachable_from(&pruned, &node.pubkey).iter().any(|pubkey| classes.contains(pubkey));
+                prop_assert!(leads_to_class, "{} doesn't lead to a class.", node.pubkey);
+            };
+            for follow in pruned.get_follows().iter().filter(|follow| follow.attribution.is_some()) {
+                prop_assert_eq!(follow.attribution.as_deref(), Some("example.com"));
+            };
+        }
+
+        #[test]
+        fn probabilities_sum_up_to_one(follows in arb_follows(), blocked in vec(1..LISTS.len(), 0..2)) {
+            let graph = build_graph(&follows, &blocked);
+            let reaches_class = me_reaches_class(&graph);
+            let pruned = prune_graph(graph, "me", "example.com");
+            if pruned.nodes.is_empty() {
+                prop_assert!(!reaches_class, "Everything got pruned but me reaches a class.");
+                return Ok(());
+            };
+
+            let predictor: WotPredictor = pruned.into();
+            let prediction = predictor.predict();
+            prop_assert!(!prediction.classes.is_empty());
+            let sum: f32 = prediction.classes.iter().map(|class| class.probability).sum();
+            prop_assert!((sum - 1.0).abs() < 1e-4, "Probabilities sum up to {}.", sum);
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use crate::prediction::graph::WotGraph;
+
+/**
+ * Removes all pubkeys blocked by the me list from the graph, including every follow pointing to them.
+ * A blocked pubkey can't contribute as a list and can't be predicted as a class.
+ * Blocks of other lists are ignored. Otherwise anybody could censor anybody else.
+ */
+pub fn prune_blocked(mut graph: WotGraph, me_pubkey: &str) -> WotGraph {
+    let mut blocked = graph.get_blocks(me_pubkey);
+    blocked.remove(me_pubkey);
+    if blocked.is_empty() {
+        return graph;
+    };
+
+    graph.nodes.retain(|node| !blocked.contains(&node.pubkey));
+    for node in graph.nodes.iter_mut() {
+        node.follows.retain(|follow| !blocked.contains(&follow.target_pubkey));
+    };
+    graph
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::public_key::PublicKey;
+    use crate::prediction::{graph::WotGraph, node::{WotNode, WotFollow}};
+    use super::prune_blocked;
+
+    fn get_graph() -> WotGraph {
+        let mut graph = WotGraph::new(vec![
+            WotNode::new_class_unchecked("d1", ""),
+            WotNode::new_list_unchecked("evil", "", vec![
+                WotFollow::new_unchecked("evil", "d1", 1.0, Some("example.com")),
+            ]),
+            WotNode::new_list_unchecked("n1", "", vec![
+                WotFollow::new_unchecked("n1", "evil", 1.0, Some("example.com")),
+            ]),
+            WotNode::new_list_unchecked("me", "", vec![
+                WotFollow::new_unchecked("me", "n1", 1.0, None),
+                WotFollow::new_unchecked("me", "evil", 1.0, None),
+            ]),
+        ]);
+        graph.blocks.insert(PublicKey::new_unchecked("me"), [PublicKey::new_unchecked("evil"), PublicKey::new_unchecked("me")].into());
+        graph
+    }
+
+    #[test]
+    fn remove_blocked_node_and_follows() {
+        let graph = prune_blocked(get_graph(), "me");
+        assert!(graph.get_node("evil").is_none());
+        assert!(graph.get_node("me").is_some());
+        assert!(graph.get_follows_by_target_pubkey("evil").is_empty());
+        assert!(graph.validate().is_ok());
+    }
+
+    #[test]
+    fn ignore_blocks_of_others() {
+        let mut graph = get_graph();
+        let blocks = graph.blocks.remove("me").unwrap();
+        graph.blocks.insert(PublicKey::new_unchecked("n1"), blocks);
+        let graph = prune_blocked(graph, "me");
+        assert!(graph.get_node("evil").is_some());
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::prediction::{graph::WotGraph, node::WotFollow};
+use crate::{prediction::{graph::WotGraph, node::WotFollow}, public_key::PublicKey};
 
 /**
  * If somebody claims a class it can't contribute to the result otherwise.
@@ -8,7 +8,7 @@ use crate::prediction::{graph::WotGraph, node::WotFollow};
  */
 pub fn prune_class_follows(mut graph: WotGraph) -> WotGraph {
     // Remove all other follows of nodes that attribute.
-    let pubkeys_that_get_attributed: HashSet<String> = graph.get_follows().into_iter().filter(|follow| follow.attribution.is_some()).map(|follow| follow.target_pubkey.clone()).collect();
+    let pubkeys_that_get_attributed: HashSet<PublicKey> = graph.get_follows().into_iter().filter(|follow| follow.attribution.is_some()).map(|follow| follow.target_pubkey.clone()).collect();
 
     for pubkey in pubkeys_that_get_attributed {
         let node = graph.get_node_mut(&pubkey).unwrap();
@@ -27,7 +27,7 @@ pub fn prune_attribution_chains(mut graph: WotGraph) -> WotGraph {
         follow.attribution.is_some()
     }).collect();
 
-    let attributor: HashSet<String> = attribution_follows.iter().map(|follow| follow.source_pubkey.clone()).collect();
+    let attributor: HashSet<PublicKey> = attribution_follows.iter().map(|follow| follow.source_pubkey.clone()).collect();
     let invalid_follows: Vec<WotFollow> = attribution_follows.into_iter().filter(|follow| attributor.contains(&follow.target_pubkey)).map(|f| f.clone()).collect();
     for follow in invalid_follows {
         graph.remove_follow(&follow);
@@ -39,6 +39,7 @@ pub fn prune_attribution_chains(mut graph: WotGraph) -> WotGraph {
 
 #[cfg(test)]
 mod tests {
+    use crate::public_key::PublicKey;
     use crate::{prediction::{node::{WotNode, WotFollow}, graph::WotGraph}, pruning::prune_class_follows::prune_class_follows};
 
     use super::prune_attribution_chains;
@@ -51,41 +52,41 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "evilexample".to_string(),
+            pubkey: PublicKey::new_unchecked("evilexample"),
             alias: String::from("example.com1"),
             follows: vec![]
         });
         nodes.push(WotNode {
-            pubkey: "goodexample".to_string(),
+            pubkey: PublicKey::new_unchecked("goodexample"),
             alias: String::from("example.com2"),
             follows: vec![]
         });
 
         nodes.push(WotNode {
-            pubkey: "registrar".to_string(),
+            pubkey: PublicKey::new_unchecked("registrar"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("registrar", "goodexample", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("registrar", "goodexample", 1.0, Some("example.com")),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "eve".to_string(),
+            pubkey: PublicKey::new_unchecked("eve"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("eve", "registrar", -1.0, Some("example.com")),
-                WotFollow::new("eve", "evilexample", 1.0, Some("example.com")),
-                WotFollow::new("eve", "goodexample", -1.0, Some("example.com")),
+                WotFollow::new_unchecked("eve", "registrar", -1.0, Some("example.com")),
+                WotFollow::new_unchecked("eve", "evilexample", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("eve", "goodexample", -1.0, Some("example.com")),
             ]
         });
 
 
         nodes.push(WotNode {
-            pubkey: "me".to_string(),
+            pubkey: PublicKey::new_unchecked("me"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("me", "eve", 1.0, None),
-                WotFollow::new("me", "registrar", 0.5, None)
+                WotFollow::new_unchecked("me", "eve", 1.0, None),
+                WotFollow::new_unchecked("me", "registrar", 0.5, None)
             ]
         });
 
@@ -1,6 +1,6 @@
 use core::panic;
-use std::{fmt, collections::HashSet};
-use crate::prediction::{graph::WotGraph, node::{WotNode, WotFollow}};
+use std::collections::HashSet;
+use crate::{prediction::{graph::WotGraph, node::{WotNode, WotFollow}}, public_key::PublicKey};
 
 
 /**
@@ -12,22 +12,20 @@ use crate::prediction::{graph::WotGraph, node::{WotNode, WotFollow}};
 
 struct DfsResult<'a> {
     pruned_cycle_follows: HashSet<& 'a WotFollow>,
-    visited: Vec<String>,
-    current: &'a WotNode, 
-    current_path: Vec<&'a WotNode>, 
-    found_paths: Vec<Vec<&'a WotNode>>,
-    end: &'a WotNode
+    /// Nodes on the path from me to `current`. A follow into one of them closes a cycle.
+    on_path: HashSet<PublicKey>,
+    /// Nodes whose follows have all been searched. Entering them again can't reveal a new cycle.
+    finished: HashSet<PublicKey>,
+    current: &'a WotNode,
 }
 
 impl<'a> DfsResult<'a> {
-    pub fn new(start: &'a WotNode, end: &'a WotNode, pruned_follows: HashSet<& 'a WotFollow>) -> Self {
+    pub fn new(start: &'a WotNode) -> Self {
         DfsResult {
-            pruned_cycle_follows: pruned_follows,
-            visited: vec![],
+            pruned_cycle_follows: HashSet::new(),
+            on_path: HashSet::from([start.pubkey.clone()]),
+            finished: HashSet::new(),
             current: start,
-            current_path: vec![start],
-            found_paths: vec![],
-            end
         }
     }
 }
@@ -55,53 +53,44 @@ impl<'a> CyclePruner<'a> {
 
     fn dfs(&mut self, mut result: DfsResult<'a>) -> DfsResult<'a> {
         // DFS traversal https://www.geeksforgeeks.org/depth-first-search-or-dfs-for-a-graph/
-        result.visited.insert(0, result.current.pubkey.clone());
-        if result.current.pubkey == result.end.pubkey {
-            result.found_paths.push(result.current_path.to_vec());
-        } else {
-            for follow in result.current.follows.iter() {
-                let is_pruned = result.pruned_cycle_follows.contains(&follow);
-                if is_pruned {
-                    continue;
-                };
-
-                let target_node_option = self.graph.get_node(&follow.target_pubkey);
-                if target_node_option.is_none() {
-                    // We don't have any data about the target node. Skip
-                    continue;
-                }
-                let target_node = target_node_option.unwrap();
-                let is_new_cyle = result.current_path.contains(&target_node);
-                if is_new_cyle {
-                    result.pruned_cycle_follows.insert(follow);
-                };
-
-                let has_been_visited = result.visited.contains(&target_node.pubkey);
-                if !has_been_visited {
-                    result.current_path.push(target_node);
-                    let old_current = result.current;
-                    result.current = target_node;
-                    result = self.dfs(result);
-                    result.current = old_current;
-                    result.current_path.pop();
-                }
+        for follow in result.current.follows.iter() {
+            let target_node_option = self.graph.get_node(&follow.target_pubkey);
+            if target_node_option.is_none() {
+                // We don't have any data about the target node. Skip
+                continue;
+            }
+            let target_node = target_node_option.unwrap();
+            if result.on_path.contains(&target_node.pubkey) {
+                result.pruned_cycle_follows.insert(follow);
+                continue;
             };
-        }
-        let visited_index = result.visited.iter().position(|x| *x == result.current.pubkey).unwrap();
-        result.visited.remove(visited_index);
+
+            // Without `finished` every path would be walked, which is exponential in the number of nodes.
+            if !result.finished.contains(&target_node.pubkey) {
+                result.on_path.insert(target_node.pubkey.clone());
+                let old_current = result.current;
+                result.current = target_node;
+                result = self.dfs(result);
+                result.current = old_current;
+                result.on_path.remove(&target_node.pubkey);
+            }
+        };
+        result.finished.insert(result.current.pubkey.clone());
         result
     }
 
+    /**
+     * One search from me over the whole graph. Every node and follow is visited once.
+     * Classes have no follows at this point, see `prune_graph`, so all paths to them are covered.
+     */
     fn search_cycles(&mut self) -> HashSet<&'a WotFollow> {
+        if self.graph.get_classes().is_empty() {
+            // Nothing to predict, so no path needs to be free of cycles.
+            return HashSet::new();
+        };
         let start = self.get_start_node();
-        let classes = self.graph.get_classes();
-        let mut pruned_follows: HashSet<& WotFollow> = HashSet::new();
-        for class in classes.iter() {
-            let result = DfsResult::new(start, class, pruned_follows.clone());
-            let result = self.dfs(result);
-            pruned_follows.extend(result.pruned_cycle_follows.iter());
-        }
-        pruned_follows
+        let result = self.dfs(DfsResult::new(start));
+        result.pruned_cycle_follows
     }
 
     pub fn prune(mut graph: WotGraph, me_pubkey: &str) -> WotGraph {
@@ -123,20 +112,11 @@ impl<'a> CyclePruner<'a> {
 }
 
 
-impl fmt::Display for DfsResult<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let strs: Vec<String> = self.found_paths.iter().map(|path| {
-            let path_str = path.iter().map(|node| node.pubkey.clone()).collect::<Vec<String>>().join(" -> ");
-            path_str
-        }).collect();
-        let result = strs.join("\n");
-        write!(f, "{}", result)
-    }
-}
-
-
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use crate::public_key::PublicKey;
     use crate::prediction::node::{WotNode, WotFollow};
     use super::{WotGraph, CyclePruner};
 
@@ -148,50 +128,50 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com1"),
             follows: vec![]
         });
         nodes.push(WotNode {
-            pubkey: "d2".to_string(),
+            pubkey: PublicKey::new_unchecked("d2"),
             alias: String::from("example.com2"),
             follows: vec![]
         });
 
         nodes.push(WotNode {
-            pubkey: "n2".to_string(),
+            pubkey: PublicKey::new_unchecked("n2"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n2", "d1", 1.0, Some("example.com")),
-                WotFollow::new("n2", "d2", -1.0, Some("example.com")),
-                WotFollow::new("n2", "n3", -1.0, None),
+                WotFollow::new_unchecked("n2", "d1", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("n2", "d2", -1.0, Some("example.com")),
+                WotFollow::new_unchecked("n2", "n3", -1.0, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n1".to_string(),
+            pubkey: PublicKey::new_unchecked("n1"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n1", "d1", -0.5, Some("example.com")),
-                WotFollow::new("n1", "d2", 0.0, Some("example.com")),
-                WotFollow::new("n1", "me", 0.0, None)
+                WotFollow::new_unchecked("n1", "d1", -0.5, Some("example.com")),
+                WotFollow::new_unchecked("n1", "d2", 0.0, Some("example.com")),
+                WotFollow::new_unchecked("n1", "me", 0.0, None)
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n3".to_string(),
+            pubkey: PublicKey::new_unchecked("n3"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n3", "me", -0.5, None),
+                WotFollow::new_unchecked("n3", "me", -0.5, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "me".to_string(),
+            pubkey: PublicKey::new_unchecked("me"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("me", "n1", 1.0, None),
-                WotFollow::new("me", "n2", 0.5, None)
+                WotFollow::new_unchecked("me", "n1", 1.0, None),
+                WotFollow::new_unchecked("me", "n2", 0.5, None)
             ]
         });
 
@@ -204,56 +184,56 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com1"),
             follows: vec![]
         });
         nodes.push(WotNode {
-            pubkey: "d2".to_string(),
+            pubkey: PublicKey::new_unchecked("d2"),
             alias: String::from("example.com2"),
             follows: vec![]
         });
 
         nodes.push(WotNode {
-            pubkey: "n1".to_string(),
+            pubkey: PublicKey::new_unchecked("n1"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n1", "n4", -0.5, None),
+                WotFollow::new_unchecked("n1", "n4", -0.5, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n2".to_string(),
+            pubkey: PublicKey::new_unchecked("n2"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n2", "n3", 1.0, None),
+                WotFollow::new_unchecked("n2", "n3", 1.0, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n3".to_string(),
+            pubkey: PublicKey::new_unchecked("n3"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n3", "d2", -0.5, Some("example.com")),
-                WotFollow::new("n3", "n1", -0.5, None),
+                WotFollow::new_unchecked("n3", "d2", -0.5, Some("example.com")),
+                WotFollow::new_unchecked("n3", "n1", -0.5, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n4".to_string(),
+            pubkey: PublicKey::new_unchecked("n4"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n4", "d1", -0.5, Some("example.com")),
-                WotFollow::new("n4", "n2", -0.5, None),
+                WotFollow::new_unchecked("n4", "d1", -0.5, Some("example.com")),
+                WotFollow::new_unchecked("n4", "n2", -0.5, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "me".to_string(),
+            pubkey: PublicKey::new_unchecked("me"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("me", "n1", 1.0, None),
-                WotFollow::new("me", "n2", 0.5, None)
+                WotFollow::new_unchecked("me", "n1", 1.0, None),
+                WotFollow::new_unchecked("me", "n2", 0.5, None)
             ]
         });
 
@@ -291,4 +271,68 @@ mod tests {
         assert!(cycles.contains(cycle));
     }
 
+    /**
+     * Random follows between me, four lists and two classes. Cycles and self follows included.
+     */
+    fn get_random_graph(rng: &mut StdRng) -> WotGraph {
+        let lists = ["me", "n1", "n2", "n3", "n4"];
+        let mut nodes: Vec<WotNode> = lists.iter().map(|pubkey| {
+            let mut follows: Vec<WotFollow> = lists.iter()
+                .filter(|_| rng.gen_bool(0.3))
+                .map(|target| WotFollow::new_unchecked(pubkey, target, 1.0, None))
+                .collect();
+            for class in ["d1", "d2"] {
+                if rng.gen_bool(0.3) {
+                    follows.push(WotFollow::new_unchecked(pubkey, class, 1.0, Some("example.com")));
+                };
+            };
+            WotNode::new_list_unchecked(pubkey, "", follows)
+        }).collect();
+        nodes.push(WotNode::new_class_unchecked("d1", "example.com1"));
+        nodes.push(WotNode::new_class_unchecked("d2", "example.com2"));
+        WotGraph::new(nodes)
+    }
+
+    /**
+     * The search without the `finished` set. Walks every path from me to every class.
+     */
+    fn find_cycles_by_enumeration(graph: &WotGraph, me_pubkey: &str) -> HashSet<WotFollow> {
+        fn walk(graph: &WotGraph, current: &WotNode, end: &PublicKey, path: &mut Vec<PublicKey>, pruned: &mut HashSet<WotFollow>) {
+            if current.pubkey == *end {
+                return;
+            };
+            for follow in current.follows.iter() {
+                if pruned.contains(follow) {
+                    continue;
+                };
+                let target = match graph.get_node(&follow.target_pubkey) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                if path.contains(&target.pubkey) {
+                    pruned.insert(follow.clone());
+                    continue;
+                };
+                path.push(target.pubkey.clone());
+                walk(graph, target, end, path, pruned);
+                path.pop();
+            };
+        }
+
+        let start = graph.get_node(me_pubkey).unwrap();
+        let mut pruned = HashSet::new();
+        for class in graph.get_classes() {
+            walk(graph, start, &class.pubkey, &mut vec![start.pubkey.clone()], &mut pruned);
+        };
+        pruned
+    }
+
+    #[test]
+    fn find_cycles_like_path_enumeration() {
+        for seed in 0..500 {
+            let graph = get_random_graph(&mut StdRng::seed_from_u64(seed));
+            let cycles: HashSet<WotFollow> = CyclePruner::find_cycles(&graph, "me").into_iter().cloned().collect();
+            assert_eq!(cycles, find_cycles_by_enumeration(&graph, "me"), "Seed {}", seed);
+        };
+    }
 }
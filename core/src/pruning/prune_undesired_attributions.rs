@@ -1,7 +1,8 @@
 use crate::prediction::{graph::WotGraph, node::WotFollow};
 
 /**
- * Prunes all attributions that are not equal to `desired_attribution`.
+ * Prunes all attributions that are not equal to `desired_attribution`
+ * and all follows whose scope doesn't cover `desired_attribution`.
  */
 pub fn prune_undesired_attributions(mut graph: WotGraph, desired_attribution: &str) -> WotGraph {
     for node in graph.nodes.iter_mut() {
@@ -10,7 +11,7 @@ pub fn prune_undesired_attributions(mut graph: WotGraph, desired_attribution: &s
             .clone()
             .into_iter()
             .filter(|follow| match follow.attribution.clone() {
-                None => true,
+                None => follow.is_in_scope(desired_attribution),
                 Some(att) => att == desired_attribution,
             })
             .collect();
@@ -18,3 +19,46 @@ pub fn prune_undesired_attributions(mut graph: WotGraph, desired_attribution: &s
     }
     graph
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::prediction::{graph::WotGraph, node::{WotNode, WotFollow}};
+    use super::prune_undesired_attributions;
+
+    fn get_graph() -> WotGraph {
+        let mut bitcoin_only = WotFollow::new_unchecked("me", "alice", 1.0, None);
+        bitcoin_only.scope = vec!["*.bitcoin".to_string()];
+        WotGraph::new(vec![
+            WotNode::new_class_unchecked("d1", ""),
+            WotNode::new_class_unchecked("d2", ""),
+            WotNode::new_list_unchecked("alice", "", vec![
+                WotFollow::new_unchecked("alice", "d1", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("alice", "d2", 1.0, Some("satoshi.bitcoin")),
+            ]),
+            WotNode::new_list_unchecked("me", "", vec![
+                bitcoin_only,
+                WotFollow::new_unchecked("me", "bob", 1.0, None),
+            ]),
+            WotNode::new_class_unchecked("bob", ""),
+        ])
+    }
+
+    #[test]
+    fn keep_desired_attribution() {
+        let graph = prune_undesired_attributions(get_graph(), "example.com");
+        assert_eq!(graph.get_node("alice").unwrap().follows.len(), 1);
+        assert!(graph.get_node("alice").unwrap().get_follow("d1").is_some());
+    }
+
+    #[test]
+    fn prune_out_of_scope_follows() {
+        let graph = prune_undesired_attributions(get_graph(), "example.com");
+        let me = graph.get_node("me").unwrap();
+        assert!(me.get_follow("alice").is_none());
+        assert!(me.get_follow("bob").is_some());
+
+        let graph = prune_undesired_attributions(get_graph(), "satoshi.bitcoin");
+        assert!(graph.get_node("me").unwrap().get_follow("alice").is_some());
+    }
+}
@@ -1,33 +1,14 @@
 use core::panic;
-use std::{fmt, collections::HashSet};
-use crate::prediction::{graph::WotGraph, node::WotNode};
+use std::collections::{HashMap, HashSet};
+use crate::{prediction::{graph::WotGraph, node::WotNode}, public_key::PublicKey};
 
 
 /**
  * Prunes the graph from useless nodes that would not contribute to final result.
  * For example: Nodes that do not follow anybody.
+ * A node is useful if it can be reached from me and leads to an attribution, or if it is attributed by a useful node.
+ * Both searches visit every node only once. Enumerating all paths instead would be exponential in the number of nodes.
  */
-
-struct DfsResult<'a> {
-    visited: Vec<String>,
-    current: &'a WotNode, 
-    current_path: Vec<&'a WotNode>, 
-    found_paths: Vec<Vec<&'a WotNode>>,
-    end: &'a WotNode
-}
-
-impl<'a> DfsResult<'a> {
-    pub fn new(start: &'a WotNode, end: &'a WotNode) -> Self {
-        DfsResult {
-            visited: vec![],
-            current: start,
-            current_path: vec![start],
-            found_paths: vec![],
-            end
-        }
-    }
-}
-
 pub struct UselessNodePruner<'a> {
     graph: &'a WotGraph,
     me_pubkey: String
@@ -49,56 +30,61 @@ impl<'a> UselessNodePruner<'a> {
         self.graph.get_node(&self.me_pubkey).expect("me_pubkey must be in graph")
     }
 
-    fn dfs(&mut self, mut result: DfsResult<'a>) -> DfsResult<'a> {
-        // DFS traversal https://www.geeksforgeeks.org/depth-first-search-or-dfs-for-a-graph/
-        result.visited.insert(0, result.current.pubkey.clone());
-        if result.current.pubkey == result.end.pubkey {
-            result.found_paths.push(result.current_path.to_vec());
-        } else {
-            for follow in result.current.follows.iter() {
-
+    /**
+     * All nodes that can be reached from me, me included.
+     */
+    fn search_reachable(&self) -> HashSet<&'a WotNode> {
+        let start = self.get_start_node();
+        let mut reachable: HashSet<&WotNode> = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(current) = stack.pop() {
+            for follow in current.follows.iter() {
                 let target_node_option = self.graph.get_node(&follow.target_pubkey);
                 if target_node_option.is_none() {
                     // We don't have any data about the target node. Skip
                     continue;
                 }
                 let target_node = target_node_option.unwrap();
-                let is_new_cyle = result.current_path.contains(&target_node);
-                if is_new_cyle {
-                    continue
-                    // panic!("Graph includes cycle. Prune cycles first before pruning useless nodes.")
-
+                if reachable.insert(target_node) {
+                    stack.push(target_node);
                 };
-
-                let has_been_visited = result.visited.contains(&target_node.pubkey);
-                if !has_been_visited {
-                    result.current_path.push(target_node);
-                    let old_current = result.current;
-                    result.current = target_node;
-                    result = self.dfs(result);
-                    result.current = old_current;
-                    result.current_path.pop();
-                }
             };
-        }
-        let visited_index = result.visited.iter().position(|x| *x == result.current.pubkey).unwrap();
-        result.visited.remove(visited_index);
-        result
+        };
+        reachable
     }
 
     fn search_nodes_in_paths(&mut self) -> HashSet<&'a WotNode> {
-        let start = self.get_start_node();
-        let classes = self.graph.get_classes();
-        let mut found_nodes_in_paths: HashSet<& WotNode> = HashSet::new();
-        for class in classes.iter() {
-            let result = DfsResult::new(start, class);
-            let result = self.dfs(result);
-            for path in result.found_paths {
-                for node in path {
-                    found_nodes_in_paths.insert(node);
-                }
+        let reachable = self.search_reachable();
+
+        // Paths end with an attribution of a reachable node. Following a class as a list doesn't make it a class.
+        let mut followers: HashMap<&PublicKey, Vec<&WotNode>> = HashMap::new();
+        let mut classes: HashSet<&WotNode> = HashSet::new();
+        let mut found_nodes_in_paths: HashSet<&WotNode> = HashSet::new();
+        let mut stack: Vec<&WotNode> = vec![];
+        for node in reachable.iter() {
+            for follow in node.follows.iter() {
+                followers.entry(&follow.target_pubkey).or_default().push(node);
+                if follow.attribution.is_none() {
+                    continue;
+                };
+                if let Some(class) = self.graph.get_node(&follow.target_pubkey) {
+                    classes.insert(class);
+                    if found_nodes_in_paths.insert(node) {
+                        stack.push(node);
+                    };
+                };
             };
-        }
+        };
+
+        // Walk the follows backwards from the attributing nodes, only through reachable nodes.
+        while let Some(current) = stack.pop() {
+            for follower in followers.get(&current.pubkey).into_iter().flatten() {
+                if found_nodes_in_paths.insert(follower) {
+                    stack.push(follower);
+                };
+            };
+        };
+        found_nodes_in_paths.extend(classes);
         found_nodes_in_paths
     }
 
@@ -120,7 +106,7 @@ impl<'a> UselessNodePruner<'a> {
             graph.remove_node(node);
         };
 
-        let useless_node_ids: HashSet<String> = useless_nodes.into_iter().map(|node| node.pubkey).collect();
+        let useless_node_ids: HashSet<PublicKey> = useless_nodes.into_iter().map(|node| node.pubkey).collect();
         for node in graph.nodes.iter_mut() {
             node.follows.retain(|follow| !useless_node_ids.contains(&follow.target_pubkey));
         };
@@ -130,20 +116,11 @@ impl<'a> UselessNodePruner<'a> {
 }
 
 
-impl fmt::Display for DfsResult<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let strs: Vec<String> = self.found_paths.iter().map(|path| {
-            let path_str = path.iter().map(|node| node.pubkey.clone()).collect::<Vec<String>>().join(" -> ");
-            path_str
-        }).collect();
-        let result = strs.join("\n");
-        write!(f, "{}", result)
-    }
-}
-
-
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use crate::public_key::PublicKey;
     use crate::prediction::node::{WotNode, WotFollow};
     use super::{WotGraph, UselessNodePruner};
 
@@ -153,51 +130,51 @@ mod tests {
 
         // Classes
         nodes.push(WotNode {
-            pubkey: "d1".to_string(),
+            pubkey: PublicKey::new_unchecked("d1"),
             alias: String::from("example.com1"),
             follows: vec![]
         });
         nodes.push(WotNode {
-            pubkey: "d2".to_string(),
+            pubkey: PublicKey::new_unchecked("d2"),
             alias: String::from("example.com2"),
             follows: vec![]
         });
 
         nodes.push(WotNode {
-            pubkey: "n1".to_string(),
+            pubkey: PublicKey::new_unchecked("n1"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n1", "d1", -0.5, Some("example.com")),
+                WotFollow::new_unchecked("n1", "d1", -0.5, Some("example.com")),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n2".to_string(),
+            pubkey: PublicKey::new_unchecked("n2"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("n2", "d2", 1.0, Some("example.com")),
-                WotFollow::new("n2", "n3", 1.0, None),
+                WotFollow::new_unchecked("n2", "d2", 1.0, Some("example.com")),
+                WotFollow::new_unchecked("n2", "n3", 1.0, None),
             ]
         });
 
         nodes.push(WotNode {
-            pubkey: "n3".to_string(),
+            pubkey: PublicKey::new_unchecked("n3"),
             alias: "".to_string(),
             follows: vec![]
         });
 
         nodes.push(WotNode {
-            pubkey: "n4".to_string(),
+            pubkey: PublicKey::new_unchecked("n4"),
             alias: "".to_string(),
             follows: vec![]
         });
 
         nodes.push(WotNode {
-            pubkey: "me".to_string(),
+            pubkey: PublicKey::new_unchecked("me"),
             alias: "".to_string(),
             follows: vec![
-                WotFollow::new("me", "n1", 1.0, None),
-                WotFollow::new("me", "n2", 0.5, None)
+                WotFollow::new_unchecked("me", "n1", 1.0, None),
+                WotFollow::new_unchecked("me", "n2", 0.5, None)
             ]
         });
 
@@ -224,4 +201,78 @@ mod tests {
         assert!(graph.get_node("n4").is_none());
         assert_eq!(graph.get_node("n2").unwrap().follows.len(), 1);
     }
+
+    #[test]
+    fn find_class_only_attributed_by_unreachable_node() {
+        // Me follows d3 as a list. The only attribution of d3 comes from n1 which me can't reach.
+        let nodes = vec![
+            WotNode::new_list_unchecked("me", "", vec![WotFollow::new_unchecked("me", "d3", 0.0, None)]),
+            WotNode::new_list_unchecked("n1", "", vec![WotFollow::new_unchecked("n1", "d3", 0.0, Some("example.com"))]),
+            WotNode::new_class_unchecked("d3", ""),
+        ];
+        let graph = WotGraph::new(nodes);
+        let useless_nodes = UselessNodePruner::find(&graph, "me");
+        assert_eq!(useless_nodes.len(), 3);
+    }
+
+    /**
+     * Random acyclic graph. Me and four lists only follow nodes that come after them.
+     * Some lists are attributed and become classes.
+     */
+    fn get_random_graph(rng: &mut StdRng) -> WotGraph {
+        let pubkeys = ["me", "n1", "n2", "n3", "n4", "d1", "d2"];
+        let mut nodes: Vec<WotNode> = pubkeys.iter().enumerate().take(5).map(|(i, pubkey)| {
+            let mut follows = vec![];
+            for target in pubkeys[i + 1..].iter() {
+                if !rng.gen_bool(0.4) {
+                    continue;
+                };
+                let attribution = if target.starts_with('d') || rng.gen_bool(0.2) { Some("example.com") } else { None };
+                follows.push(WotFollow::new_unchecked(pubkey, target, 1.0, attribution));
+            };
+            WotNode::new_list_unchecked(pubkey, "", follows)
+        }).collect();
+        nodes.push(WotNode::new_class_unchecked("d1", "example.com1"));
+        nodes.push(WotNode::new_class_unchecked("d2", "example.com2"));
+        WotGraph::new(nodes)
+    }
+
+    /**
+     * The search before it was made linear. Collects the nodes of every path from me that ends with an attribution.
+     */
+    fn find_useful_by_enumeration(graph: &WotGraph, me_pubkey: &str) -> HashSet<PublicKey> {
+        fn walk(graph: &WotGraph, current: &WotNode, path: &mut Vec<PublicKey>, found: &mut HashSet<PublicKey>) {
+            for follow in current.follows.iter() {
+                let target = match graph.get_node(&follow.target_pubkey) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                if follow.attribution.is_some() {
+                    found.extend(path.iter().cloned());
+                    found.insert(target.pubkey.clone());
+                };
+                if path.contains(&target.pubkey) {
+                    continue;
+                };
+                path.push(target.pubkey.clone());
+                walk(graph, target, path, found);
+                path.pop();
+            };
+        }
+
+        let start = graph.get_node(me_pubkey).unwrap();
+        let mut found = HashSet::new();
+        walk(graph, start, &mut vec![start.pubkey.clone()], &mut found);
+        found
+    }
+
+    #[test]
+    fn find_like_path_enumeration() {
+        for seed in 0..500 {
+            let graph = get_random_graph(&mut StdRng::seed_from_u64(seed));
+            let useless: HashSet<PublicKey> = UselessNodePruner::find(&graph, "me").iter().map(|node| node.pubkey.clone()).collect();
+            let useful: HashSet<PublicKey> = graph.nodes.iter().map(|node| node.pubkey.clone()).filter(|pubkey| !useless.contains(pubkey)).collect();
+            assert_eq!(useful, find_useful_by_enumeration(&graph, "me"), "Seed {}", seed);
+        };
+    }
 }
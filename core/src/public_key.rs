@@ -0,0 +1,171 @@
+use std::{borrow::Borrow, fmt, ops::Deref, str::FromStr};
+use serde::{Deserialize, Serialize};
+
+
+/**
+ * Pkarr public key in its canonical form `pk:<z-base32>`.
+ * Keys with or without `pk:` are accepted as input, everything else is rejected.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PublicKey(String);
+
+impl PublicKey {
+    /**
+     * Parses a z-base32 encoded pkarr public key with or without the `pk:` prefix.
+     */
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let trimmed = input.trim();
+        let z32 = trimmed.strip_prefix("pk:").unwrap_or(trimmed);
+        let parsed: Result<pkarr::PublicKey, _> = z32.try_into();
+        match parsed {
+            Ok(key) => Ok(PublicKey(key.to_uri_string())),
+            Err(_) => Err(format!("\"{}\" is not a valid public key. Expected pk: followed by 52 z-base32 characters.", input)),
+        }
+    }
+
+    /**
+     * Wraps `value` without any validation.
+     * Only meant for synthetic graphs like in tests and simulations where keys are just labels like `me` or `d1`.
+     */
+    pub fn new_unchecked(value: &str) -> Self {
+        PublicKey(value.to_string())
+    }
+
+    /**
+     * Canonical `pk:...` form.
+     */
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /**
+     * Key without the `pk:` prefix.
+     */
+    pub fn to_z32(&self) -> &str {
+        self.0.strip_prefix("pk:").unwrap_or(&self.0)
+    }
+
+    /**
+     * Converts into the pkarr type. Fails for keys created with `new_unchecked`.
+     */
+    pub fn to_pkarr(&self) -> Result<pkarr::PublicKey, String> {
+        self.to_z32().try_into().map_err(|_| format!("\"{}\" is not a valid public key.", self.0))
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PublicKey::parse(s)
+    }
+}
+
+impl TryFrom<&str> for PublicKey {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        PublicKey::parse(value)
+    }
+}
+
+impl TryFrom<String> for PublicKey {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        PublicKey::parse(&value)
+    }
+}
+
+impl From<PublicKey> for String {
+    fn from(value: PublicKey) -> Self {
+        value.0
+    }
+}
+
+impl From<&pkarr::PublicKey> for PublicKey {
+    fn from(value: &pkarr::PublicKey) -> Self {
+        PublicKey(value.to_uri_string())
+    }
+}
+
+impl Deref for PublicKey {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<str> for PublicKey {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for PublicKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for PublicKey {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for PublicKey {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for PublicKey {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::PublicKey;
+
+    const KEY: &str = "pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqso";
+
+    #[test]
+    fn canonicalize() {
+        let with_prefix = PublicKey::parse(KEY).unwrap();
+        let without_prefix = PublicKey::parse(&KEY[3..]).unwrap();
+        assert_eq!(with_prefix, without_prefix);
+        assert_eq!(without_prefix.as_str(), KEY);
+        assert_eq!(without_prefix.to_z32(), &KEY[3..]);
+        assert!(with_prefix.to_pkarr().is_ok());
+    }
+
+    #[test]
+    fn reject_malformed() {
+        assert!(PublicKey::parse("hello").is_err());
+        assert!(PublicKey::parse("pk:").is_err());
+        assert!(PublicKey::parse(&KEY[..KEY.len() - 1]).is_err());
+        assert!(PublicKey::parse("pk:rcwgkobba4yupekhzxz6imtkyy1ph33emqt16fw6q6cnnbhdoqs0").is_err());
+    }
+
+    #[test]
+    fn json() {
+        let key = PublicKey::parse(KEY).unwrap();
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(json, format!("\"{}\"", KEY));
+        let recovered: PublicKey = serde_json::from_str(&format!("\"{}\"", &KEY[3..])).unwrap();
+        assert_eq!(recovered, key);
+        assert!(serde_json::from_str::<PublicKey>("\"hello\"").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+use rand::Rng;
+
+use crate::prediction::{graph::WotGraph, node::{WotNode, WotFollow}};
+use super::generator::{ME, DOMAIN, RIGHT, WRONG};
+
+pub const ATTACKER: &str = "attacker";
+
+/**
+ * Strategy of an attacker who wants `DOMAIN` to resolve to `WRONG`.
+ * Except for the hijack, the attacker starts with a single honest list that follows the attacker's list, for example a friend who got tricked.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attack {
+    /// The attacker introduces `size` fresh keys that follow each other in a ring. Each one attributes `WRONG` and distrusts `RIGHT`.
+    SybilCluster { size: usize },
+    /// The attacker follows me, the friend and every list that attributes `RIGHT` to create cycles.
+    /// The cycle pruning might cut an honest follow instead of the attacker's. The attacker attributes `WRONG`.
+    CycleInjection,
+    /// A chain of `length` keys. Each one follows and attributes the next key and attributes `WRONG`.
+    AttributionChain { length: usize },
+    /// The attacker steals the key of the list I trust most and replaces its attributions with `WRONG`.
+    ListHijack,
+}
+
+impl fmt::Display for Attack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attack::SybilCluster { size } => write!(f, "sybil cluster of {} keys", size),
+            Attack::CycleInjection => write!(f, "cycle injection"),
+            Attack::AttributionChain { length } => write!(f, "attribution chain of {} keys", length),
+            Attack::ListHijack => write!(f, "list hijack"),
+        }
+    }
+}
+
+impl Attack {
+    /**
+     * Every strategy with its default size.
+     */
+    pub fn all() -> Vec<Attack> {
+        vec![
+            Attack::SybilCluster { size: 100 },
+            Attack::CycleInjection,
+            Attack::AttributionChain { length: 10 },
+            Attack::ListHijack,
+        ]
+    }
+
+    /**
+     * Adds the attacker to an honest graph created by `generate_honest_graph`.
+     */
+    pub fn apply(&self, graph: WotGraph, rng: &mut impl Rng) -> WotGraph {
+        let mut nodes = graph.nodes;
+        let honest: Vec<String> = nodes.iter().filter(|node| node.pubkey != ME && !node.follows.is_empty()).map(|node| node.pubkey.to_string()).collect();
+        if honest.is_empty() {
+            return WotGraph::new(nodes);
+        };
+        let friend = honest[rng.gen_range(0..honest.len())].clone();
+        nodes.push(WotNode::new_class_unchecked(WRONG, DOMAIN));
+
+        match self {
+            Attack::SybilCluster { size } => {
+                follow(&mut nodes, &friend, ATTACKER, rng.gen_range(0.2..=1.0));
+                let sybils: Vec<String> = (0..*size).map(|i| format!("sybil{:03}", i)).collect();
+                nodes.push(WotNode::new_list_unchecked(ATTACKER, "", sybils.iter().map(|sybil| WotFollow::new_unchecked(ATTACKER, sybil, 1.0, None)).collect()));
+                for (i, sybil) in sybils.iter().enumerate() {
+                    nodes.push(WotNode::new_list_unchecked(sybil, "", vec![
+                        WotFollow::new_unchecked(sybil, &sybils[(i + 1) % sybils.len()], 1.0, None),
+                        WotFollow::new_unchecked(sybil, WRONG, 1.0, Some(DOMAIN)),
+                        WotFollow::new_unchecked(sybil, RIGHT, -1.0, Some(DOMAIN)),
+                    ]));
+                };
+            },
+            Attack::CycleInjection => {
+                follow(&mut nodes, &friend, ATTACKER, rng.gen_range(0.2..=1.0));
+                let attesters: Vec<String> = nodes.iter().filter(|node| node.get_follow(RIGHT).is_some()).map(|node| node.pubkey.to_string()).collect();
+                let mut follows = vec![
+                    WotFollow::new_unchecked(ATTACKER, ME, 1.0, None),
+                    WotFollow::new_unchecked(ATTACKER, &friend, 1.0, None),
+                    WotFollow::new_unchecked(ATTACKER, WRONG, 1.0, Some(DOMAIN)),
+                ];
+                follows.extend(attesters.iter().filter(|attester| **attester != friend).map(|attester| WotFollow::new_unchecked(ATTACKER, attester, 1.0, None)));
+                nodes.push(WotNode::new_list_unchecked(ATTACKER, "", follows));
+            },
+            Attack::AttributionChain { length } => {
+                let chain: Vec<String> = (0..(*length).max(1)).map(|i| format!("chain{:03}", i)).collect();
+                follow(&mut nodes, &friend, &chain[0], rng.gen_range(0.2..=1.0));
+                for (i, key) in chain.iter().enumerate() {
+                    let mut follows = vec![WotFollow::new_unchecked(key, WRONG, 1.0, Some(DOMAIN))];
+                    if let Some(next) = chain.get(i + 1) {
+                        follows.push(WotFollow::new_unchecked(key, next, 1.0, None));
+                        follows.push(WotFollow::new_unchecked(key, next, 1.0, Some(DOMAIN)));
+                    };
+                    nodes.push(WotNode::new_list_unchecked(key, "", follows));
+                };
+            },
+            Attack::ListHijack => {
+                let me = nodes.iter().find(|node| node.pubkey == ME).unwrap();
+                let victim = me.follows.iter()
+                    .filter(|follow| follow.attribution.is_none())
+                    .max_by(|a, b| a.weight.total_cmp(&b.weight))
+                    .map(|follow| follow.target_pubkey.to_string());
+                if let Some(victim) = victim {
+                    let node = nodes.iter_mut().find(|node| node.pubkey == victim.as_str()).unwrap();
+                    node.follows.retain(|follow| follow.attribution.is_none());
+                    node.follows.push(WotFollow::new_unchecked(&victim, WRONG, 1.0, Some(DOMAIN)));
+                    node.follows.push(WotFollow::new_unchecked(&victim, RIGHT, -1.0, Some(DOMAIN)));
+                };
+            },
+        };
+        WotGraph::new(nodes)
+    }
+}
+
+fn follow(nodes: &mut [WotNode], source: &str, target: &str, weight: f32) {
+    let node = nodes.iter_mut().find(|node| node.pubkey == source).unwrap();
+    node.follows.push(WotFollow::new_unchecked(source, target, weight, None));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use crate::simulation::generator::{generate_honest_graph, HonestGraphConfig, ME, WRONG};
+    use super::Attack;
+
+    #[test]
+    fn apply_attacks() {
+        let config = HonestGraphConfig::default();
+        for attack in Attack::all() {
+            let mut rng = StdRng::seed_from_u64(2);
+            let graph = generate_honest_graph(&config, &mut rng);
+            let attacked = attack.apply(graph, &mut rng);
+            assert!(attacked.validate().is_ok(), "{}", attack);
+            assert!(attacked.get_node(WRONG).is_some());
+            assert!(attacked.get_node(ME).is_some());
+            assert!(!attacked.get_follows_by_target_pubkey(WRONG).is_empty(), "{}", attack);
+        };
+    }
+}
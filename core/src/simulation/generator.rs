@@ -0,0 +1,98 @@
+use rand::Rng;
+
+use crate::prediction::{graph::WotGraph, node::{WotNode, WotFollow}};
+
+pub const ME: &str = "me";
+pub const DOMAIN: &str = "example.com";
+/// Key the honest lists attribute to `DOMAIN`.
+pub const RIGHT: &str = "right";
+/// Key the attacker wants `DOMAIN` to resolve to.
+pub const WRONG: &str = "wrong";
+
+/**
+ * Shape of a synthetic honest web of trust.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct HonestGraphConfig {
+    /// Number of lists besides me.
+    pub lists: usize,
+    /// Number of lists every list follows.
+    pub follows_per_list: usize,
+    /// Probability that a follow goes to a random list instead of a neighbour. Small values give a small-world graph.
+    pub rewire_probability: f64,
+    /// Number of lists that attribute `RIGHT` to `DOMAIN`.
+    pub attesters: usize,
+}
+
+impl Default for HonestGraphConfig {
+    fn default() -> Self {
+        HonestGraphConfig { lists: 20, follows_per_list: 3, rewire_probability: 0.2, attesters: 3 }
+    }
+}
+
+/**
+ * Name of the i-th honest list.
+ */
+pub fn list_pubkey(i: usize) -> String {
+    format!("list{:03}", i)
+}
+
+/**
+ * Generates a directed Watts-Strogatz graph. Me and the lists sit on a ring and follow their next neighbours.
+ * Every follow is rewired to a random list with `rewire_probability`.
+ * Follow weights are random between 0.2 and 1.
+ */
+pub fn generate_honest_graph(config: &HonestGraphConfig, rng: &mut impl Rng) -> WotGraph {
+    let mut pubkeys = vec![ME.to_string()];
+    pubkeys.extend((0..config.lists).map(list_pubkey));
+    let count = pubkeys.len();
+    let follows_per_list = config.follows_per_list.min(count - 1);
+
+    let mut nodes: Vec<WotNode> = pubkeys.iter().enumerate().map(|(i, pubkey)| {
+        let mut targets: Vec<usize> = vec![];
+        for step in 1..=follows_per_list {
+            let mut target = (i + step) % count;
+            if rng.gen_bool(config.rewire_probability) {
+                target = rng.gen_range(0..count);
+            };
+            if target != i && !targets.contains(&target) {
+                targets.push(target);
+            };
+        };
+        let follows = targets.into_iter().map(|target| WotFollow::new_unchecked(pubkey, &pubkeys[target], rng.gen_range(0.2..=1.0), None)).collect();
+        WotNode::new_list_unchecked(pubkey, "", follows)
+    }).collect();
+
+    let mut candidates: Vec<usize> = (1..count).collect();
+    for _ in 0..config.attesters.min(config.lists) {
+        let attester = candidates.remove(rng.gen_range(0..candidates.len()));
+        let weight = rng.gen_range(0.5..=1.0);
+        nodes[attester].follows.push(WotFollow::new_unchecked(&pubkeys[attester], RIGHT, weight, Some(DOMAIN)));
+    };
+
+    nodes.push(WotNode::new_class_unchecked(RIGHT, DOMAIN));
+    WotGraph::new(nodes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use crate::prediction::graph::WotGraph;
+    use super::{generate_honest_graph, HonestGraphConfig, ME, RIGHT};
+
+    #[test]
+    fn honest_graph() {
+        let config = HonestGraphConfig::default();
+        let graph = generate_honest_graph(&config, &mut StdRng::seed_from_u64(1));
+        assert_eq!(graph.nodes.len(), config.lists + 2);
+        assert!(!graph.get_node(ME).unwrap().follows.is_empty());
+        assert_eq!(graph.get_follows_by_target_pubkey(RIGHT).len(), config.attesters);
+        assert!(graph.validate().is_ok());
+
+        // Same seed, same graph.
+        let again = generate_honest_graph(&config, &mut StdRng::seed_from_u64(1));
+        let follows = |graph: &WotGraph| -> Vec<String> { graph.nodes.iter().flat_map(|node| node.follows.iter().map(|follow| follow.to_string())).collect() };
+        assert_eq!(follows(&graph), follows(&again));
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{prediction::{graph::WotGraph, predictor::WotPredictor}, pruning::prune::prune_graph};
+use super::{attack::Attack, generator::{generate_honest_graph, HonestGraphConfig, ME, DOMAIN, RIGHT}};
+
+/**
+ * How many graphs to generate and how.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationConfig {
+    pub graph: HonestGraphConfig,
+    pub trials: usize,
+    /// Trial i uses the seed `seed + i`, wrapping around at `u64::MAX`, so every attack is run against the same honest graphs.
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig { graph: HonestGraphConfig::default(), trials: 20, seed: 0 }
+    }
+}
+
+/**
+ * Result of running one attack against many graphs.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    pub attack: Attack,
+    pub trials: usize,
+    /// Trials in which the honest graph didn't resolve to `RIGHT` in the first place. They are not counted as flips.
+    pub baseline_failures: usize,
+    /// Trials in which the attacker changed the best class.
+    pub flips: usize,
+    /// Average probability of `RIGHT` after the attack, over the trials without baseline failure.
+    pub mean_probability: f32,
+}
+
+impl SimulationReport {
+    /**
+     * Share of the trials with a valid baseline that got flipped. 0 to 1.
+     */
+    pub fn flip_rate(&self) -> f32 {
+        let valid = self.trials - self.baseline_failures;
+        if valid == 0 {
+            return 0.0;
+        };
+        self.flips as f32 / valid as f32
+    }
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}/{} flipped ({:.1}%), right key {:.2} on average",
+            self.attack,
+            self.flips,
+            self.trials - self.baseline_failures,
+            self.flip_rate() * 100.0,
+            self.mean_probability
+        )?;
+        if self.baseline_failures > 0 {
+            write!(f, ", {} graphs without baseline", self.baseline_failures)?;
+        };
+        Ok(())
+    }
+}
+
+/**
+ * Runs the full pipeline, `prune_graph` and `WotPredictor`, for `DOMAIN`.
+ * Returns the best class and the probability of `RIGHT`. None if no class is left after pruning.
+ */
+pub fn predict(graph: WotGraph) -> Option<(String, f32)> {
+    let graph = prune_graph(graph, ME, DOMAIN);
+    if graph.get_classes().is_empty() {
+        return None;
+    };
+    let predictor: WotPredictor = graph.into();
+    let prediction = predictor.predict();
+    let best = prediction.get_best_class()?.pubkey.clone();
+    let right = prediction.classes.iter().find(|class| class.pubkey == RIGHT).map(|class| class.probability).unwrap_or(0.0);
+    Some((best, right))
+}
+
+/**
+ * Runs `attack` against `config.trials` generated honest graphs and counts how often it changes the result.
+ */
+pub fn simulate(attack: &Attack, config: &SimulationConfig) -> SimulationReport {
+    let mut report = SimulationReport { attack: *attack, trials: config.trials, baseline_failures: 0, flips: 0, mean_probability: 0.0 };
+    let mut probability_sum = 0.0;
+    for trial in 0..config.trials {
+        let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(trial as u64));
+        let graph = generate_honest_graph(&config.graph, &mut rng);
+        match predict(graph.clone()) {
+            Some((best, _)) if best == RIGHT => {},
+            _ => {
+                report.baseline_failures += 1;
+                continue;
+            },
+        };
+
+        let attacked = attack.apply(graph, &mut rng);
+        match predict(attacked) {
+            Some((best, probability)) => {
+                probability_sum += probability;
+                if best != RIGHT {
+                    report.flips += 1;
+                };
+            },
+            None => report.flips += 1,
+        };
+    };
+
+    let valid = config.trials - report.baseline_failures;
+    if valid > 0 {
+        report.mean_probability = probability_sum / valid as f32;
+    };
+    report
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{attack::Attack, generator::HonestGraphConfig};
+    use super::{simulate, SimulationConfig};
+
+    #[test]
+    fn simulate_attacks() {
+        let config = SimulationConfig { graph: HonestGraphConfig::default(), trials: 3, seed: 7 };
+        for attack in Attack::all() {
+            let report = simulate(&attack, &config);
+            assert_eq!(report.trials, 3);
+            assert!(report.flips + report.baseline_failures <= report.trials);
+            assert!((0.0..=1.0).contains(&report.flip_rate()));
+            assert_eq!(simulate(&attack, &config), report, "Same seed, same report.");
+        };
+    }
+
+    #[test]
+    fn hijack_of_the_only_list_flips() {
+        // Me follows a single list. Whoever controls it decides.
+        let graph = HonestGraphConfig { lists: 1, follows_per_list: 1, rewire_probability: 0.0, attesters: 1 };
+        let config = SimulationConfig { graph, trials: 2, seed: 0 };
+        let report = simulate(&Attack::ListHijack, &config);
+        assert_eq!(report.baseline_failures, 0);
+        assert_eq!(report.flip_rate(), 1.0);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod generator;
+pub mod attack;
+pub mod harness;
\ No newline at end of file
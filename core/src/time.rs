@@ -0,0 +1,79 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/**
+ * Current unix timestamp in seconds.
+ */
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/**
+ * Parses a duration like `30s`, `15m`, `12h` or `7d` into seconds.
+ */
+pub fn parse_duration(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid duration {}. Use for example 30s, 15m, 12h or 7d.", value);
+    if value.is_empty() {
+        return Err("Empty duration.".to_string());
+    };
+    let (number, multiplier) = if let Some(number) = value.strip_suffix('s') {
+        (number, 1)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60)
+    } else if let Some(number) = value.strip_suffix('h') {
+        (number, 60 * 60)
+    } else if let Some(number) = value.strip_suffix('d') {
+        (number, 24 * 60 * 60)
+    } else {
+        return Err(invalid());
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    number.checked_mul(multiplier).ok_or_else(|| format!("Duration {} is too long.", value))
+}
+
+/**
+ * Formats seconds in the largest unit that fits, for example `3d` or `45m`. The inverse of `parse_duration` but rounded down.
+ */
+pub fn format_duration(secs: u64) -> String {
+    if secs >= 24 * 60 * 60 {
+        format!("{}d", secs / (24 * 60 * 60))
+    } else if secs >= 60 * 60 {
+        format!("{}h", secs / (60 * 60))
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_duration, format_duration};
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30s").unwrap(), 30);
+        assert_eq!(parse_duration("15m").unwrap(), 15 * 60);
+        assert_eq!(parse_duration("7d").unwrap(), 7 * 24 * 60 * 60);
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("xd").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn invalid_durations_dont_panic() {
+        assert!(parse_duration("5é").is_err());
+        assert!(parse_duration("é").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+        assert_eq!(parse_duration("18446744073709551615s").unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format_duration(30), "30s");
+        assert_eq!(format_duration(15 * 60 + 10), "15m");
+        assert_eq!(format_duration(7 * 24 * 60 * 60), "7d");
+    }
+}
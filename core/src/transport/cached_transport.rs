@@ -0,0 +1,138 @@
+use pkarr::{PublicKey, SignedPacket};
+
+use crate::config_directory::dirs::packet_cache_directory::PacketCacheDirectory;
+
+use super::pkarr_transport::PkarrTransport;
+
+/**
+ * Serves packets from the local packet cache while their records are within TTL.
+ * Everything else goes to the inner transport and the result is cached.
+ */
+pub struct CachedTransport {
+    inner: Box<dyn PkarrTransport>,
+    pub cache: PacketCacheDirectory,
+}
+
+impl CachedTransport {
+    pub fn new(inner: Box<dyn PkarrTransport>, cache: PacketCacheDirectory) -> Self {
+        CachedTransport { inner, cache }
+    }
+}
+
+impl PkarrTransport for CachedTransport {
+    /**
+     * The cache is only a shortcut. Failing to write it doesn't fail the publish.
+     */
+    fn publish(&self, packet: &SignedPacket) -> Result<(), String> {
+        self.inner.publish(packet)?;
+        let _ = self.cache.write_packet(packet);
+        Ok(())
+    }
+
+    /**
+     * Falls back to a stale cached packet if the network fails or does not know the key anymore.
+     * Failing to write the cache doesn't fail the resolve.
+     */
+    fn resolve_most_recent(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        if let Ok(Some(fresh)) = self.cache.read_fresh_packet(pubkey) {
+            return Ok(Some(fresh));
+        };
+
+        let resolved = self.inner.resolve_most_recent(pubkey);
+        if let Ok(Some(packet)) = &resolved {
+            let _ = self.cache.write_packet(packet);
+        };
+        // Answer with the most recent packet we have ever seen.
+        match (self.cache.read_packet(pubkey).ok().flatten(), resolved) {
+            (Some(cached), Ok(Some(resolved))) if resolved.timestamp() > cached.timestamp() => Ok(Some(resolved)),
+            (Some(cached), _) => Ok(Some(cached)),
+            (None, resolved) => resolved,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use pkarr::{dns::{Name, Packet, ResourceRecord, rdata::{RData, A}, CLASS}, Keypair, SignedPacket};
+
+    use crate::{config_directory::dirs::packet_cache_directory::PacketCacheDirectory, transport::{memory_transport::MemoryTransport, pkarr_transport::PkarrTransport}};
+
+    use super::CachedTransport;
+
+    fn create_packet(keypair: &Keypair, ttl: u32, address: u32) -> SignedPacket {
+        let mut packet = Packet::new_reply(0);
+        packet.answers.push(ResourceRecord::new(Name::new("test").unwrap(), CLASS::IN, ttl, RData::A(A { address })));
+        SignedPacket::from_packet(keypair, &packet).unwrap()
+    }
+
+    fn create_transport(name: &str) -> (Arc<MemoryTransport>, CachedTransport) {
+        let cache = PacketCacheDirectory::new(PathBuf::from(format!("/tmp/pknames_cached_transport_{}", name)));
+        let _ = cache.delete(); // Delete so the test can work again even though it failed before.
+        cache.create_if_it_does_not_exist().unwrap();
+        let network = Arc::new(MemoryTransport::new());
+        let transport = CachedTransport::new(Box::new(network.clone()), cache);
+        (network, transport)
+    }
+
+    #[test]
+    fn serve_fresh_from_cache() {
+        let (network, transport) = create_transport("serve_fresh_from_cache");
+        let keypair = Keypair::random();
+        network.publish(&create_packet(&keypair, 60, 1)).unwrap();
+        let first = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        network.publish(&create_packet(&keypair, 60, 2)).unwrap();
+        let second = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(first.timestamp(), second.timestamp());
+        transport.cache.delete().unwrap();
+    }
+
+    #[test]
+    fn refetch_expired() {
+        let (network, transport) = create_transport("refetch_expired");
+        let keypair = Keypair::random();
+        network.publish(&create_packet(&keypair, 0, 1)).unwrap();
+        let first = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let newer = create_packet(&keypair, 0, 2);
+        network.publish(&newer).unwrap();
+        let second = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_ne!(first.timestamp(), second.timestamp());
+        assert_eq!(second.timestamp(), newer.timestamp());
+        transport.cache.delete().unwrap();
+    }
+
+    #[test]
+    fn unwritable_cache() {
+        let path = PathBuf::from("/tmp/pknames_cached_transport_unwritable_cache");
+        let _ = std::fs::remove_dir_all(&path); // Delete so the test can work again even though it failed before.
+        // A file where the cache directory should be, so every write fails.
+        std::fs::write(&path, "").unwrap();
+        let network = Arc::new(MemoryTransport::new());
+        let transport = CachedTransport::new(Box::new(network.clone()), PacketCacheDirectory::new(path.clone()));
+        let keypair = Keypair::random();
+        let packet = create_packet(&keypair, 60, 1);
+
+        transport.publish(&packet).unwrap();
+        assert!(network.resolve_most_recent(&keypair.public_key()).unwrap().is_some());
+        let resolved = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(resolved.timestamp(), packet.timestamp());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn fall_back_to_stale_cache() {
+        let (_network, transport) = create_transport("fall_back_to_stale_cache");
+        let keypair = Keypair::random();
+        let packet = create_packet(&keypair, 0, 1);
+        transport.cache.write_packet(&packet).unwrap();
+
+        let resolved = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(resolved.timestamp(), packet.timestamp());
+        transport.cache.delete().unwrap();
+    }
+}
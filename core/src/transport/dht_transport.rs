@@ -0,0 +1,34 @@
+use pkarr::{PkarrClient, PublicKey, SignedPacket};
+
+use super::pkarr_transport::PkarrTransport;
+
+/**
+ * Publishes and resolves packets on the mainline DHT.
+ */
+pub struct DhtTransport {
+    client: PkarrClient,
+}
+
+impl DhtTransport {
+    pub fn new() -> Self {
+        DhtTransport {
+            client: PkarrClient::new(),
+        }
+    }
+}
+
+impl Default for DhtTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PkarrTransport for DhtTransport {
+    fn publish(&self, packet: &SignedPacket) -> Result<(), String> {
+        self.client.publish(packet).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn resolve_most_recent(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        Ok(self.client.resolve_most_recent(pubkey.clone()))
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fs, path::PathBuf};
+
+use pkarr::{PublicKey, SignedPacket};
+
+use super::pkarr_transport::{packet_from_bytes, packet_to_bytes, PkarrTransport};
+
+/**
+ * Stores published packets as files in a directory. One `<z32 pubkey>.packet` file per key.
+ * Useful to share packets between processes without network, for example in CI.
+ */
+pub struct DiskTransport {
+    pub path: PathBuf,
+}
+
+impl DiskTransport {
+    pub fn new(path: PathBuf) -> Self {
+        DiskTransport { path }
+    }
+
+    fn derive_filename(&self, pubkey: &PublicKey) -> PathBuf {
+        self.path.join(format!("{}.packet", pubkey.to_z32()))
+    }
+
+    fn read_packet(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        let path = self.derive_filename(pubkey);
+        if !path.exists() {
+            return Ok(None);
+        };
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        Ok(Some(packet_from_bytes(pubkey, &bytes)?))
+    }
+}
+
+impl PkarrTransport for DiskTransport {
+    fn publish(&self, packet: &SignedPacket) -> Result<(), String> {
+        if !self.path.exists() {
+            fs::create_dir_all(&self.path).map_err(|e| e.to_string())?;
+        };
+        if let Ok(Some(existing)) = self.read_packet(packet.public_key()) {
+            if existing.more_recent_than(packet) {
+                return Ok(());
+            }
+        };
+        let path = self.derive_filename(packet.public_key());
+        fs::write(path, packet_to_bytes(packet)).map_err(|e| e.to_string())
+    }
+
+    fn resolve_most_recent(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        self.read_packet(pubkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pkarr::{dns::{Name, Packet, ResourceRecord, rdata::{RData, A}, CLASS}, Keypair, SignedPacket};
+
+    use crate::transport::pkarr_transport::PkarrTransport;
+
+    use super::DiskTransport;
+
+    #[test]
+    fn publish_and_resolve() {
+        let path = PathBuf::from("/tmp/pknames_disk_transport_test");
+        let _ = std::fs::remove_dir_all(&path);
+        let transport = DiskTransport::new(path.clone());
+        let keypair = Keypair::random();
+
+        let mut packet = Packet::new_reply(0);
+        packet.answers.push(ResourceRecord::new(Name::new("test").unwrap(), CLASS::IN, 30, RData::A(A { address: 0x7f000001 })));
+        let packet = SignedPacket::from_packet(&keypair, &packet).unwrap();
+        transport.publish(&packet).unwrap();
+
+        let resolved = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(resolved.timestamp(), packet.timestamp());
+        assert_eq!(resolved.packet().answers.len(), 1);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
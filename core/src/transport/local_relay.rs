@@ -0,0 +1,92 @@
+use std::{io::{BufRead, BufReader, Read, Write}, net::{TcpListener, TcpStream}, sync::Arc, thread};
+
+use pkarr::PublicKey;
+
+use super::{memory_transport::MemoryTransport, pkarr_transport::{packet_from_bytes, packet_to_bytes, PkarrTransport}};
+
+/**
+ * Minimal pkarr relay on localhost backed by a `MemoryTransport`.
+ * Stand-in for a real relay so `RelayTransport` can be tested without network.
+ */
+pub struct LocalRelay {
+    pub url: String,
+    pub store: Arc<MemoryTransport>,
+}
+
+impl LocalRelay {
+    /**
+     * Binds a random local port and serves requests in a background thread.
+     */
+    pub fn start() -> Result<Self, String> {
+        let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        let store = Arc::new(MemoryTransport::new());
+
+        let thread_store = store.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = Self::handle(stream, &thread_store);
+            }
+        });
+
+        Ok(LocalRelay {
+            url: format!("http://{}", address),
+            store,
+        })
+    }
+
+    fn handle(stream: TcpStream, store: &MemoryTransport) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let parts: Vec<&str> = request_line.split_whitespace().collect();
+        if parts.len() < 2 {
+            return Self::respond(stream, "400 Bad Request", &[]);
+        };
+        let (method, path) = (parts[0], parts[1]);
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            };
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            };
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        let pubkey: Result<PublicKey, _> = path.trim_start_matches('/').try_into();
+        if pubkey.is_err() {
+            return Self::respond(stream, "400 Bad Request", &[]);
+        };
+        let pubkey = pubkey.unwrap();
+
+        match method {
+            "PUT" => {
+                let stored = packet_from_bytes(&pubkey, &body).and_then(|packet| store.publish(&packet));
+                match stored {
+                    Ok(_) => Self::respond(stream, "200 OK", &[]),
+                    Err(_) => Self::respond(stream, "400 Bad Request", &[]),
+                }
+            }
+            "GET" => match store.resolve_most_recent(&pubkey) {
+                Ok(Some(packet)) => Self::respond(stream, "200 OK", &packet_to_bytes(&packet)),
+                _ => Self::respond(stream, "404 Not Found", &[]),
+            },
+            _ => Self::respond(stream, "405 Method Not Allowed", &[]),
+        }
+    }
+
+    fn respond(mut stream: TcpStream, status: &str, body: &[u8]) -> std::io::Result<()> {
+        write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len())?;
+        stream.write_all(body)?;
+        stream.flush()
+    }
+}
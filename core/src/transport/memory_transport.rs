@@ -0,0 +1,85 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use pkarr::{bytes::Bytes, PublicKey, SignedPacket};
+
+use super::pkarr_transport::{packet_from_bytes, packet_to_bytes, PkarrTransport};
+
+/**
+ * Keeps published packets in memory. Behaves like the DHT but never touches the network.
+ */
+#[derive(Default)]
+pub struct MemoryTransport {
+    packets: Mutex<HashMap<String, Bytes>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        MemoryTransport::default()
+    }
+}
+
+impl PkarrTransport for MemoryTransport {
+    fn publish(&self, packet: &SignedPacket) -> Result<(), String> {
+        let key = packet.public_key().to_z32();
+        let mut packets = self.packets.lock().map_err(|e| e.to_string())?;
+        if let Some(existing) = packets.get(&key) {
+            let existing = packet_from_bytes(packet.public_key(), existing)?;
+            if existing.more_recent_than(packet) {
+                return Ok(());
+            }
+        };
+        packets.insert(key, packet_to_bytes(packet));
+        Ok(())
+    }
+
+    fn resolve_most_recent(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        let packets = self.packets.lock().map_err(|e| e.to_string())?;
+        match packets.get(&pubkey.to_z32()) {
+            Some(bytes) => Ok(Some(packet_from_bytes(pubkey, bytes)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pkarr::{dns::{Name, Packet, ResourceRecord, rdata::{RData, TXT}, CLASS}, Keypair, SignedPacket};
+
+    use crate::transport::pkarr_transport::PkarrTransport;
+
+    use super::MemoryTransport;
+
+    fn create_packet(keypair: &Keypair, value: &str) -> SignedPacket {
+        let mut txt = TXT::new();
+        txt.add_string(value).unwrap();
+        let mut packet = Packet::new_reply(0);
+        packet.answers.push(ResourceRecord::new(Name::new("test").unwrap(), CLASS::IN, 30, RData::TXT(txt)));
+        SignedPacket::from_packet(keypair, &packet).unwrap()
+    }
+
+    #[test]
+    fn publish_and_resolve() {
+        let transport = MemoryTransport::new();
+        let keypair = Keypair::random();
+        assert!(transport.resolve_most_recent(&keypair.public_key()).unwrap().is_none());
+
+        let packet = create_packet(&keypair, "hello");
+        transport.publish(&packet).unwrap();
+        let resolved = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(resolved.timestamp(), packet.timestamp());
+    }
+
+    #[test]
+    fn keep_most_recent() {
+        let transport = MemoryTransport::new();
+        let keypair = Keypair::random();
+        let older = create_packet(&keypair, "older");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let newer = create_packet(&keypair, "newer");
+
+        transport.publish(&newer).unwrap();
+        transport.publish(&older).unwrap();
+        let resolved = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(resolved.timestamp(), newer.timestamp());
+    }
+}
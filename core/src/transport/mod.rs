@@ -0,0 +1,8 @@
+pub mod pkarr_transport;
+pub mod dht_transport;
+pub mod memory_transport;
+pub mod disk_transport;
+pub mod relay_transport;
+#[cfg(test)]
+mod local_relay;
+pub mod cached_transport;
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use pkarr::{bytes::Bytes, PublicKey, SignedPacket};
+
+/**
+ * Abstraction over the network that stores and serves signed pkarr packets.
+ * Publish, resolve and list sync go through this trait so they can run against the DHT or a local store.
+ */
+pub trait PkarrTransport {
+    /**
+     * Publishes a signed packet.
+     */
+    fn publish(&self, packet: &SignedPacket) -> Result<(), String>;
+
+    /**
+     * Resolves the most recent signed packet of a public key. Ok(None) if nothing has been published.
+     */
+    fn resolve_most_recent(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String>;
+}
+
+/**
+ * Lets multiple owners share one transport, for example a test and the code under test.
+ */
+impl<T: PkarrTransport + ?Sized> PkarrTransport for Arc<T> {
+    fn publish(&self, packet: &SignedPacket) -> Result<(), String> {
+        self.as_ref().publish(packet)
+    }
+
+    fn resolve_most_recent(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        self.as_ref().resolve_most_recent(pubkey)
+    }
+}
+
+/**
+ * Raw bytes of a signed packet without the public key. Format: `<signature><timestamp><encoded packet>`.
+ */
+pub fn packet_to_bytes(packet: &SignedPacket) -> Bytes {
+    // `SignedPacket::as_relay_request` assumes the inner bytes start with the public key.
+    // That is only true for packets built from parts, not for packets parsed from relay bytes.
+    let raw = Bytes::copy_from_slice(packet.as_ref());
+    if raw.starts_with(packet.public_key().as_bytes()) {
+        raw.slice(32..)
+    } else {
+        raw
+    }
+}
+
+/**
+ * Parses and verifies the bytes produced by `packet_to_bytes`.
+ */
+pub fn packet_from_bytes(pubkey: &PublicKey, bytes: &[u8]) -> Result<SignedPacket, String> {
+    let bytes = Bytes::copy_from_slice(bytes);
+    SignedPacket::from_relay_response(pubkey.clone(), bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use pkarr::{dns::Packet, Keypair, SignedPacket};
+
+    use super::{packet_from_bytes, packet_to_bytes};
+
+    #[test]
+    fn bytes_roundtrip_twice() {
+        let keypair = Keypair::random();
+        let packet = SignedPacket::from_packet(&keypair, &Packet::new_reply(0)).unwrap();
+        let bytes = packet_to_bytes(&packet);
+        let parsed = packet_from_bytes(&keypair.public_key(), &bytes).unwrap();
+        assert_eq!(packet_to_bytes(&parsed), bytes);
+    }
+}
@@ -0,0 +1,158 @@
+use pkarr::{PublicKey, SignedPacket};
+
+use super::pkarr_transport::{packet_from_bytes, packet_to_bytes, PkarrTransport};
+
+/**
+ * Publishes and resolves packets through pkarr HTTP relays instead of the DHT.
+ * Relays serve `PUT` and `GET` of signed packets at `<relay>/<z32 pubkey>`.
+ * https://github.com/Nuhvi/pkarr/blob/main/design/relays.md
+ */
+pub struct RelayTransport {
+    pub relays: Vec<String>,
+    client: reqwest::blocking::Client,
+}
+
+impl RelayTransport {
+    pub fn new(relays: Vec<String>) -> Self {
+        RelayTransport {
+            relays,
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    fn derive_url(relay: &str, pubkey: &PublicKey) -> String {
+        format!("{}/{}", relay.trim_end_matches('/'), pubkey.to_z32())
+    }
+
+    fn relay_put(&self, relay: &str, packet: &SignedPacket) -> Result<(), String> {
+        let url = Self::derive_url(relay, packet.public_key());
+        let response = self
+            .client
+            .put(&url)
+            .body(packet_to_bytes(packet))
+            .send()
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("Relay {} responded with {}.", url, response.status()));
+        };
+        Ok(())
+    }
+
+    fn relay_get(&self, relay: &str, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        let url = Self::derive_url(relay, pubkey);
+        let response = self.client.get(&url).send().map_err(|e| e.to_string())?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        };
+        if !response.status().is_success() {
+            return Err(format!("Relay {} responded with {}.", url, response.status()));
+        };
+        let bytes = response.bytes().map_err(|e| e.to_string())?;
+        Ok(Some(packet_from_bytes(pubkey, &bytes)?))
+    }
+}
+
+impl PkarrTransport for RelayTransport {
+    /**
+     * Publishes to all relays. Succeeds if at least one relay accepted the packet.
+     */
+    fn publish(&self, packet: &SignedPacket) -> Result<(), String> {
+        if self.relays.is_empty() {
+            return Err("No relays configured.".to_string());
+        };
+        let errors: Vec<String> = self
+            .relays
+            .iter()
+            .filter_map(|relay| self.relay_put(relay, packet).err())
+            .collect();
+        if errors.len() == self.relays.len() {
+            return Err(format!("All relays failed. {}", errors.join(" ")));
+        };
+        Ok(())
+    }
+
+    /**
+     * Asks all relays and returns the most recent packet. Fails only if every relay failed.
+     */
+    fn resolve_most_recent(&self, pubkey: &PublicKey) -> Result<Option<SignedPacket>, String> {
+        if self.relays.is_empty() {
+            return Err("No relays configured.".to_string());
+        };
+        let mut most_recent: Option<SignedPacket> = None;
+        let mut errors: Vec<String> = vec![];
+        for relay in self.relays.iter() {
+            match self.relay_get(relay, pubkey) {
+                Ok(Some(packet)) => {
+                    if let Some(current) = &most_recent {
+                        if current.more_recent_than(&packet) {
+                            continue;
+                        }
+                    };
+                    most_recent = Some(packet);
+                }
+                Ok(None) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+        if most_recent.is_none() && errors.len() == self.relays.len() {
+            return Err(format!("All relays failed. {}", errors.join(" ")));
+        };
+        Ok(most_recent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pkarr::{dns::{Name, Packet, ResourceRecord, rdata::{RData, TXT}, CLASS}, Keypair, SignedPacket};
+
+    use crate::transport::{local_relay::LocalRelay, pkarr_transport::PkarrTransport};
+
+    use super::RelayTransport;
+
+    fn create_packet(keypair: &Keypair) -> SignedPacket {
+        let mut txt = TXT::new();
+        txt.add_string("hello").unwrap();
+        let mut packet = Packet::new_reply(0);
+        packet.answers.push(ResourceRecord::new(Name::new("test").unwrap(), CLASS::IN, 30, RData::TXT(txt)));
+        SignedPacket::from_packet(keypair, &packet).unwrap()
+    }
+
+    #[test]
+    fn publish_and_resolve() {
+        let relay = LocalRelay::start().unwrap();
+        let transport = RelayTransport::new(vec![relay.url.clone()]);
+        let keypair = Keypair::random();
+        assert!(transport.resolve_most_recent(&keypair.public_key()).unwrap().is_none());
+
+        let packet = create_packet(&keypair);
+        transport.publish(&packet).unwrap();
+        let resolved = transport.resolve_most_recent(&keypair.public_key()).unwrap().unwrap();
+        assert_eq!(resolved.timestamp(), packet.timestamp());
+    }
+
+    #[test]
+    fn one_relay_down() {
+        let relay = LocalRelay::start().unwrap();
+        let transport = RelayTransport::new(vec!["http://127.0.0.1:1".to_string(), relay.url.clone()]);
+        let keypair = Keypair::random();
+        transport.publish(&create_packet(&keypair)).unwrap();
+        assert!(relay.store.resolve_most_recent(&keypair.public_key()).unwrap().is_some());
+        assert!(transport.resolve_most_recent(&keypair.public_key()).unwrap().is_some());
+    }
+
+    #[test]
+    fn all_relays_down() {
+        let transport = RelayTransport::new(vec!["http://127.0.0.1:1".to_string()]);
+        let keypair = Keypair::random();
+        assert!(transport.publish(&create_packet(&keypair)).is_err());
+        assert!(transport.resolve_most_recent(&keypair.public_key()).is_err());
+    }
+
+    #[test]
+    fn no_relays() {
+        let transport = RelayTransport::new(vec![]);
+        let keypair = Keypair::random();
+        assert_eq!(transport.publish(&create_packet(&keypair)), Err("No relays configured.".to_string()));
+        assert_eq!(transport.resolve_most_recent(&keypair.public_key()).unwrap_err(), "No relays configured.");
+    }
+}